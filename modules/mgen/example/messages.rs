@@ -0,0 +1,14 @@
+// Input of scanner tests in `src/generator.rs`.
+
+pub mod shapes {
+    pub struct Point {
+        pub x: i32,
+        pub label: Option<String>,
+    }
+}
+
+pub enum Message {
+    Ping,
+    Move(shapes::Point, Vec<u8>),
+    Rename { name: String },
+}
@@ -0,0 +1,7 @@
+// Input of scanner tests in `src/generator.rs`.
+
+pub struct Pair(pub i32, pub i32);
+
+pub struct Letter {
+    pub value: char,
+}
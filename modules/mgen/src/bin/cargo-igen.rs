@@ -0,0 +1,390 @@
+//! `cargo igen`
+//!
+//! Runs `mgen` on library target of a Cargo package.
+//!
+//!     cargo igen [--manifest-path PATH] [--package NAME] [--release]
+//!                [--profile NAME] [--target TRIPLE] [-- MGEN-OPTIONS...]
+//!
+//! 1. Locates the package with `cargo metadata`.
+//! 2. Builds its dependencies with `cargo build` to get their artifacts.
+//!    `--release`, `--profile` and `--target` are passed to it.
+//! 3. Runs `mgen` with `--extern`/`-L` arguments for the dependencies.
+//!    `igen.toml` at the package root will be used if it exists.
+
+extern crate serde_json;
+
+use std::env;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use std::process::Command;
+use std::process::Stdio;
+use serde_json::Value;
+
+fn main() {
+    match run() {
+        Ok(code) => process::exit(code),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        },
+    }
+}
+
+/// Command line arguments of `cargo igen`.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Args {
+    manifest_path: Option<String>,
+    package_name: Option<String>,
+    /// Passed to `cargo build`.
+    build_args: Vec<String>,
+    /// Passed to `mgen` as is.
+    mgen_args: Vec<String>,
+    help: bool,
+}
+
+fn run() -> Result<i32, String> {
+    let args = parse_args(env::args().skip(1).collect())?;
+    if args.help {
+        println!("cargo igen [--manifest-path PATH] [--package NAME] [--release] [--profile NAME] [--target TRIPLE] [-- MGEN-OPTIONS...]");
+        return Ok(0);
+    }
+
+    let metadata = cargo_metadata(&args.manifest_path)?;
+    let package = find_package(&metadata, &args.manifest_path, &args.package_name)?;
+    let package_id = str_field(package, "id")?;
+    let manifest_dir = PathBuf::from(str_field(package, "manifest_path")?).parent().unwrap().to_path_buf();
+    let lib = find_lib_target(package)?;
+    let crate_name = str_field(lib, "name")?.replace("-", "_");
+    let edition = lib["edition"].as_str().unwrap_or("2015").to_string();
+
+    let artifacts = build_deps(&args.manifest_path, str_field(package, "name")?, &args.build_args)?;
+    // Artifacts are under `<target>/<triple>/<profile>/deps` with
+    // `--target` or `CARGO_BUILD_TARGET`, so the directory is taken
+    // from them instead of guessing.
+    let mut dep_dirs = Vec::<PathBuf>::new();
+    for &(ref id, ref path) in &artifacts {
+        if id == package_id { continue }
+        if let Some(dir) = Path::new(path).parent() {
+            if !dep_dirs.iter().any(|dir1| dir1 == dir) {
+                dep_dirs.push(dir.to_path_buf());
+            }
+        }
+    }
+    let mut externs = Vec::<String>::new();
+    for (name, dep_id) in direct_deps(&metadata, package_id) {
+        for &(ref id, ref path) in &artifacts {
+            if *id == dep_id {
+                externs.push([name.as_str(), "=", path].join(""));
+            }
+        }
+    }
+
+    let mut cmd = Command::new(mgen_path());
+    cmd.arg("--sysroot").arg(sysroot()?);
+    cmd.args(&mgen_args(&crate_name, &edition, &dep_dirs, &externs, find_config(&manifest_dir), &args.mgen_args));
+    cmd.arg(str_field(lib, "src_path")?);
+    let status = cmd.status().map_err(|err| format!("cannot run mgen: {}", err))?;
+    return Ok(status.code().unwrap_or(1));
+}
+
+fn parse_args(mut args: Vec<String>) -> Result<Args, String> {
+    // Cargo passes subcommand name as the first argument.
+    if args.first().map(|s| s == "igen").unwrap_or(false) {
+        args.remove(0);
+    }
+    let mut r = Args::default();
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--manifest-path" => {
+                i += 1;
+                r.manifest_path = Some(args.get(i).ok_or("missing argument for --manifest-path")?.clone());
+            },
+            "-p" | "--package" => {
+                i += 1;
+                r.package_name = Some(args.get(i).ok_or("missing argument for --package")?.clone());
+            },
+            "--release" => {
+                r.build_args.push(args[i].clone());
+            },
+            "--profile" | "--target" => {
+                let name = args[i].clone();
+                i += 1;
+                let value = args.get(i).ok_or(format!("missing argument for {}", name))?.clone();
+                r.build_args.push(name);
+                r.build_args.push(value);
+            },
+            "-h" | "--help" => {
+                r.help = true;
+            },
+            "--" => {
+                r.mgen_args.extend_from_slice(&args[i + 1..]);
+                break;
+            },
+            arg => return Err(format!("unknown argument `{}`", arg)),
+        }
+        i += 1;
+    }
+    return Ok(r);
+}
+
+/// `igen.toml` at the package root.
+fn find_config(manifest_dir: &Path) -> Option<PathBuf> {
+    let path = manifest_dir.join("igen.toml");
+    if path.is_file() {
+        return Some(path);
+    }
+    return None;
+}
+
+/// Arguments of `mgen` except `--sysroot` and the input file.
+///
+/// `--edition` is unstable in `mgen`, so it needs
+/// `-Z unstable-options` and is omitted for the default 2015.
+/// The package configuration is not used if `user_args` has
+/// `--config`.
+fn mgen_args(crate_name: &str, edition: &str, dep_dirs: &[PathBuf], externs: &[String], config_path: Option<PathBuf>, user_args: &[String]) -> Vec<String> {
+    let mut r = Vec::<String>::new();
+    r.push("--crate-name".to_string());
+    r.push(crate_name.to_string());
+    if edition != "2015" {
+        r.push("-Z".to_string());
+        r.push("unstable-options".to_string());
+        r.push("--edition".to_string());
+        r.push(edition.to_string());
+    }
+    for dir in dep_dirs {
+        r.push("-L".to_string());
+        r.push(["dependency=", &dir.to_string_lossy()].join(""));
+    }
+    for ext in externs {
+        r.push("--extern".to_string());
+        r.push(ext.clone());
+    }
+    let has_config = user_args.iter().any(|arg| arg == "--config" || arg.starts_with("--config="));
+    if let Some(path) = config_path {
+        if !has_config {
+            r.push("--config".to_string());
+            r.push(path.to_string_lossy().to_string());
+        }
+    }
+    r.extend_from_slice(user_args);
+    return r;
+}
+
+fn cargo() -> Command {
+    return Command::new(env::var("CARGO").unwrap_or("cargo".to_string()));
+}
+
+/// `mgen` next to this executable, or from `PATH`.
+fn mgen_path() -> PathBuf {
+    let name = ["mgen", env::consts::EXE_SUFFIX].join("");
+    if let Ok(exe) = env::current_exe() {
+        let path = exe.with_file_name(&name);
+        if path.exists() {
+            return path;
+        }
+    }
+    return PathBuf::from(name);
+}
+
+fn sysroot() -> Result<String, String> {
+    let rustc = env::var("RUSTC").unwrap_or("rustc".to_string());
+    let output = Command::new(rustc).arg("--print").arg("sysroot").output()
+        .map_err(|err| format!("cannot run rustc: {}", err))?;
+    return Ok(String::from_utf8_lossy(&output.stdout).trim().to_string());
+}
+
+fn cargo_metadata(manifest_path: &Option<String>) -> Result<Value, String> {
+    let mut cmd = cargo();
+    cmd.arg("metadata").arg("--format-version").arg("1");
+    if let Some(ref path) = *manifest_path {
+        cmd.arg("--manifest-path").arg(path);
+    }
+    let output = cmd.stderr(Stdio::inherit()).output().map_err(|err| format!("cannot run cargo metadata: {}", err))?;
+    if !output.status.success() {
+        return Err("cargo metadata failed".to_string());
+    }
+    return serde_json::from_slice(&output.stdout).map_err(|err| format!("cannot parse cargo metadata: {}", err));
+}
+
+/// Selects package by name, by manifest path, or by current
+/// directory, in that order.
+fn find_package<'a>(metadata: &'a Value, manifest_path: &Option<String>, name: &Option<String>) -> Result<&'a Value, String> {
+    let members = metadata["workspace_members"].as_array().ok_or("missing workspace members")?;
+    let packages = metadata["packages"].as_array().ok_or("missing packages")?;
+    let members = packages.iter().filter(|p| members.contains(&p["id"])).collect::<Vec<&Value>>();
+    if let Some(ref name) = *name {
+        return members.into_iter().find(|p| p["name"] == **name).ok_or(format!("package `{}` is not found", name));
+    }
+    let dir = match *manifest_path {
+        Some(ref path) => Path::new(path).canonicalize().map_err(|err| err.to_string())?.parent().unwrap().to_path_buf(),
+        None => env::current_dir().map_err(|err| err.to_string())?,
+    };
+    // Prefer the deepest package for nested packages.
+    let mut found = None::<&Value>;
+    let mut found_depth = 0;
+    for p in members.iter() {
+        let p_dir = Path::new(p["manifest_path"].as_str().unwrap_or("")).parent().unwrap().to_path_buf();
+        let depth = p_dir.components().count();
+        if dir.starts_with(&p_dir) && depth > found_depth {
+            found = Some(p);
+            found_depth = depth;
+        }
+    }
+    if let Some(p) = found {
+        return Ok(p);
+    }
+    if members.len() == 1 {
+        return Ok(members[0]);
+    }
+    return Err("cannot determine package. Use --package to select one".to_string());
+}
+
+fn find_lib_target(package: &Value) -> Result<&Value, String> {
+    let targets = package["targets"].as_array().ok_or("missing targets")?;
+    for target in targets {
+        let kinds = target["kind"].as_array().map(|ks| ks.clone()).unwrap_or(Vec::new());
+        if kinds.iter().any(|k| k == "lib" || k == "rlib") {
+            return Ok(target);
+        }
+    }
+    return Err(format!("package `{}` has no library target", package["name"]));
+}
+
+/// Extern crate names and package IDs of direct dependencies.
+fn direct_deps(metadata: &Value, package_id: &str) -> Vec<(String, String)> {
+    let mut r = Vec::<(String, String)>::new();
+    let nodes = match metadata["resolve"]["nodes"].as_array() {
+        Some(nodes) => nodes,
+        None => return r,
+    };
+    for node in nodes {
+        if node["id"] != package_id { continue }
+        for dep in node["deps"].as_array().unwrap_or(&Vec::new()) {
+            let name = dep["name"].as_str().unwrap_or("").to_string();
+            let id = dep["pkg"].as_str().unwrap_or("").to_string();
+            r.push((name, id));
+        }
+    }
+    return r;
+}
+
+/// Builds the package's library to get artifacts of all dependencies.
+/// Returns package IDs and library file paths.
+fn build_deps(manifest_path: &Option<String>, package_name: &str, build_args: &[String]) -> Result<Vec<(String, String)>, String> {
+    let mut cmd = cargo();
+    cmd.arg("build").arg("--lib").arg("--message-format=json").arg("--package").arg(package_name);
+    cmd.args(build_args);
+    if let Some(ref path) = *manifest_path {
+        cmd.arg("--manifest-path").arg(path);
+    }
+    let output = cmd.stderr(Stdio::inherit()).output().map_err(|err| format!("cannot run cargo build: {}", err))?;
+    if !output.status.success() {
+        return Err("cargo build failed".to_string());
+    }
+    let mut r = Vec::<(String, String)>::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let message: Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(_) => continue,
+        };
+        if message["reason"] != "compiler-artifact" { continue }
+        let id = message["package_id"].as_str().unwrap_or("").to_string();
+        for filename in message["filenames"].as_array().unwrap_or(&Vec::new()) {
+            let filename = filename.as_str().unwrap_or("");
+            let is_lib = filename.ends_with(".rlib") || filename.ends_with(".so")
+                || filename.ends_with(".dylib") || filename.ends_with(".dll");
+            if is_lib {
+                r.push((id.clone(), filename.to_string()));
+            }
+        }
+    }
+    return Ok(r);
+}
+
+fn str_field<'a>(value: &'a Value, name: &str) -> Result<&'a str, String> {
+    return value[name].as_str().ok_or(format!("missing `{}` in cargo metadata", name));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use serde_json;
+    use serde_json::Value;
+    use super::parse_args;
+    use super::find_config;
+    use super::find_package;
+    use super::direct_deps;
+    use super::mgen_args;
+
+    fn strings(ss: &[&str]) -> Vec<String> {
+        return ss.iter().map(|s| s.to_string()).collect();
+    }
+
+    #[test]
+    fn splits_build_and_mgen_arguments() {
+        let args = parse_args(strings(&["igen", "-p", "proto", "--release", "--target", "wasm32-unknown-unknown", "--", "--check", "--release"])).unwrap();
+        assert_eq!(args.package_name, Some("proto".to_string()));
+        assert_eq!(args.build_args, strings(&["--release", "--target", "wasm32-unknown-unknown"]));
+        assert_eq!(args.mgen_args, strings(&["--check", "--release"]));
+        assert_eq!(parse_args(strings(&["--output", "gen"])), Err("unknown argument `--output`".to_string()));
+        assert_eq!(parse_args(strings(&["--manifest-path"])), Err("missing argument for --manifest-path".to_string()));
+    }
+
+    #[test]
+    fn passes_edition_only_with_unstable_options() {
+        let args = mgen_args("proto", "2015", &[], &[], None, &[]);
+        assert_eq!(args, strings(&["--crate-name", "proto"]));
+        let args = mgen_args("proto", "2018", &[PathBuf::from("/t/debug/deps")], &strings(&["serde=/t/debug/deps/libserde.rlib"]), None, &[]);
+        assert_eq!(args, strings(&[
+            "--crate-name", "proto",
+            "-Z", "unstable-options", "--edition", "2018",
+            "-L", "dependency=/t/debug/deps",
+            "--extern", "serde=/t/debug/deps/libserde.rlib",
+        ]));
+    }
+
+    #[test]
+    fn prefers_configuration_given_by_user() {
+        let config_path = Some(PathBuf::from("/p/igen.toml"));
+        let args = mgen_args("proto", "2015", &[], &[], config_path.clone(), &strings(&["--check"]));
+        assert_eq!(args, strings(&["--crate-name", "proto", "--config", "/p/igen.toml", "--check"]));
+        let args = mgen_args("proto", "2015", &[], &[], config_path.clone(), &strings(&["--config", "other.toml"]));
+        assert_eq!(args, strings(&["--crate-name", "proto", "--config", "other.toml"]));
+        let args = mgen_args("proto", "2015", &[], &[], config_path, &strings(&["--config=other.toml"]));
+        assert_eq!(args, strings(&["--crate-name", "proto", "--config=other.toml"]));
+    }
+
+    #[test]
+    fn finds_configuration_at_package_root() {
+        let dir = env::temp_dir().join("cargo-igen-config-test");
+        let _ = fs::remove_dir_all(&dir);
+        // A directory is not a configuration.
+        fs::create_dir_all(dir.join("igen.toml")).unwrap();
+        assert_eq!(find_config(&dir), None);
+        fs::remove_dir(dir.join("igen.toml")).unwrap();
+        fs::write(dir.join("igen.toml"), "").unwrap();
+        assert_eq!(find_config(&dir), Some(dir.join("igen.toml")));
+    }
+
+    #[test]
+    fn selects_packages_and_their_direct_dependencies() {
+        let metadata: Value = serde_json::from_str(r#"{
+            "workspace_members": ["app 0.1.0", "proto 0.1.0"],
+            "packages": [
+                {"id": "app 0.1.0", "name": "app", "manifest_path": "/w/Cargo.toml"},
+                {"id": "proto 0.1.0", "name": "proto", "manifest_path": "/w/proto/Cargo.toml"},
+                {"id": "serde 1.0.0", "name": "serde", "manifest_path": "/r/serde/Cargo.toml"}],
+            "resolve": {"nodes": [
+                {"id": "proto 0.1.0", "deps": [{"name": "serde", "pkg": "serde 1.0.0"}]}]}}"#).unwrap();
+        let package = find_package(&metadata, &None, &Some("proto".to_string())).unwrap();
+        assert_eq!(package["id"], "proto 0.1.0");
+        assert_eq!(find_package(&metadata, &None, &Some("serde".to_string())).unwrap_err(), "package `serde` is not found");
+        assert_eq!(direct_deps(&metadata, "proto 0.1.0"), vec![("serde".to_string(), "serde 1.0.0".to_string())]);
+        assert_eq!(direct_deps(&metadata, "app 0.1.0"), Vec::<(String, String)>::new());
+    }
+}
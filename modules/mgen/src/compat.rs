@@ -0,0 +1,93 @@
+
+//! `mgen compat` command.
+//!
+//! Compares two versions of a schema and classifies every change
+//! as compatible or breaking. Each version can be a crate root
+//! source file or a schema JSON file produced by `schema` target.
+//! Exits with non-zero if there's any breaking change.
+
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use rustc::session::early_error;
+use rustc::session::config::ErrorOutputType;
+use getopts;
+use serde_json;
+use rustdoc::core;
+use config;
+use driver;
+use error::Error;
+use schema;
+use schema::compat::Report;
+use opts;
+
+pub fn main_args(args: &[String]) -> isize {
+    let mut options = getopts::Options::new();
+    for option in opts() {
+        (option.apply)(&mut options);
+    }
+    options.optopt("", "format", "report format (default: human)", "human|json");
+    let matches = match options.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(err) => {
+            early_error(ErrorOutputType::default(), &err.to_string());
+        }
+    };
+    if matches.opt_present("h") || matches.opt_present("help") {
+        println!("{}", options.usage("mgen compat [options] <old> <new>"));
+        return 0;
+    }
+    let error_format = match driver::error_format(&matches) {
+        Ok(error_format) => error_format,
+        Err(err) => {
+            early_error(ErrorOutputType::default(), &err.to_string());
+        }
+    };
+    let diag = core::new_handler(error_format, None);
+
+    if matches.free.len() != 2 {
+        diag.struct_err("expected two file operands: <old> <new>").emit();
+        return 1;
+    }
+    let format = matches.opt_str("format").unwrap_or("human".to_string());
+    if format != "human" && format != "json" {
+        diag.struct_err(&format!("argument for --format must be `human` or `json` \
+                                  (instead was `{}`)", format)).emit();
+        return 1;
+    }
+    let report = match compare(&matches, Path::new(&matches.free[0]), Path::new(&matches.free[1])) {
+        Ok(report) => report,
+        Err(err) => {
+            diag.struct_err(&err.to_string()).emit();
+            return 1;
+        },
+    };
+    print_report(&report, &format);
+    return if report.is_breaking() { 1 } else { 0 };
+}
+
+pub fn compare(matches: &getopts::Matches, old: &Path, new: &Path) -> Result<Report, Error> {
+    let old = load_schema(matches, old)?;
+    let new = load_schema(matches, new)?;
+    return Ok(new.compare(&old));
+}
+
+pub fn print_report(report: &Report, format: &str) {
+    if format == "json" {
+        println!("{}", serde_json::to_string_pretty(report).unwrap());
+        return;
+    }
+    for change in &report.changes {
+        println!("{}", change);
+    }
+}
+
+/// Loads a schema JSON file, or scans a crate root source file.
+fn load_schema(matches: &getopts::Matches, path: &Path) -> Result<schema::Module, Error> {
+    if path.extension().map(|ext| ext == "json").unwrap_or(false) {
+        let text = fs::read_to_string(path).map_err(|err| Error::Io(path.to_path_buf(), err))?;
+        return serde_json::from_str(&text).map_err(|err| Error::Config(format!("cannot parse `{}`: {}", path.display(), err)));
+    }
+    let input = config::Input { path: PathBuf::from(path), crate_name: None, roots: Vec::new() };
+    return driver::scan(matches, &input);
+}
@@ -0,0 +1,446 @@
+
+//! Project configuration. (`igen.toml`)
+//!
+//! ```toml
+//! [defaults]
+//! naming = "idiomatic"
+//! encoding = "json"
+//! handshake = false
+//!
+//! [[input]]
+//! path = "src/lib.rs"
+//! roots = ["protocol"]
+//!
+//! [[target]]
+//! lang = "swift"
+//! out-dir = "gen/swift"
+//! ```
+//!
+//! Relative paths are resolved against the directory of the
+//! configuration file.
+//!
+//! `[[input]]` can be omitted if the input file is given on
+//! command line. (`cargo igen` does this)
+
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use ::toml;
+use ::serde_json;
+use schema::Module;
+use schema::codegen::GenOptions;
+use schema::naming::Naming;
+
+#[derive(Debug, Clone)]
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub defaults: Defaults,
+    #[serde(default, rename = "input")]
+    pub inputs: Vec<Input>,
+    #[serde(default, rename = "target")]
+    pub targets: Vec<Target>,
+}
+
+#[derive(Debug, Clone)]
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Defaults {
+    #[serde(default = "default_naming")]
+    pub naming: String,
+    #[serde(default = "default_encoding")]
+    pub encoding: String,
+    #[serde(default)]
+    pub handshake: bool,
+}
+
+/// A crate to scan.
+#[derive(Debug, Clone)]
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Input {
+    /// Crate root source file.
+    pub path: PathBuf,
+    #[serde(default)]
+    pub crate_name: Option<String>,
+    /// Module paths to export relative to the crate root.
+    /// (e.g. `protocol::messages`)
+    /// Whole crate will be exported if this is empty.
+    #[serde(default)]
+    pub roots: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Target {
+    pub lang: String,
+    /// Generated code will be printed to stdout if this is missing.
+    #[serde(default)]
+    pub out_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub naming: Option<String>,
+    #[serde(default)]
+    pub encoding: Option<String>,
+    /// Generates version negotiation messages.
+    #[serde(default)]
+    pub handshake: Option<bool>,
+    /// Import path of the root package for Go, or Java package.
+    #[serde(default)]
+    pub package: Option<String>,
+    /// Enums of messages received through channels for AsyncAPI.
+    #[serde(default)]
+    pub inbound: Vec<String>,
+    /// Enums of messages sent through channels for AsyncAPI.
+    #[serde(default)]
+    pub outbound: Vec<String>,
+}
+
+/// Languages which have a backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetLang {
+    Swift,
+    Python,
+    CSharp,
+    /// A package for each module.
+    Go,
+    Java,
+    Dart,
+    /// A header and a source file.
+    C,
+    Cpp,
+    /// JSON Schema of the wire format.
+    JsonSchema,
+    /// Protocol Buffers. A file for each module.
+    Proto,
+    /// AsyncAPI document with channels of `inbound` and
+    /// `outbound` enums.
+    AsyncApi,
+    /// The schema itself in JSON.
+    /// This can be an input of schema comparison later.
+    Schema,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    JSON,
+}
+
+impl Default for Defaults {
+    fn default() -> Defaults {
+        return Defaults { naming: default_naming(), encoding: default_encoding(), handshake: false };
+    }
+}
+fn default_naming() -> String {
+    return "idiomatic".to_string();
+}
+fn default_encoding() -> String {
+    return "json".to_string();
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("cannot read `{}`: {}", path.display(), err))?;
+        let mut config: Config = toml::from_str(&text).map_err(|err| format!("cannot parse `{}`: {}", path.display(), err))?;
+        let base = path.parent().unwrap_or(Path::new("."));
+        for input in &mut config.inputs {
+            input.path = base.join(&input.path);
+        }
+        for target in &mut config.targets {
+            target.out_dir = target.out_dir.as_ref().map(|dir| base.join(dir));
+        }
+        return Ok(config);
+    }
+    /// Configuration for a single input file given on command line.
+    pub fn single(path: PathBuf, out_dir: Option<PathBuf>) -> Config {
+        let input = Input { path: path, crate_name: None, roots: Vec::new() };
+        let target = Target { lang: "swift".to_string(), out_dir: out_dir, naming: None, encoding: None, handshake: None, package: None, inbound: Vec::new(), outbound: Vec::new() };
+        return Config { defaults: Defaults::default(), inputs: vec![input], targets: vec![target] };
+    }
+    /// Replaces path of the only input.
+    /// If there's no input, a new one will be added.
+    pub fn override_input(&mut self, path: PathBuf) -> Result<(), String> {
+        match self.inputs.len() {
+            0 => self.inputs.push(Input { path: path, crate_name: None, roots: Vec::new() }),
+            1 => self.inputs[0].path = path,
+            _ => return Err("cannot override input of configuration with multiple inputs".to_string()),
+        }
+        return Ok(());
+    }
+    pub fn validate(&self) -> Result<(), String> {
+        if self.inputs.len() == 0 {
+            return Err("no input in configuration".to_string());
+        }
+        if self.targets.len() == 0 {
+            return Err("no target in configuration".to_string());
+        }
+        let mut root_names = Vec::<String>::new();
+        for input in &self.inputs {
+            for name in input.root_names() {
+                if root_names.contains(&name) {
+                    return Err(format!("root module `{}` is exported more than once, so output files collide", name));
+                }
+                root_names.push(name);
+            }
+        }
+        parse_naming(&self.defaults.naming)?;
+        parse_encoding(&self.defaults.encoding)?;
+        for target in &self.targets {
+            if target.lang()? == TargetLang::AsyncApi && target.inbound.len() == 0 && target.outbound.len() == 0 {
+                return Err("asyncapi target requires `inbound` or `outbound` enums".to_string());
+            }
+            self.gen_options(target)?;
+            self.encoding(target)?;
+        }
+        return Ok(());
+    }
+    pub fn gen_options(&self, target: &Target) -> Result<GenOptions, String> {
+        let naming = target.naming.as_ref().unwrap_or(&self.defaults.naming);
+        let mut opts = GenOptions::new();
+        opts.naming = parse_naming(naming)?;
+        opts.handshake = target.handshake.unwrap_or(self.defaults.handshake);
+        opts.package = target.package.clone();
+        opts.inbound = target.inbound.clone();
+        opts.outbound = target.outbound.clone();
+        return Ok(opts);
+    }
+    pub fn encoding(&self, target: &Target) -> Result<Encoding, String> {
+        let encoding = target.encoding.as_ref().unwrap_or(&self.defaults.encoding);
+        return parse_encoding(encoding);
+    }
+}
+
+impl Input {
+    /// Names of exported root modules, which name output files.
+    /// The crate root is named after the crate.
+    pub fn root_names(&self) -> Vec<String> {
+        if self.roots.len() == 0 {
+            let stem = self.path.file_stem().map(|stem| stem.to_string_lossy().replace("-", "_"));
+            return vec![self.crate_name.clone().or(stem).unwrap_or(String::new())];
+        }
+        return self.roots.iter().map(|root| root.split("::").last().unwrap().to_string()).collect();
+    }
+}
+
+impl Target {
+    pub fn lang(&self) -> Result<TargetLang, String> {
+        return TargetLang::parse(&self.lang).ok_or(format!("unsupported target language `{}`", self.lang));
+    }
+}
+
+impl TargetLang {
+    pub fn parse(s: &str) -> Option<TargetLang> {
+        return match s {
+            "swift" => Some(TargetLang::Swift),
+            "python" => Some(TargetLang::Python),
+            "csharp" => Some(TargetLang::CSharp),
+            "go" => Some(TargetLang::Go),
+            "java" => Some(TargetLang::Java),
+            "dart" => Some(TargetLang::Dart),
+            "c" => Some(TargetLang::C),
+            "cpp" => Some(TargetLang::Cpp),
+            "json-schema" => Some(TargetLang::JsonSchema),
+            "proto" => Some(TargetLang::Proto),
+            "asyncapi" => Some(TargetLang::AsyncApi),
+            "schema" => Some(TargetLang::Schema),
+            _ => None,
+        };
+    }
+    pub fn name(&self) -> &'static str {
+        return match self {
+            TargetLang::Swift => "swift",
+            TargetLang::Python => "python",
+            TargetLang::CSharp => "csharp",
+            TargetLang::Go => "go",
+            TargetLang::Java => "java",
+            TargetLang::Dart => "dart",
+            TargetLang::C => "c",
+            TargetLang::Cpp => "cpp",
+            TargetLang::JsonSchema => "json-schema",
+            TargetLang::Proto => "proto",
+            TargetLang::AsyncApi => "asyncapi",
+            TargetLang::Schema => "schema",
+        };
+    }
+    /// Generated files with paths relative to the output
+    /// directory. Most languages produce a single file named
+    /// after the root module.
+    pub fn gen_files(&self, kmod: &Module, opts: &GenOptions) -> Vec<(PathBuf, String)> {
+        return match self {
+            TargetLang::Go => kmod.gen_go_files(opts),
+            // File name must match the public class name.
+            TargetLang::Java => vec![(kmod.gen_java_path(opts), kmod.gen_java_code(opts))],
+            TargetLang::C => kmod.gen_c_files(opts),
+            TargetLang::Proto => kmod.gen_proto_files(opts),
+            _ => {
+                let path = PathBuf::from([&kmod.name, ".", self.file_extension()].join(""));
+                vec![(path, self.gen_code(kmod, opts))]
+            },
+        };
+    }
+    /// Code of the root module only for languages which produce
+    /// multiple files.
+    pub fn gen_code(&self, kmod: &Module, opts: &GenOptions) -> String {
+        return match self {
+            TargetLang::Swift => {
+                let mut code = kmod.gen_swift_code(opts);
+                code.push_str("\n\n\n");
+                code.push_str(&kmod.gen_swift_fingerprint());
+                if opts.handshake {
+                    code.push_str("\n\n\n");
+                    code.push_str(&kmod.gen_swift_handshake());
+                }
+                code
+            },
+            TargetLang::Python => {
+                let mut code = kmod.gen_python_prelude();
+                code.push_str("\n\n");
+                code.push_str(&kmod.gen_python_code(opts));
+                code.push_str("\n\n");
+                code.push_str(&kmod.gen_python_fingerprint());
+                if opts.handshake {
+                    code.push_str("\n\n");
+                    code.push_str(&kmod.gen_python_handshake());
+                }
+                code
+            },
+            TargetLang::CSharp => kmod.gen_csharp_code(opts),
+            TargetLang::Go => kmod.gen_go_files(opts).remove(0).1,
+            TargetLang::Java => kmod.gen_java_code(opts),
+            TargetLang::Dart => {
+                let mut code = kmod.gen_dart_prelude();
+                code.push_str("\n");
+                code.push_str(&kmod.gen_dart_code(opts));
+                code.push_str("\n");
+                code.push_str(&kmod.gen_dart_fingerprint());
+                if opts.handshake {
+                    code.push_str("\n");
+                    code.push_str(&kmod.gen_dart_handshake(opts));
+                }
+                code
+            },
+            TargetLang::C => kmod.gen_c_files(opts).remove(0).1,
+            TargetLang::Cpp => kmod.gen_cpp_code(opts),
+            TargetLang::JsonSchema => kmod.gen_json_schema(opts),
+            TargetLang::Proto => kmod.gen_proto_files(opts).remove(0).1,
+            TargetLang::AsyncApi => kmod.gen_asyncapi(opts),
+            TargetLang::Schema => serde_json::to_string_pretty(kmod).unwrap(),
+        };
+    }
+    pub fn file_extension(&self) -> &'static str {
+        return match self {
+            TargetLang::Swift => "swift",
+            TargetLang::Python => "py",
+            TargetLang::CSharp => "cs",
+            TargetLang::Go => "go",
+            TargetLang::Java => "java",
+            TargetLang::Dart => "dart",
+            TargetLang::C => "h",
+            TargetLang::Cpp => "hpp",
+            TargetLang::JsonSchema => "schema.json",
+            TargetLang::Proto => "proto",
+            TargetLang::AsyncApi => "asyncapi.json",
+            TargetLang::Schema => "json",
+        };
+    }
+}
+
+fn parse_naming(s: &str) -> Result<Naming, String> {
+    return Naming::parse(s).ok_or(format!("naming must be `preserve` or `idiomatic` (instead was `{}`)", s));
+}
+fn parse_encoding(s: &str) -> Result<Encoding, String> {
+    return match s {
+        "json" => Ok(Encoding::JSON),
+        _ => Err(format!("unsupported encoding `{}`", s)),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use ::toml;
+    use schema::naming::Naming;
+    use super::Config;
+
+    fn parse(text: &str) -> Result<Config, String> {
+        return toml::from_str(text).map_err(|err| err.to_string());
+    }
+
+    #[test]
+    fn resolves_paths_against_configuration_directory() {
+        let dir = env::temp_dir().join("mgen-config-test");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("igen.toml");
+        fs::write(&path, concat!(
+            "[[input]]\n",
+            "path = \"src/lib.rs\"\n",
+            "roots = [\"protocol\"]\n",
+            "[[target]]\n",
+            "lang = \"swift\"\n",
+            "out-dir = \"gen/swift\"\n")).unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.inputs[0].path, dir.join("src/lib.rs"));
+        assert_eq!(config.targets[0].out_dir, Some(dir.join("gen/swift")));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn takes_target_options_over_defaults() {
+        let config = parse(concat!(
+            "[defaults]\n",
+            "naming = \"preserve\"\n",
+            "[[input]]\n",
+            "path = \"lib.rs\"\n",
+            "[[target]]\n",
+            "lang = \"swift\"\n",
+            "[[target]]\n",
+            "lang = \"python\"\n",
+            "naming = \"idiomatic\"\n")).unwrap();
+        assert_eq!(config.gen_options(&config.targets[0]).unwrap().naming, Naming::Preserve);
+        assert_eq!(config.gen_options(&config.targets[1]).unwrap().naming, Naming::Idiomatic);
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let err = parse(concat!(
+            "[[target]]\n",
+            "lang = \"swift\"\n",
+            "outdir = \"gen\"\n")).unwrap_err();
+        assert!(err.contains("unknown field `outdir`"), "{}", err);
+        assert!(parse("[defaults]\nnameing = \"preserve\"\n").is_err());
+        assert!(parse("[[input]]\npath = \"lib.rs\"\nroot = [\"a\"]\n").is_err());
+        assert!(parse("verbose = true\n").is_err());
+    }
+
+    #[test]
+    fn rejects_inputs_with_the_same_root_name() {
+        let config = parse(concat!(
+            "[[input]]\n",
+            "path = \"a/lib.rs\"\n",
+            "roots = [\"v1::protocol\"]\n",
+            "[[input]]\n",
+            "path = \"b/lib.rs\"\n",
+            "roots = [\"protocol\"]\n",
+            "[[target]]\n",
+            "lang = \"swift\"\n")).unwrap();
+        assert_eq!(config.validate(), Err("root module `protocol` is exported more than once, so output files collide".to_string()));
+        let config = parse(concat!(
+            "[[input]]\n",
+            "path = \"a/lib.rs\"\n",
+            "[[input]]\n",
+            "path = \"b/lib.rs\"\n",
+            "crate-name = \"b\"\n",
+            "[[target]]\n",
+            "lang = \"swift\"\n")).unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn names_crate_roots_after_crates() {
+        let config = Config::single(PathBuf::from("src/my-proto.rs"), None);
+        assert_eq!(config.inputs[0].root_names(), vec!["my_proto".to_string()]);
+    }
+}
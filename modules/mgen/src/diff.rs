@@ -0,0 +1,194 @@
+
+//! Line based unified diff.
+
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Produces a unified diff from `old` to `new`.
+/// Returns an empty string if they're equal.
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    let old_lines = old.lines().collect::<Vec<&str>>();
+    let new_lines = new.lines().collect::<Vec<&str>>();
+    let ops = diff_lines(&old_lines, &new_lines);
+    if ops.iter().all(|&op| op == Op::Equal) {
+        return String::new();
+    }
+    let mut s = String::new();
+    s.push_str(&format!("--- {}\n", old_label));
+    s.push_str(&format!("+++ {}\n", new_label));
+    for (start, end) in hunk_ranges(&ops) {
+        // Line positions at start of the hunk.
+        let mut i = 0;
+        let mut j = 0;
+        for &op in &ops[..start] {
+            if op != Op::Insert { i += 1 }
+            if op != Op::Delete { j += 1 }
+        }
+        let old_count = ops[start..end].iter().filter(|&&op| op != Op::Insert).count();
+        let new_count = ops[start..end].iter().filter(|&&op| op != Op::Delete).count();
+        s.push_str(&format!("@@ -{},{} +{},{} @@\n", hunk_start(i, old_count), old_count, hunk_start(j, new_count), new_count));
+        for &op in &ops[start..end] {
+            match op {
+                Op::Equal => {
+                    s.push_str(" ");
+                    s.push_str(old_lines[i]);
+                    i += 1;
+                    j += 1;
+                },
+                Op::Delete => {
+                    s.push_str("-");
+                    s.push_str(old_lines[i]);
+                    i += 1;
+                },
+                Op::Insert => {
+                    s.push_str("+");
+                    s.push_str(new_lines[j]);
+                    j += 1;
+                },
+            }
+            s.push_str("\n");
+        }
+    }
+    return s;
+}
+
+/// Unified diff uses 1-based line numbers, and 0 for empty ranges.
+fn hunk_start(i: usize, count: usize) -> usize {
+    return if count == 0 { i } else { i + 1 };
+}
+
+/// Edit script by longest common subsequence.
+/// Common prefix and suffix are excluded from the table, because
+/// regenerated code usually changes only a few lines.
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let mut prefix = 0;
+    while prefix < old.len() && prefix < new.len() && old[prefix] == new[prefix] {
+        prefix += 1;
+    }
+    let mut suffix = 0;
+    while suffix < old.len() - prefix && suffix < new.len() - prefix
+        && old[old.len() - 1 - suffix] == new[new.len() - 1 - suffix] {
+        suffix += 1;
+    }
+    let mut ops = vec![Op::Equal; prefix];
+    ops.extend(diff_lines_lcs(&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]));
+    ops.extend(vec![Op::Equal; suffix]);
+    return ops;
+}
+
+fn diff_lines_lcs(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let n = old.len();
+    let m = new.len();
+    // `lcs[i][j]` is LCS length of `old[i..]` and `new[j..]`.
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            }
+            else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut ops = Vec::<Op>::new();
+    let mut i = 0;
+    let mut j = 0;
+    while i < n && j < m {
+        if old[i] == new[j] {
+            ops.push(Op::Equal);
+            i += 1;
+            j += 1;
+        }
+        else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            ops.push(Op::Delete);
+            i += 1;
+        }
+        else {
+            ops.push(Op::Insert);
+            j += 1;
+        }
+    }
+    for _ in i..n { ops.push(Op::Delete) }
+    for _ in j..m { ops.push(Op::Insert) }
+    return ops;
+}
+
+/// Ranges of `ops` to print as hunks.
+/// Changes closer than twice of context lines are merged.
+fn hunk_ranges(ops: &[Op]) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::<(usize, usize)>::new();
+    for (k, &op) in ops.iter().enumerate() {
+        if op == Op::Equal { continue }
+        let start = if k > CONTEXT { k - CONTEXT } else { 0 };
+        let end = (k + 1 + CONTEXT).min(ops.len());
+        if let Some(last) = ranges.last_mut() {
+            if start <= last.1 {
+                last.1 = end;
+                continue;
+            }
+        }
+        ranges.push((start, end));
+    }
+    return ranges;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unified_diff;
+
+    #[test]
+    fn produces_nothing_for_equal_text() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb\n", "old", "new"), "");
+    }
+
+    #[test]
+    fn keeps_context_around_changes() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n";
+        let new = "1\n2\n3\n4\nfive\n6\n7\n8\n";
+        assert_eq!(unified_diff(old, new, "old", "new"), [
+            "--- old\n",
+            "+++ new\n",
+            "@@ -2,7 +2,7 @@\n",
+            " 2\n",
+            " 3\n",
+            " 4\n",
+            "-5\n",
+            "+five\n",
+            " 6\n",
+            " 7\n",
+            " 8\n",
+        ].join(""));
+    }
+
+    #[test]
+    fn numbers_empty_ranges_from_zero() {
+        assert_eq!(unified_diff("", "a\n", "old", "new"), "--- old\n+++ new\n@@ -0,0 +1,1 @@\n+a\n");
+        assert_eq!(unified_diff("a\n", "", "old", "new"), "--- old\n+++ new\n@@ -1,1 +0,0 @@\n-a\n");
+    }
+
+    #[test]
+    fn splits_distant_changes_into_hunks() {
+        let old = (0..20).map(|i| i.to_string()).collect::<Vec<String>>().join("\n");
+        let new = (0..20).map(|i| match i {
+            2 => "two".to_string(),
+            17 => "seventeen".to_string(),
+            _ => i.to_string(),
+        }).collect::<Vec<String>>().join("\n");
+        let diff = unified_diff(&old, &new, "old", "new");
+        let hunks = diff.lines().filter(|line| line.starts_with("@@")).collect::<Vec<&str>>();
+        assert_eq!(hunks, vec!["@@ -1,6 +1,6 @@", "@@ -15,6 +15,6 @@"]);
+    }
+
+    #[test]
+    fn aligns_moved_lines_by_longest_common_subsequence() {
+        let diff = unified_diff("a\nb\nc\nd\n", "b\nc\nd\na\n", "old", "new");
+        assert_eq!(diff, "--- old\n+++ new\n@@ -1,4 +1,4 @@\n-a\n b\n c\n d\n+a\n");
+    }
+}
@@ -0,0 +1,198 @@
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::panic;
+use std::path::PathBuf;
+use std::sync::mpsc::channel;
+use errors::ColorConfig;
+use syntax;
+use syntax::edition::Edition;
+use rustc::session::search_paths::SearchPaths;
+use rustc::session::config::{ErrorOutputType, Externs, CodegenOptions};
+use rustc::session::config::build_codegen_options;
+use rustc::session::config::get_cmd_lint_options;
+use rustc_target::spec::TargetTriple;
+use rustc_driver;
+use getopts;
+use rustdoc::core;
+use config;
+use schema;
+use schema::naming::Naming;
+use error::Error;
+use generator::Generated;
+use generator::GeneratedFile;
+
+pub fn error_format(matches: &getopts::Matches) -> Result<ErrorOutputType, Error> {
+    let color = match matches.opt_str("color").as_ref().map(|s| &s[..]) {
+        Some("auto") => ColorConfig::Auto,
+        Some("always") => ColorConfig::Always,
+        Some("never") => ColorConfig::Never,
+        None => ColorConfig::Auto,
+        Some(arg) => {
+            return Err(Error::Config(format!("argument for --color must be `auto`, `always` or `never` \
+                                              (instead was `{}`)", arg)));
+        }
+    };
+    return match matches.opt_str("error-format").as_ref().map(|s| &s[..]) {
+        Some("human") => Ok(ErrorOutputType::HumanReadable(color)),
+        Some("json") => Ok(ErrorOutputType::Json(false)),
+        Some("pretty-json") => Ok(ErrorOutputType::Json(true)),
+        Some("short") => Ok(ErrorOutputType::Short(color)),
+        None => Ok(ErrorOutputType::HumanReadable(color)),
+        Some(arg) => {
+            Err(Error::Config(format!("argument for --error-format must be `human`, `json` or \
+                                       `short` (instead was `{}`)", arg)))
+        }
+    };
+}
+
+/// Scans all inputs in `config` and generates code for all of
+/// its targets. Nothing will be written to file system.
+///
+/// `matches` provides options for the Rust compiler.
+pub fn run(matches: &getopts::Matches, config: &config::Config) -> Result<Generated, Error> {
+    let mut generated = Generated { files: Vec::new(), warnings: Vec::new() };
+    for input in &config.inputs {
+        let kmod = scan(matches, input)?;
+        let symbols = kmod.resolve().map_err(Error::Resolve)?;
+        for ambiguity in symbols.ambiguities() {
+            generated.warnings.push(ambiguity.to_string());
+        }
+        generated.files.extend(generate(&kmod, input, config)?);
+    }
+    return Ok(generated);
+}
+
+/// Compiles an input crate and scans its schema.
+pub fn scan(matches: &getopts::Matches, input: &config::Input) -> Result<schema::Module, Error> {
+    let error_format = error_format(matches)?;
+    let externs = parse_externs(matches).map_err(Error::Config)?;
+    let edition = matches.opt_str("edition").unwrap_or("2015".to_string());
+    let edition = edition.parse().map_err(|_| Error::Config("could not parse edition".to_string()))?;
+    let cg = build_codegen_options(matches, ErrorOutputType::default());
+    return rust_input(input, externs, edition, cg, matches, error_format);
+}
+
+/// Extracts `--extern CRATE=PATH` arguments from `matches` and
+/// returns a map mapping crate names to their paths or else an
+/// error message.
+fn parse_externs(matches: &getopts::Matches) -> Result<Externs, String> {
+    let mut externs = BTreeMap::new();
+    for arg in &matches.opt_strs("extern") {
+        let mut parts = arg.splitn(2, '=');
+        let name = parts.next().ok_or("--extern value must not be empty".to_string())?;
+        let location = parts.next()
+                                 .ok_or("--extern value must be of the format `foo=bar`"
+                                    .to_string())?;
+        let name = name.to_string();
+        externs.entry(name).or_insert_with(BTreeSet::new).insert(location.to_string());
+    }
+    Ok(Externs::new(externs))
+}
+
+/// Interprets the input file as a rust source file, passing it through the
+/// compiler all the way through the analysis passes. The schema is then
+/// scanned from the cleaned AST of the crate.
+fn rust_input(input: &config::Input,
+                    externs: Externs,
+                    edition: Edition,
+                    cg: CodegenOptions,
+                    matches: &getopts::Matches,
+                    error_format: ErrorOutputType) -> Result<schema::Module, Error>
+{
+    // First, parse the crate and extract all relevant information.
+    let mut paths = SearchPaths::new();
+    for s in &matches.opt_strs("L") {
+        paths.add_path(s, ErrorOutputType::default());
+    }
+    // Lets input crates enable `#[igen(...)]` attributes only
+    // for `mgen` with `cfg_attr`.
+    let mut cfgs = matches.opt_strs("cfg");
+    cfgs.push("igen".to_string());
+    let triple = matches.opt_str("target").map(|target| {
+        if target.ends_with(".json") {
+            TargetTriple::TargetPath(PathBuf::from(target))
+        } else {
+            TargetTriple::TargetTriple(target)
+        }
+    });
+    let maybe_sysroot = matches.opt_str("sysroot").map(PathBuf::from);
+    let crate_name = input.crate_name.clone().or(matches.opt_str("crate-name"));
+    let cratefile = input.path.clone();
+
+    info!("starting to run rustc");
+    let display_warnings = matches.opt_present("display-warnings");
+
+    let force_unstable_if_unmarked = matches.opt_strs("Z").iter().any(|x| {
+        *x == "force-unstable-if-unmarked"
+    });
+
+    let (lint_opts, describe_lints, lint_cap) = get_cmd_lint_options(matches, error_format);
+
+    let (tx, rx) = channel();
+
+    // Compiler errors abort the compiler thread. The sender will be
+    // dropped without sending anything in that case.
+    let _ = panic::catch_unwind(panic::AssertUnwindSafe(move || {
+        rustc_driver::monitor(move || syntax::with_globals(move || {
+            use rustc::session::config::Input;
+
+            let (mut krate, _) =
+                core::run_core(paths, cfgs, externs, Input::File(cratefile), triple, maybe_sysroot,
+                               display_warnings, crate_name.clone(),
+                               force_unstable_if_unmarked, edition, cg, error_format,
+                               lint_opts, lint_cap, describe_lints);
+
+            info!("finished with rustc");
+            krate.name = crate_name.unwrap_or(krate.name);
+
+            tx.send(schema::Module::scan(&krate).map_err(Error::Scan)).unwrap();
+        }));
+    }));
+    return match rx.recv() {
+        Ok(r) => r,
+        Err(_) => Err(Error::Compile),
+    };
+}
+
+/// Generates code for all configured targets from a scanned crate.
+fn generate(kmod: &schema::Module, input: &config::Input, config: &config::Config) -> Result<Vec<GeneratedFile>, Error> {
+    let mut kroots = Vec::<&schema::Module>::new();
+    if input.roots.len() == 0 {
+        kroots.push(kmod);
+    }
+    for root in &input.roots {
+        let path = root.split("::").collect::<Vec<&str>>();
+        match kmod.find_module(&path) {
+            Some(kroot) => kroots.push(kroot),
+            None => return Err(Error::RootNotFound(root.clone())),
+        }
+    }
+    let mut files = Vec::<GeneratedFile>::new();
+    for target in &config.targets {
+        let lang = target.lang().map_err(Error::Config)?;
+        let opts = config.gen_options(target).map_err(Error::Config)?;
+        for kroot in &kroots {
+            if opts.naming == Naming::Idiomatic {
+                kroot.check_converted_names().map_err(Error::Resolve)?;
+            }
+            if lang == config::TargetLang::Python || lang == config::TargetLang::Go || lang == config::TargetLang::Dart {
+                kroot.check_variant_classes(opts.naming).map_err(Error::Resolve)?;
+            }
+            if lang == config::TargetLang::Go {
+                kroot.check_go_package(&opts).map_err(Error::Config)?;
+            }
+            if lang == config::TargetLang::AsyncApi {
+                kroot.check_asyncapi_channels(&opts).map_err(Error::Config)?;
+            }
+            if lang == config::TargetLang::Proto {
+                kroot.check_tags().map_err(Error::Resolve)?;
+            }
+            let fingerprint = kroot.fingerprint();
+            for (rel_path, code) in lang.gen_files(kroot, &opts) {
+                let path = target.out_dir.as_ref().map(|dir| dir.join(&rel_path));
+                files.push(GeneratedFile { lang: lang, module: kroot.name.clone(), fingerprint: fingerprint.clone(), path: path, code: code });
+            }
+        }
+    }
+    return Ok(files);
+}
@@ -0,0 +1,76 @@
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use schema::scan::ScanError;
+use schema::resolve::ResolveError;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Invalid configuration or compiler options.
+    Config(String),
+    /// Rust compiler failed.
+    /// Diagnostics have already been printed by the compiler.
+    Compile,
+    /// Input crate uses unsupported constructs.
+    Scan(Vec<ScanError>),
+    /// Schema of input crate is invalid.
+    Resolve(Vec<ResolveError>),
+    /// A root module in configuration does not exist in the crate.
+    RootNotFound(String),
+    Io(PathBuf, io::Error),
+    Git(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Config(ref message) => write!(f, "{}", message),
+            Error::Compile => write!(f, "could not compile input crate"),
+            Error::Scan(ref errs) => {
+                let ss = errs.iter().map(|err| err.to_string()).collect::<Vec<String>>();
+                write!(f, "unsupported Rust constructs:\n{}", ss.join("\n"))
+            },
+            Error::Resolve(ref errs) => {
+                let ss = errs.iter().map(|err| err.to_string()).collect::<Vec<String>>();
+                write!(f, "schema resolution failed:\n{}", ss.join("\n"))
+            },
+            Error::RootNotFound(ref path) => write!(f, "root module `{}` is not found", path),
+            Error::Io(ref path, ref err) => write!(f, "`{}`: {}", path.display(), err),
+            Error::Git(ref message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        return match self {
+            Error::Config(_) => "invalid configuration",
+            Error::Compile => "compilation failure",
+            Error::Scan(_) => "unsupported Rust constructs",
+            Error::Resolve(_) => "schema resolution failure",
+            Error::RootNotFound(_) => "root module not found",
+            Error::Io(_, _) => "I/O error",
+            Error::Git(_) => "git failure",
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use schema::resolve::ResolveError;
+    use super::Error;
+
+    #[test]
+    fn lists_each_resolve_error_under_a_generic_header() {
+        let err = Error::Resolve(vec![
+            ResolveError::Duplicate { path: vec!["p".to_string(), "Point".to_string()] },
+            ResolveError::EmptyPath { referrer: vec!["p".to_string(), "Line".to_string()] },
+        ]);
+        assert_eq!(err.to_string(), concat!(
+            "schema resolution failed:\n",
+            "`p::Point` is defined more than once\n",
+            "`p::Line` refers to a type with an empty path"));
+    }
+}
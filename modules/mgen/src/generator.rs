@@ -0,0 +1,409 @@
+
+//! Library entry point.
+//!
+//! This is for `build.rs` scripts and other tools which want to
+//! run generation without spawning `mgen`.
+//!
+//! ```ignore
+//! let generated = mgen::Generator::new("src/protocol.rs")
+//!     .root("messages")
+//!     .target(TargetLang::Swift, "gen/swift")
+//!     .run()?;
+//! ```
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use getopts;
+use syntax;
+use config::Config;
+use config::Defaults;
+use config::Input;
+use config::Target;
+use config::TargetLang;
+use schema::naming::Naming;
+use driver;
+use diff;
+use error::Error;
+use opts;
+
+#[derive(Debug, Clone)]
+pub struct Generator {
+    input: PathBuf,
+    crate_name: Option<String>,
+    roots: Vec<String>,
+    targets: Vec<Target>,
+    packages: Vec<(TargetLang, String)>,
+    channels: Vec<(TargetLang, Vec<String>, Vec<String>)>,
+    naming: Option<Naming>,
+    handshake: bool,
+    sysroot: Option<PathBuf>,
+    library_paths: Vec<String>,
+    externs: Vec<(String, PathBuf)>,
+    cfgs: Vec<String>,
+    edition: Option<String>,
+}
+
+/// Result of generation.
+#[derive(Debug)]
+pub struct Generated {
+    pub files: Vec<GeneratedFile>,
+    /// Non-fatal issues like ambiguous short names.
+    pub warnings: Vec<String>,
+}
+#[derive(Debug)]
+pub struct GeneratedFile {
+    pub lang: TargetLang,
+    /// Name of root module which produced this file.
+    pub module: String,
+    /// Fingerprint of the root module schema.
+    pub fingerprint: String,
+    /// `None` if the target has no output directory.
+    pub path: Option<PathBuf>,
+    pub code: String,
+}
+
+impl Generator {
+    /// `input` is crate root source file.
+    pub fn new<P: Into<PathBuf>>(input: P) -> Generator {
+        return Generator {
+            input: input.into(),
+            crate_name: None,
+            roots: Vec::new(),
+            targets: Vec::new(),
+            packages: Vec::new(),
+            channels: Vec::new(),
+            naming: None,
+            handshake: false,
+            sysroot: None,
+            library_paths: Vec::new(),
+            externs: Vec::new(),
+            cfgs: Vec::new(),
+            edition: None,
+        };
+    }
+    pub fn crate_name(mut self, name: &str) -> Generator {
+        self.crate_name = Some(name.to_string());
+        return self;
+    }
+    /// Adds a module to export. (e.g. `protocol::messages`)
+    /// Whole crate will be exported if no root is added.
+    pub fn root(mut self, path: &str) -> Generator {
+        self.roots.push(path.to_string());
+        return self;
+    }
+    pub fn target<P: Into<PathBuf>>(mut self, lang: TargetLang, out_dir: P) -> Generator {
+        let target = Target { lang: lang.name().to_string(), out_dir: Some(out_dir.into()), naming: None, encoding: None, handshake: None, package: None, inbound: Vec::new(), outbound: Vec::new() };
+        self.targets.push(target);
+        return self;
+    }
+    /// Sets package of targets in `lang`, including targets added
+    /// later. (e.g. import path of the root package for Go)
+    pub fn package(mut self, lang: TargetLang, package: &str) -> Generator {
+        self.packages.push((lang, package.to_string()));
+        return self;
+    }
+    /// Sets channels of targets in `lang`, including targets added
+    /// later. (e.g. AsyncAPI)
+    /// Names are top-level enums of received and sent messages.
+    pub fn channels(mut self, lang: TargetLang, inbound: &[&str], outbound: &[&str]) -> Generator {
+        let inbound = inbound.iter().map(|s| s.to_string()).collect();
+        let outbound = outbound.iter().map(|s| s.to_string()).collect();
+        self.channels.push((lang, inbound, outbound));
+        return self;
+    }
+    pub fn naming(mut self, naming: Naming) -> Generator {
+        self.naming = Some(naming);
+        return self;
+    }
+    /// Generates version negotiation messages.
+    pub fn handshake(mut self, handshake: bool) -> Generator {
+        self.handshake = handshake;
+        return self;
+    }
+    /// Defaults to `rustc --print sysroot`.
+    pub fn sysroot<P: Into<PathBuf>>(mut self, path: P) -> Generator {
+        self.sysroot = Some(path.into());
+        return self;
+    }
+    /// Same with `-L`. (e.g. `dependency=target/debug/deps`)
+    pub fn library_path(mut self, path: &str) -> Generator {
+        self.library_paths.push(path.to_string());
+        return self;
+    }
+    pub fn extern_crate<P: Into<PathBuf>>(mut self, name: &str, path: P) -> Generator {
+        self.externs.push((name.to_string(), path.into()));
+        return self;
+    }
+    pub fn cfg(mut self, cfg: &str) -> Generator {
+        self.cfgs.push(cfg.to_string());
+        return self;
+    }
+    pub fn edition(mut self, edition: &str) -> Generator {
+        self.edition = Some(edition.to_string());
+        return self;
+    }
+
+    /// Generates code in memory.
+    pub fn generate(&self) -> Result<Generated, Error> {
+        let config = self.config();
+        config.validate().map_err(Error::Config)?;
+        let matches = self.matches()?;
+        return syntax::with_globals(|| driver::run(&matches, &config));
+    }
+    /// Generates code and writes it to output directories.
+    pub fn run(&self) -> Result<Generated, Error> {
+        let generated = self.generate()?;
+        generated.write()?;
+        return Ok(generated);
+    }
+
+    fn config(&self) -> Config {
+        let input = Input { path: self.input.clone(), crate_name: self.crate_name.clone(), roots: self.roots.clone() };
+        let mut defaults = Defaults::default();
+        if let Some(naming) = self.naming {
+            defaults.naming = naming.name().to_string();
+        }
+        defaults.handshake = self.handshake;
+        let mut targets = self.targets.clone();
+        for target in &mut targets {
+            for &(lang, ref package) in &self.packages {
+                if target.lang == lang.name() {
+                    target.package = Some(package.clone());
+                }
+            }
+            for &(lang, ref inbound, ref outbound) in &self.channels {
+                if target.lang == lang.name() {
+                    target.inbound = inbound.clone();
+                    target.outbound = outbound.clone();
+                }
+            }
+        }
+        return Config { defaults: defaults, inputs: vec![input], targets: targets };
+    }
+    /// Compiler options in the same form as command line.
+    fn matches(&self) -> Result<getopts::Matches, Error> {
+        let mut args = Vec::<String>::new();
+        let sysroot = match self.sysroot {
+            Some(ref path) => path.to_string_lossy().to_string(),
+            None => default_sysroot()?,
+        };
+        args.push("--sysroot".to_string());
+        args.push(sysroot);
+        for path in &self.library_paths {
+            args.push("-L".to_string());
+            args.push(path.clone());
+        }
+        for &(ref name, ref path) in &self.externs {
+            args.push("--extern".to_string());
+            args.push([name.as_str(), "=", &path.to_string_lossy()].join(""));
+        }
+        for cfg in &self.cfgs {
+            args.push("--cfg".to_string());
+            args.push(cfg.clone());
+        }
+        if let Some(ref edition) = self.edition {
+            args.push("--edition".to_string());
+            args.push(edition.clone());
+        }
+        let mut options = getopts::Options::new();
+        for option in opts() {
+            (option.apply)(&mut options);
+        }
+        return options.parse(&args).map_err(|err| Error::Config(err.to_string()));
+    }
+}
+
+impl Generated {
+    /// Writes files which have a path.
+    /// Files with same content are not touched to prevent
+    /// needless rebuilds in IDEs.
+    /// Returns paths to written files.
+    pub fn write(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut written = Vec::<PathBuf>::new();
+        for file in &self.files {
+            let path = match file.path {
+                Some(ref path) => path,
+                None => continue,
+            };
+            if let Ok(old_code) = fs::read_to_string(path) {
+                if old_code == file.code { continue }
+            }
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).map_err(|err| Error::Io(dir.to_path_buf(), err))?;
+            }
+            fs::write(path, &file.code).map_err(|err| Error::Io(path.clone(), err))?;
+            written.push(path.clone());
+        }
+        return Ok(written);
+    }
+    /// Compares generated code with files at output paths.
+    /// Returns unified diffs of files which are missing or
+    /// different. Empty result means everything is up to date.
+    /// Files without a path are not checked.
+    ///
+    /// Files are compared by bytes like `write`, so a difference
+    /// only in line endings is also reported.
+    pub fn check(&self) -> Vec<String> {
+        let mut diffs = Vec::<String>::new();
+        for file in &self.files {
+            let path = match file.path {
+                Some(ref path) => path,
+                None => continue,
+            };
+            let old_bytes = fs::read(path).unwrap_or(Vec::new());
+            if old_bytes == file.code.as_bytes() { continue }
+            let old_code = String::from_utf8_lossy(&old_bytes);
+            let label = path.display().to_string();
+            let mut d = diff::unified_diff(&old_code, &file.code, &label, &label);
+            if d.len() == 0 {
+                d = ["--- ", &label, "\n+++ ", &label, "\n", "line endings or trailing newline differ\n"].join("");
+            }
+            diffs.push(d);
+        }
+        return diffs;
+    }
+}
+
+fn default_sysroot() -> Result<String, Error> {
+    let rustc = env::var("RUSTC").unwrap_or("rustc".to_string());
+    let output = Command::new(&rustc).arg("--print").arg("sysroot").output()
+        .map_err(|err| Error::Io(PathBuf::from(&rustc), err))?;
+    return Ok(String::from_utf8_lossy(&output.stdout).trim().to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use serde_json;
+    use config::TargetLang;
+    use schema::Module;
+    use schema::Item;
+    use schema::naming::Naming;
+    use error::Error;
+    use super::Generator;
+    use super::Generated;
+    use super::GeneratedFile;
+
+    fn example(name: &str) -> PathBuf {
+        return PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("example").join(name);
+    }
+
+    /// Items of scanned modules are grouped by kinds, so they are
+    /// looked up by names.
+    fn find_item(kmod: &Module, name: &str) -> Item {
+        let kitem = kmod.items.iter().find(|kitem| {
+            return match kitem {
+                Item::Module(ref kmod1) => kmod1.name == name,
+                Item::Enum(ref kenum) => kenum.name == name,
+                Item::Struct(ref kstruct) => kstruct.name == name,
+            };
+        });
+        return kitem.unwrap().clone();
+    }
+
+    fn item(json: &str) -> Item {
+        return serde_json::from_str(json).unwrap();
+    }
+
+    #[test]
+    fn builds_configuration_from_options() {
+        let config = Generator::new("src/protocol.rs")
+            .crate_name("protocol")
+            .root("messages")
+            .naming(Naming::Preserve)
+            .handshake(true)
+            .target(TargetLang::Swift, "gen/swift")
+            .config();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.inputs[0].path, PathBuf::from("src/protocol.rs"));
+        assert_eq!(config.inputs[0].crate_name, Some("protocol".to_string()));
+        assert_eq!(config.inputs[0].roots, vec!["messages".to_string()]);
+        assert_eq!(config.targets[0].lang().unwrap(), TargetLang::Swift);
+        assert_eq!(config.targets[0].out_dir, Some(PathBuf::from("gen/swift")));
+        let opts = config.gen_options(&config.targets[0]).unwrap();
+        assert_eq!(opts.naming, Naming::Preserve);
+        assert!(opts.handshake);
+    }
+
+    #[test]
+    fn applies_packages_to_targets_added_later() {
+        let config = Generator::new("src/protocol.rs")
+            .target(TargetLang::Go, "gen/go")
+            .package(TargetLang::Go, "example.com/gen/protocol")
+            .target(TargetLang::Go, "gen/go2")
+            .target(TargetLang::Swift, "gen/swift")
+            .config();
+        assert_eq!(config.targets[0].package, Some("example.com/gen/protocol".to_string()));
+        assert_eq!(config.targets[1].package, Some("example.com/gen/protocol".to_string()));
+        assert_eq!(config.targets[2].package, None);
+    }
+
+    #[test]
+    fn applies_channels_to_targets_added_later() {
+        let config = Generator::new("src/protocol.rs")
+            .channels(TargetLang::AsyncApi, &["Request"], &["Event"])
+            .target(TargetLang::AsyncApi, "gen/asyncapi")
+            .target(TargetLang::Go, "gen/go")
+            .config();
+        assert_eq!(config.targets[0].inbound, vec!["Request".to_string()]);
+        assert_eq!(config.targets[0].outbound, vec!["Event".to_string()]);
+        assert!(config.targets[1].inbound.is_empty());
+        assert!(config.targets[1].outbound.is_empty());
+    }
+
+    #[test]
+    fn scans_input_crate() {
+        let generated = Generator::new(example("messages.rs")).target(TargetLang::Schema, "unused").generate().unwrap();
+        assert_eq!(generated.files.len(), 1);
+        assert_eq!(generated.files[0].path, Some(PathBuf::from("unused/messages.json")));
+        let kmod: Module = serde_json::from_str(&generated.files[0].code).unwrap();
+        assert_eq!(kmod.name, "messages");
+        let kshapes = match find_item(&kmod, "shapes") {
+            Item::Module(kmod1) => kmod1,
+            _ => panic!("`shapes` is not a module"),
+        };
+        assert_eq!(find_item(&kshapes, "Point"), item(r#"{"Struct": {"name": "Point", "visibility": "Public", "fields": [
+            {"name": "x", "ty": {"Core": "I32"}},
+            {"name": "label", "ty": {"Option": "String"}}]}}"#));
+        assert_eq!(find_item(&kmod, "Message"), item(r#"{"Enum": {"name": "Message", "visibility": "Public", "variants": [
+            {"name": "Ping", "fields": []},
+            {"name": "Move", "fields": [
+                {"name": "", "ty": {"Item": ["messages", "shapes", "Point"]}},
+                {"name": "", "ty": {"Vec": {"Core": "U8"}}}]},
+            {"name": "Rename", "fields": [
+                {"name": "name", "ty": "String"}]}]}}"#));
+    }
+
+    #[test]
+    fn reports_unsupported_constructs() {
+        let err = Generator::new(example("unsupported.rs")).target(TargetLang::Swift, "unused").generate().unwrap_err();
+        let messages = match err {
+            Error::Scan(errs) => errs.iter().map(|err| err.to_string()).collect::<Vec<String>>(),
+            err => panic!("unexpected error: {}", err),
+        };
+        assert_eq!(messages, vec![
+            "`unsupported::Pair`: tuple structs are not supported".to_string(),
+            "`unsupported::Letter::value`: primitive type `char` is not supported".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn writes_only_changed_files() {
+        let dir = env::temp_dir().join("mgen-write-test");
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("swift/p.swift");
+        let generated = |code: &str| {
+            let file = GeneratedFile { lang: TargetLang::Swift, module: "p".to_string(), fingerprint: String::new(), path: Some(path.clone()), code: code.to_string() };
+            let stdout = GeneratedFile { lang: TargetLang::Swift, module: "p".to_string(), fingerprint: String::new(), path: None, code: code.to_string() };
+            return Generated { files: vec![file, stdout], warnings: Vec::new() };
+        };
+        assert_eq!(generated("a").write().unwrap(), vec![path.clone()]);
+        assert_eq!(generated("a").write().unwrap(), Vec::<PathBuf>::new());
+        assert_eq!(generated("b").write().unwrap(), vec![path.clone()]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "b");
+    }
+}
@@ -0,0 +1,172 @@
+
+//! `mgen diff` command.
+//!
+//! Compares the schema at a git revision with the schema in the
+//! working tree. The old version is checked out into a temporary
+//! worktree, so the working tree is not touched.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use std::process::Command;
+use rustc::session::early_error;
+use rustc::session::config::ErrorOutputType;
+use getopts;
+use rustdoc::core;
+use config;
+use driver;
+use error::Error;
+use compat::print_report;
+use schema::compat::Report;
+use opts;
+
+pub fn main_args(args: &[String]) -> isize {
+    let mut options = getopts::Options::new();
+    for option in opts() {
+        (option.apply)(&mut options);
+    }
+    options.optopt("", "format", "report format (default: human)", "human|json");
+    let matches = match options.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(err) => {
+            early_error(ErrorOutputType::default(), &err.to_string());
+        }
+    };
+    if matches.opt_present("h") || matches.opt_present("help") {
+        println!("{}", options.usage("mgen diff [options] <rev> <input>\n    \
+                                      mgen diff [options] --config <igen.toml> <rev>"));
+        return 0;
+    }
+    let error_format = match driver::error_format(&matches) {
+        Ok(error_format) => error_format,
+        Err(err) => {
+            early_error(ErrorOutputType::default(), &err.to_string());
+        }
+    };
+    let diag = core::new_handler(error_format, None);
+
+    let format = matches.opt_str("format").unwrap_or("human".to_string());
+    if format != "human" && format != "json" {
+        diag.struct_err(&format!("argument for --format must be `human` or `json` \
+                                  (instead was `{}`)", format)).emit();
+        return 1;
+    }
+    if matches.free.len() == 0 {
+        diag.struct_err("missing revision operand").emit();
+        return 1;
+    }
+    let rev = &matches.free[0];
+    let input = match matches.opt_str("config") {
+        Some(path) => {
+            let config = match config::Config::load(Path::new(&path)) {
+                Ok(config) => config,
+                Err(err) => {
+                    diag.struct_err(&err).emit();
+                    return 1;
+                },
+            };
+            if config.inputs.len() != 1 || matches.free.len() != 1 {
+                diag.struct_err("configuration must have exactly one input for diff").emit();
+                return 1;
+            }
+            config.inputs[0].clone()
+        },
+        None => {
+            if matches.free.len() != 2 {
+                diag.struct_err("expected two operands: <rev> <input>").emit();
+                return 1;
+            }
+            config::Input { path: PathBuf::from(&matches.free[1]), crate_name: None, roots: Vec::new() }
+        },
+    };
+    let report = match diff(&matches, rev, &input) {
+        Ok(report) => report,
+        Err(err) => {
+            diag.struct_err(&err.to_string()).emit();
+            return 1;
+        },
+    };
+    print_report(&report, &format);
+    return if report.is_breaking() { 1 } else { 0 };
+}
+
+/// Compares schema of `input` at `rev` with current one.
+pub fn diff(matches: &getopts::Matches, rev: &str, input: &config::Input) -> Result<Report, Error> {
+    let old = with_revision(&input.path, rev, |path| {
+        let mut old_input = input.clone();
+        old_input.path = path.to_path_buf();
+        return driver::scan(matches, &old_input);
+    })?;
+    let old = old?;
+    let new = driver::scan(matches, input)?;
+    return Ok(new.compare(&old));
+}
+
+/// Checks out `rev` into a temporary worktree, and calls `f`
+/// with the path of the same file in it. The worktree is removed
+/// after `f` returns.
+fn with_revision<T, F: FnOnce(&Path) -> T>(path: &Path, rev: &str, f: F) -> Result<T, Error> {
+    let path = path.canonicalize().map_err(|err| Error::Io(path.to_path_buf(), err))?;
+    let dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+    let toplevel = PathBuf::from(git(&dir, &["rev-parse", "--show-toplevel"])?.trim());
+    let toplevel = toplevel.canonicalize().map_err(|err| Error::Io(toplevel.clone(), err))?;
+    let relative = path.strip_prefix(&toplevel).map_err(|_| {
+        return Error::Config(format!("`{}` is not in git repository", path.display()));
+    })?.to_path_buf();
+
+    let worktree = env::temp_dir().join(format!("mgen-diff-{}", process::id()));
+    let worktree_str = worktree.to_string_lossy().to_string();
+    git(&toplevel, &["worktree", "add", "--detach", &worktree_str, rev])?;
+    let r = f(&worktree.join(&relative));
+    // Remove the worktree regardless of the result.
+    let _ = git(&toplevel, &["worktree", "remove", "--force", &worktree_str]);
+    let _ = fs::remove_dir_all(&worktree);
+    let _ = git(&toplevel, &["worktree", "prune"]);
+    return Ok(r);
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String, Error> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()
+        .map_err(|err| Error::Io(PathBuf::from("git"), err))?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(Error::Git(format!("git {} failed: {}", args.join(" "), message)));
+    }
+    return Ok(String::from_utf8_lossy(&output.stdout).to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+    use super::git;
+    use super::with_revision;
+
+    #[test]
+    fn reads_files_at_a_revision_without_touching_working_tree() {
+        let dir = env::temp_dir().join("mgen-git-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("src")).unwrap();
+        let path = dir.join("src/lib.rs");
+        git(&dir, &["init", "-q"]).unwrap();
+        fs::write(&path, "pub struct Old;").unwrap();
+        git(&dir, &["add", "."]).unwrap();
+        git(&dir, &["-c", "user.name=mgen", "-c", "user.email=mgen@localhost", "commit", "-q", "-m", "old"]).unwrap();
+        fs::write(&path, "pub struct New;").unwrap();
+
+        let mut worktree = None;
+        let old = with_revision(&path, "HEAD", |path| {
+            worktree = Some(path.to_path_buf());
+            return fs::read_to_string(path).unwrap();
+        }).unwrap();
+        assert_eq!(old, "pub struct Old;");
+        assert!(!worktree.unwrap().exists());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "pub struct New;");
+        assert_eq!(git(&dir, &["worktree", "list"]).unwrap().lines().count(), 1);
+        assert!(with_revision(&path, "no-such-revision", |_| ()).is_err());
+        assert!(with_revision(Path::new("/"), "HEAD", |_| ()).is_err());
+    }
+}
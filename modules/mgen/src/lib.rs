@@ -0,0 +1,321 @@
+// Copyright 2012-2014 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#![feature(rustc_private)]
+#![feature(box_patterns)]
+#![feature(box_syntax)]
+
+extern crate arena;
+extern crate getopts;
+extern crate env_logger;
+extern crate rustc;
+extern crate rustc_data_structures;
+extern crate rustc_codegen_utils;
+extern crate rustc_driver;
+extern crate rustc_resolve;
+extern crate rustc_lint;
+extern crate rustc_metadata;
+extern crate rustc_target;
+extern crate rustc_typeck;
+extern crate serialize;
+extern crate syntax;
+extern crate syntax_pos;
+#[macro_use] extern crate log;
+extern crate rustc_errors as errors;
+extern crate rustdoc;
+extern crate serialize as rustc_serialize; // used by deriving
+extern crate serde;
+#[macro_use] extern crate serde_derive;
+extern crate toml;
+extern crate serde_json;
+
+pub mod schema;
+pub mod config;
+mod compat;
+mod diff;
+mod driver;
+mod error;
+mod generator;
+mod git;
+mod watch;
+
+pub use error::Error;
+pub use generator::Generator;
+pub use generator::Generated;
+pub use generator::GeneratedFile;
+
+use std::env;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use rustc::session::{early_warn, early_error};
+use rustc::session::config::{ErrorOutputType, RustcOptGroup};
+use rustc::session::config::nightly_options;
+pub use rustdoc::core;
+
+pub fn main() {
+    rustc_driver::set_sigpipe_handler();
+    env_logger::init();
+    let code = syntax::with_globals(move || {
+        get_args().map(|args| main_args(&args)).unwrap_or(1)
+    });
+    process::exit(code as i32);
+}
+
+pub fn main_args(args: &[String]) -> isize {
+    if args.len() > 1 && args[1] == "compat" {
+        return compat::main_args(&args[1..]);
+    }
+    if args.len() > 1 && args[1] == "diff" {
+        return git::main_args(&args[1..]);
+    }
+    let mut options = getopts::Options::new();
+    for option in opts() {
+        (option.apply)(&mut options);
+    }
+    let matches = match options.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(err) => {
+            early_error(ErrorOutputType::default(), &err.to_string());
+        }
+    };
+    // Check for unstable options.
+    nightly_options::check_nightly_options(&matches, &opts());
+
+    if matches.opt_present("h") || matches.opt_present("help") {
+        usage("mgen");
+        return 0;
+    } else if matches.opt_present("version") {
+        rustc_driver::version("mgen", &matches);
+        return 0;
+    }
+
+    let error_format = match driver::error_format(&matches) {
+        Ok(error_format) => error_format,
+        Err(err) => {
+            early_error(ErrorOutputType::default(), &err.to_string());
+        }
+    };
+    let diag = core::new_handler(error_format, None);
+
+    if matches.free.len() > 1 {
+        diag.struct_err("too many file operands").emit();
+        return 1;
+    }
+    let mut config = match matches.opt_str("config") {
+        Some(path) => {
+            match config::Config::load(Path::new(&path)) {
+                Ok(config) => config,
+                Err(err) => {
+                    diag.struct_err(&err).emit();
+                    return 1;
+                },
+            }
+        },
+        None => {
+            if matches.free.is_empty() {
+                diag.struct_err("missing file operand").emit();
+                return 1;
+            }
+            let out_dir = matches.opt_str("o").map(PathBuf::from);
+            config::Config::single(PathBuf::from(&matches.free[0]), out_dir)
+        },
+    };
+    // A file operand given with `--config` designates the input crate.
+    // `cargo igen` uses this to feed the package's library target.
+    if matches.opt_present("config") && matches.free.len() == 1 {
+        if let Err(err) = config.override_input(PathBuf::from(&matches.free[0])) {
+            diag.struct_err(&err).emit();
+            return 1;
+        }
+    }
+    if let Some(naming) = matches.opt_str("naming") {
+        for target in &mut config.targets {
+            target.naming = Some(naming.clone());
+        }
+    }
+    if matches.opt_present("handshake") {
+        for target in &mut config.targets {
+            target.handshake = Some(true);
+        }
+    }
+    if let Err(err) = config.validate() {
+        diag.struct_err(&err).emit();
+        return 1;
+    }
+
+    if matches.opt_present("check") && config.targets.iter().any(|target| target.out_dir.is_none()) {
+        diag.struct_err("--check requires an output directory for every target").emit();
+        return 1;
+    }
+    if matches.opt_present("watch") {
+        return watch::watch(&matches, &config, &diag);
+    }
+    let generated = match driver::run(&matches, &config) {
+        Ok(generated) => generated,
+        Err(err) => {
+            diag.struct_err(&err.to_string()).emit();
+            return 1;
+        }
+    };
+    for warning in &generated.warnings {
+        diag.struct_warn(warning).emit();
+    }
+    if matches.opt_present("check") {
+        let diffs = generated.check();
+        for d in &diffs {
+            print!("{}", d);
+        }
+        if diffs.len() > 0 {
+            diag.struct_err("generated code is out of date").emit();
+            return 1;
+        }
+        return 0;
+    }
+    if let Err(err) = generated.write() {
+        diag.struct_err(&err.to_string()).emit();
+        return 1;
+    }
+    for file in &generated.files {
+        if file.path.is_none() {
+            println!("{}", file.code);
+        }
+    }
+    return 0;
+}
+fn get_args() -> Option<Vec<String>> {
+    env::args_os().enumerate()
+        .map(|(i, arg)| arg.into_string().map_err(|arg| {
+             early_warn(ErrorOutputType::default(),
+                        &format!("Argument {} is not valid Unicode: {:?}", i, arg));
+        }).ok())
+        .collect()
+}
+
+fn stable<F>(name: &'static str, f: F) -> RustcOptGroup
+    where F: Fn(&mut getopts::Options) -> &mut getopts::Options + 'static
+{
+    RustcOptGroup::stable(name, f)
+}
+
+fn unstable<F>(name: &'static str, f: F) -> RustcOptGroup
+    where F: Fn(&mut getopts::Options) -> &mut getopts::Options + 'static
+{
+    RustcOptGroup::unstable(name, f)
+}
+
+pub fn opts() -> Vec<RustcOptGroup> {
+    vec![
+        stable("h", |o| o.optflag("h", "help", "show this help message")),
+        stable("V", |o| o.optflag("V", "version", "print version")),
+        stable("v", |o| o.optflag("v", "verbose", "use verbose output")),
+        stable("config", |o| {
+            o.optopt("", "config", "project configuration file (e.g. igen.toml)", "PATH")
+        }),
+        stable("o", |o| {
+            o.optopt("o", "output", "directory to place generated code \
+                                     (default: print to stdout)", "DIR")
+        }),
+        stable("check", |o| {
+            o.optflag("", "check", "fail if generated files are out of date \
+                                    instead of writing them")
+        }),
+        stable("watch", |o| {
+            o.optflag("", "watch", "regenerate whenever input source files change")
+        }),
+        stable("naming", |o| {
+            o.optopt("", "naming",
+                     "naming convention of generated code (default: idiomatic)",
+                     "preserve|idiomatic")
+        }),
+        stable("handshake", |o| {
+            o.optflag("", "handshake", "generate version negotiation messages \
+                                        with the schema fingerprint")
+        }),
+        stable("crate-name", |o| {
+            o.optopt("", "crate-name", "specify the name of this crate", "NAME")
+        }),
+        stable("L", |o| {
+            o.optmulti("L", "library-path", "directory to add to crate search path",
+                       "DIR")
+        }),
+        stable("cfg", |o| o.optmulti("", "cfg", "pass a --cfg to rustc", "")),
+        stable("extern", |o| {
+            o.optmulti("", "extern", "pass an --extern to rustc", "NAME=PATH")
+        }),
+        stable("C", |o| {
+            o.optmulti("C", "codegen", "pass a codegen option to rustc", "OPT[=VALUE]")
+        }),
+        stable("target", |o| o.optopt("", "target", "target triple to compile for", "TRIPLE")),
+        unstable("Z", |o| {
+            o.optmulti("Z", "",
+                       "internal and debugging options (only on nightly build)", "FLAG")
+        }),
+        stable("sysroot", |o| {
+            o.optopt("", "sysroot", "Override the system root", "PATH")
+        }),
+        unstable("display-warnings", |o| {
+            o.optflag("", "display-warnings", "to print code warnings of input crate")
+        }),
+        unstable("edition", |o| {
+            o.optopt("", "edition",
+                     "edition to use when compiling rust code (default: 2015)",
+                     "EDITION")
+        }),
+        unstable("color", |o| {
+            o.optopt("",
+                     "color",
+                     "Configure coloring of output:
+                                          auto   = colorize, if output goes to a tty (default);
+                                          always = always colorize output;
+                                          never  = never colorize output",
+                     "auto|always|never")
+        }),
+        unstable("error-format", |o| {
+            o.optopt("",
+                     "error-format",
+                     "How errors and other messages are produced",
+                     "human|json|short")
+        }),
+        unstable("warn", |o| {
+            o.optmulti("W", "warn", "Set lint warnings", "OPT")
+        }),
+        unstable("allow", |o| {
+            o.optmulti("A", "allow", "Set lint allowed", "OPT")
+        }),
+        unstable("deny", |o| {
+            o.optmulti("D", "deny", "Set lint denied", "OPT")
+        }),
+        unstable("forbid", |o| {
+            o.optmulti("F", "forbid", "Set lint forbidden", "OPT")
+        }),
+        unstable("cap-lints", |o| {
+            o.optmulti(
+                "",
+                "cap-lints",
+                "Set the most restrictive lint level. \
+                 More restrictive lints are capped at this \
+                 level. By default, it is at `forbid` level.",
+                "LEVEL",
+            )
+        }),
+    ]
+}
+
+pub fn usage(argv0: &str) {
+    let mut options = getopts::Options::new();
+    for option in opts() {
+        (option.apply)(&mut options);
+    }
+    println!("{}", options.usage(&format!("{} [options] <input>\n    \
+                                           {} [options] --config <igen.toml>\n    \
+                                           {} compat [options] <old> <new>\n    \
+                                           {} diff [options] <rev> <input>", argv0, argv0, argv0, argv0)));
+}
@@ -0,0 +1,255 @@
+use serde_json;
+use serde_json::Map;
+use serde_json::Value;
+use super::Module;
+use super::Struct;
+use super::Enum;
+use super::StructField;
+use super::Item;
+use super::Type;
+use super::TypePath;
+use super::Visibility;
+use super::ident::wire_name;
+use super::codegen::GenOptions;
+use super::json_schema::Flavor;
+use super::json_schema::struct_schema;
+use super::json_schema::enum_schema;
+use super::json_schema::variant_schema;
+use super::json_schema::path_key;
+use super::json_schema::find_item;
+
+const VERSION: &'static str = "3.0.0";
+
+/// AsyncAPI 3.0 export.
+///
+/// Top-level enums in `GenOptions::inbound` and
+/// `GenOptions::outbound` become channels, and each of their
+/// variants becomes a message. Inbound messages are received by
+/// the documented peer, and outbound messages are sent by it.
+/// Items reachable from channels become `components/schemas`
+/// keyed like JSON Schema export.
+///
+/// Schemas are draft 07, which is the default schema format of
+/// AsyncAPI.
+impl Module {
+    pub fn gen_asyncapi(&self, opts: &GenOptions) -> String {
+        let cx = Context { kroot: self };
+        let mut channels = Map::new();
+        let mut operations = Map::new();
+        let mut messages = Map::new();
+        let mut schemas = Map::new();
+        let directions = [("receive", &opts.inbound), ("send", &opts.outbound)];
+        for &(action, names) in &directions {
+            for name in names.iter() {
+                let kenum = match self.find_channel(name) {
+                    Some(kenum) => kenum,
+                    None => continue,
+                };
+                let key = path_key(&item_path(&cx, kenum));
+                if !channels.contains_key(wire_name(&kenum.name)) {
+                    gen_channel(&cx, kenum, &key, &mut channels, &mut messages);
+                    collect_enum(&cx, kenum, &key, &mut schemas);
+                }
+                gen_operation(action, wire_name(&kenum.name), &channel_messages(kenum), &mut operations);
+            }
+        }
+        if opts.handshake {
+            gen_handshake(&cx, &mut channels, &mut operations, &mut messages, &mut schemas);
+        }
+
+        let mut info = Map::new();
+        info.insert("title".to_string(), Value::String(wire_name(&self.name).to_string()));
+        info.insert("version".to_string(), Value::String(self.fingerprint()));
+        info.insert("description".to_string(), Value::String("Version is the schema fingerprint.".to_string()));
+        let mut components = Map::new();
+        components.insert("schemas".to_string(), Value::Object(schemas));
+        components.insert("messages".to_string(), Value::Object(messages));
+        let mut root = Map::new();
+        root.insert("asyncapi".to_string(), Value::String(VERSION.to_string()));
+        root.insert("info".to_string(), Value::Object(info));
+        root.insert("defaultContentType".to_string(), Value::String("application/json".to_string()));
+        root.insert("channels".to_string(), Value::Object(channels));
+        root.insert("operations".to_string(), Value::Object(operations));
+        root.insert("components".to_string(), Value::Object(components));
+        return serde_json::to_string_pretty(&Value::Object(root)).unwrap();
+    }
+    /// Checks that channels in `opts` are public top-level enums.
+    pub fn check_asyncapi_channels(&self, opts: &GenOptions) -> Result<(), String> {
+        for name in opts.inbound.iter().chain(opts.outbound.iter()) {
+            if self.find_channel(name).is_none() {
+                return Err(format!("channel `{}` is not a public enum in `{}`", name, self.name));
+            }
+        }
+        return Ok(());
+    }
+    fn find_channel(&self, name: &str) -> Option<&Enum> {
+        for kitem in &self.items {
+            if let Item::Enum(ref kenum) = kitem {
+                if kenum.visibility == Visibility::Public && wire_name(&kenum.name) == name {
+                    return Some(kenum);
+                }
+            }
+        }
+        return None;
+    }
+}
+
+struct Context<'a> {
+    kroot: &'a Module,
+}
+
+/// Messages are keyed by variant names in a channel, and by paths
+/// of variants in components. (`proto.Message.Ping`)
+fn gen_channel(cx: &Context, kenum: &Enum, key: &str, channels: &mut Map<String, Value>, messages: &mut Map<String, Value>) {
+    let mut channel_messages = Map::new();
+    for kvariant in &kenum.variants {
+        let name = wire_name(&kvariant.name).to_string();
+        let message_key = [key, ".", &name].join("");
+        let mut message = Map::new();
+        message.insert("name".to_string(), Value::String(name.clone()));
+        message.insert("title".to_string(), Value::String(name.clone()));
+        message.insert("payload".to_string(), variant_schema(cx.kroot, kvariant, Flavor::AsyncApi));
+        messages.insert(message_key.clone(), Value::Object(message));
+        channel_messages.insert(name, reference(&["#/components/messages/", &message_key].join("")));
+    }
+    let mut channel = Map::new();
+    channel.insert("address".to_string(), Value::String(wire_name(&kenum.name).to_string()));
+    channel.insert("title".to_string(), Value::String(wire_name(&kenum.name).to_string()));
+    channel.insert("messages".to_string(), Value::Object(channel_messages));
+    channels.insert(wire_name(&kenum.name).to_string(), Value::Object(channel));
+}
+
+/// `receiveMessage` or `sendMessage` for channel `Message`.
+fn gen_operation(action: &str, channel: &str, names: &[String], operations: &mut Map<String, Value>) {
+    let refs = names.iter().map(|name| {
+        return reference(&["#/channels/", channel, "/messages/", name].join(""));
+    }).collect::<Vec<Value>>();
+    let mut operation = Map::new();
+    operation.insert("action".to_string(), Value::String(action.to_string()));
+    operation.insert("channel".to_string(), reference(&["#/channels/", channel].join("")));
+    operation.insert("messages".to_string(), Value::Array(refs));
+    operations.insert([action, channel].join(""), Value::Object(operation));
+}
+
+/// Same messages with Swift `IgenHandshake` in channel
+/// `IgenHandshake`. The documented peer receives `IgenHello` and
+/// replies `IgenHelloAck`.
+fn gen_handshake(cx: &Context, channels: &mut Map<String, Value>, operations: &mut Map<String, Value>, messages: &mut Map<String, Value>, schemas: &mut Map<String, Value>) {
+    let mut hello = Struct::new();
+    hello.name = "IgenHello".to_string();
+    hello.fields.push(StructField { name: "fingerprints".to_string(), ty: Type::Vec(Box::new(Type::String)), default: false, deprecation: None, tag: None });
+    let mut hello_ack = Struct::new();
+    hello_ack.name = "IgenHelloAck".to_string();
+    hello_ack.fields.push(StructField { name: "fingerprint".to_string(), ty: Type::Option(Box::new(Type::String)), default: false, deprecation: None, tag: None });
+    let mut channel_messages = Map::new();
+    for kstruct in &[hello, hello_ack] {
+        schemas.insert(kstruct.name.clone(), struct_schema(cx.kroot, kstruct, Flavor::AsyncApi));
+        let mut message = Map::new();
+        message.insert("name".to_string(), Value::String(kstruct.name.clone()));
+        message.insert("payload".to_string(), reference(&["#/components/schemas/", &kstruct.name].join("")));
+        messages.insert(kstruct.name.clone(), Value::Object(message));
+        channel_messages.insert(kstruct.name.clone(), reference(&["#/components/messages/", &kstruct.name].join("")));
+    }
+    let mut channel = Map::new();
+    channel.insert("address".to_string(), Value::String("IgenHandshake".to_string()));
+    channel.insert("title".to_string(), Value::String("IgenHandshake".to_string()));
+    channel.insert("messages".to_string(), Value::Object(channel_messages));
+    channels.insert("IgenHandshake".to_string(), Value::Object(channel));
+    gen_operation("receive", "IgenHandshake", &["IgenHello".to_string()], operations);
+    gen_operation("send", "IgenHandshake", &["IgenHelloAck".to_string()], operations);
+}
+
+fn channel_messages(kenum: &Enum) -> Vec<String> {
+    return kenum.variants.iter().map(|kvariant| wire_name(&kvariant.name).to_string()).collect();
+}
+
+/// Adds schemas of a channel enum and items reachable from it.
+fn collect_enum(cx: &Context, kenum: &Enum, key: &str, schemas: &mut Map<String, Value>) {
+    schemas.insert(key.to_string(), enum_schema(cx.kroot, kenum, Flavor::AsyncApi));
+    for kvariant in &kenum.variants {
+        for kfield in &kvariant.fields {
+            collect_type(cx, &kfield.ty, schemas);
+        }
+    }
+}
+
+/// Items outside of the root module are skipped like JSON Schema
+/// export.
+fn collect_type(cx: &Context, ty: &Type, schemas: &mut Map<String, Value>) {
+    match ty {
+        Type::Core(_) | Type::String => {},
+        Type::Option(ref ty1) | Type::Vec(ref ty1) => collect_type(cx, ty1, schemas),
+        Type::Item(ref path) => {
+            let (key, kitem) = match find_item(cx.kroot, path) {
+                Some(found) => found,
+                None => return,
+            };
+            if schemas.contains_key(&key) { return }
+            match kitem {
+                Item::Struct(ref kstruct) => {
+                    schemas.insert(key, struct_schema(cx.kroot, kstruct, Flavor::AsyncApi));
+                    for kfield in &kstruct.fields {
+                        collect_type(cx, &kfield.ty, schemas);
+                    }
+                },
+                Item::Enum(ref kenum) => collect_enum(cx, kenum, &key, schemas),
+                Item::Module(_) => {},
+            }
+        },
+    }
+}
+
+/// Path of a top-level item.
+fn item_path(cx: &Context, kenum: &Enum) -> TypePath {
+    return TypePath(vec![cx.kroot.name.clone(), kenum.name.clone()]);
+}
+
+fn reference(target: &str) -> Value {
+    let mut m = Map::new();
+    m.insert("$ref".to_string(), Value::String(target.to_string()));
+    return Value::Object(m);
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use serde_json::Value;
+    use schema::Module;
+    use schema::codegen::GenOptions;
+    use schema::tests::sample_module;
+
+    fn value(json: &str) -> Value {
+        return serde_json::from_str(json).unwrap();
+    }
+
+    fn document(kmod: &Module, inbound: &str) -> Value {
+        let mut opts = GenOptions::new();
+        opts.inbound = vec![inbound.to_string()];
+        return value(&kmod.gen_asyncapi(&opts));
+    }
+
+    #[test]
+    fn sends_variants_as_messages() {
+        let doc = document(&sample_module(), "Message");
+        assert_eq!(doc["channels"]["Message"]["messages"]["Move"]["$ref"], value(r##""#/components/messages/p.Message.Move""##));
+        let payload = &doc["components"]["messages"]["p.Message.Move"]["payload"];
+        assert_eq!(payload["minItems"], value("3"));
+        assert_eq!(payload["items"][0], value(r#"{"const": "Move"}"#));
+        assert_eq!(payload["items"][2]["anyOf"][1], value(r#"{"type": "null"}"#));
+    }
+
+    #[test]
+    fn keys_schemas_from_the_root_module() {
+        let kmod: Module = serde_json::from_str(r#"{"name": "protocol", "visibility": "Public", "items": [
+            {"Module": {"name": "sub", "visibility": "Public", "items": [
+                {"Struct": {"name": "Point", "visibility": "Public", "fields": [
+                    {"name": "x", "ty": {"Core": "I32"}}]}}]}},
+            {"Enum": {"name": "Message", "visibility": "Public", "variants": [
+                {"name": "Move", "fields": [{"name": "", "ty": {"Item": ["app", "protocol", "sub", "Point"]}}]}]}}]}"#).unwrap();
+        let doc = document(&kmod, "Message");
+        let payload = &doc["components"]["messages"]["protocol.Message.Move"]["payload"];
+        assert_eq!(payload["items"][1]["$ref"], value(r##""#/components/schemas/protocol.sub.Point""##));
+        assert!(doc["components"]["schemas"]["protocol.Message"].is_object());
+        assert!(doc["components"]["schemas"]["protocol.sub.Point"].is_object());
+    }
+}
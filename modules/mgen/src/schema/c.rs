@@ -0,0 +1,1591 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use super::Module;
+use super::Struct;
+use super::Enum;
+use super::EnumVariant;
+use super::StructField;
+use super::Item;
+use super::Type;
+use super::CoreType;
+use super::Visibility;
+use super::Deprecation;
+use super::ident::Lang;
+use super::ident::wire_name;
+use super::ident::string_literal;
+use super::ident::indent;
+use super::naming::snake_case;
+use super::codegen::GenOptions;
+use super::codegen::deprecation_message;
+
+/// C backend.
+///
+/// Produces a header and a source file. Structs become structs,
+/// and enums become tagged unions with a discriminant enum.
+/// `Vec` becomes a pointer and length pair, and `Option` becomes
+/// a value with a `has` flag. Strings are UTF-8 with length, and
+/// decoded strings are also NUL-terminated.
+///
+/// Each type has `encode`, `decode` and `free` functions for the
+/// wire format of Swift code. Encoders write through a callback.
+/// Decoders allocate memory only through the given allocator.
+/// Zero-filled values are same with `Default::default()` of Rust,
+/// so missing fields with defaults are just left zero.
+///
+/// Modules are flattened like Swift code, and all names are
+/// prefixed with the root module name. Generated code is C99.
+impl Module {
+    /// Header and source files relative to the output directory.
+    pub fn gen_c_files(&self, opts: &GenOptions) -> Vec<(PathBuf, String)> {
+        let handshake = handshake_structs();
+        let cx = Context::new(self, opts, &handshake);
+        let header = [&self.name, ".h"].join("");
+        let source = [&self.name, ".c"].join("");
+        return vec![
+            (PathBuf::from(&header), gen_header(&cx)),
+            (PathBuf::from(&source), gen_source(&cx, &header)),
+        ];
+    }
+}
+
+/// A struct or an enum.
+#[derive(Clone, Copy)]
+enum Def<'a> {
+    Struct(&'a Struct),
+    Enum(&'a Enum),
+}
+
+struct Context<'a> {
+    kroot: &'a Module,
+    opts: &'a GenOptions,
+    /// Prefix of all names. (`proto`)
+    prefix: String,
+    /// Public items in the root module and its submodules.
+    defs: Vec<Def<'a>>,
+    /// All types used by fields in dependency order, without
+    /// duplicates.
+    types: Vec<Type>,
+}
+
+impl<'a> Context<'a> {
+    fn new(kroot: &'a Module, opts: &'a GenOptions, handshake: &'a (Struct, Struct)) -> Context<'a> {
+        let mut cx = Context {
+            kroot: kroot,
+            opts: opts,
+            prefix: c_ident(&snake_case(wire_name(&kroot.name))),
+            defs: Vec::new(),
+            types: Vec::new(),
+        };
+        collect_defs(kroot, &mut cx.defs);
+        if opts.handshake {
+            cx.defs.push(Def::Struct(&handshake.0));
+            cx.defs.push(Def::Struct(&handshake.1));
+        }
+        let mut seen = HashSet::<String>::new();
+        let mut types = Vec::<Type>::new();
+        for def in &cx.defs {
+            for kfield in def_fields(def) {
+                collect_types(&kfield.ty, &mut seen, &mut types);
+            }
+        }
+        cx.types = types;
+        return cx;
+    }
+    fn find_def(&self, ty: &Type) -> Option<Def<'a>> {
+        let path = match ty {
+            Type::Item(ref path) => path,
+            _ => return None,
+        };
+        let name = match path.0.last() {
+            Some(name) => name,
+            None => return None,
+        };
+        return self.defs.iter().cloned().find(|def| def_name(def) == name);
+    }
+    fn upper_prefix(&self) -> String {
+        return self.prefix.to_uppercase();
+    }
+}
+
+/// Same messages with Swift `IgenHandshake`.
+fn handshake_structs() -> (Struct, Struct) {
+    let mut hello = Struct::new();
+    hello.name = "IgenHello".to_string();
+    hello.fields.push(StructField { name: "fingerprints".to_string(), ty: Type::Vec(Box::new(Type::String)), default: false, deprecation: None, tag: None });
+    let mut hello_ack = Struct::new();
+    hello_ack.name = "IgenHelloAck".to_string();
+    hello_ack.fields.push(StructField { name: "fingerprint".to_string(), ty: Type::Option(Box::new(Type::String)), default: false, deprecation: None, tag: None });
+    return (hello, hello_ack);
+}
+
+fn collect_defs<'a>(kmod: &'a Module, defs: &mut Vec<Def<'a>>) {
+    for kitem in &kmod.items {
+        if kitem.visibility() != Visibility::Public { continue }
+        match kitem {
+            Item::Module(ref kmod1) => collect_defs(kmod1, defs),
+            Item::Enum(ref kenum) => defs.push(Def::Enum(kenum)),
+            Item::Struct(ref kstruct) => defs.push(Def::Struct(kstruct)),
+        }
+    }
+}
+
+/// Post-order, so element types come before their containers.
+fn collect_types(ty: &Type, seen: &mut HashSet<String>, types: &mut Vec<Type>) {
+    match ty {
+        Type::Option(ref ty1) => collect_types(ty1, seen, types),
+        Type::Vec(ref ty1) => collect_types(ty1, seen, types),
+        _ => {},
+    }
+    if seen.insert(mangle(ty)) {
+        types.push(ty.clone());
+    }
+}
+
+fn def_name<'a>(def: &Def<'a>) -> &'a str {
+    return match def {
+        Def::Struct(kstruct) => &kstruct.name,
+        Def::Enum(kenum) => &kenum.name,
+    };
+}
+
+/// Fields of a struct or all variants.
+fn def_fields<'a>(def: &Def<'a>) -> Vec<&'a StructField> {
+    return match def {
+        Def::Struct(kstruct) => kstruct.fields.iter().collect(),
+        Def::Enum(kenum) => kenum.variants.iter().flat_map(|kvariant| kvariant.fields.iter()).collect(),
+    };
+}
+
+fn gen_header(cx: &Context) -> String {
+    let guard = [&cx.upper_prefix(), "_H"].join("");
+    let mut s = String::new();
+    s.push_str("#ifndef ");
+    s.push_str(&guard);
+    s.push_str("\n");
+    s.push_str("#define ");
+    s.push_str(&guard);
+    s.push_str("\n");
+    s.push_str("\n");
+    s.push_str("#include <stdbool.h>\n");
+    s.push_str("#include <stddef.h>\n");
+    s.push_str("#include <stdint.h>\n");
+    s.push_str("\n");
+    s.push_str("#ifdef __cplusplus\n");
+    s.push_str("extern \"C\" {\n");
+    s.push_str("#endif\n");
+    s.push_str("\n");
+    s.push_str(&substitute(cx, HEADER_PRELUDE));
+    s.push_str("\n");
+    s.push_str("#define ");
+    s.push_str(&cx.upper_prefix());
+    s.push_str("_SCHEMA_FINGERPRINT \"");
+    s.push_str(&cx.kroot.fingerprint());
+    s.push_str("\"\n");
+    s.push_str("\n");
+
+    // Every named type is declared first, so vectors can point
+    // to any type.
+    for def in &cx.defs {
+        let name = def_type_name(cx, def);
+        s.push_str("typedef struct ");
+        s.push_str(&name);
+        s.push_str(" ");
+        s.push_str(&name);
+        s.push_str(";\n");
+    }
+    for ty in &cx.types {
+        match ty {
+            Type::Option(_) | Type::Vec(_) => {
+                let name = c_type(cx, ty);
+                s.push_str("typedef struct ");
+                s.push_str(&name);
+                s.push_str(" ");
+                s.push_str(&name);
+                s.push_str(";\n");
+            },
+            _ => {},
+        }
+    }
+    s.push_str("\n");
+    for ty in &cx.types {
+        if let Type::Vec(ref ty1) = ty {
+            s.push_str("struct ");
+            s.push_str(&c_type(cx, ty));
+            s.push_str(" {\n");
+            s.push_str("    ");
+            s.push_str(&c_type(cx, ty1));
+            s.push_str(" *data;\n");
+            s.push_str("    size_t len;\n");
+            s.push_str("};\n");
+            s.push_str("\n");
+        }
+    }
+
+    // Other types are defined after types of their fields.
+    let mut done = HashSet::<String>::new();
+    for def in &cx.defs {
+        gen_definition(cx, &Type::Item(item_path(def)), &mut done, &mut s);
+    }
+
+    // Types are used by other types, so only functions are
+    // marked as deprecated.
+    for def in &cx.defs {
+        let name = def_type_name(cx, def);
+        let attr = match def {
+            Def::Struct(kstruct) => deprecated(&kstruct.deprecation),
+            Def::Enum(kenum) => deprecated(&kenum.deprecation),
+        };
+        s.push_str(&cx.prefix);
+        s.push_str("_status ");
+        s.push_str(&name);
+        s.push_str("_encode(const ");
+        s.push_str(&name);
+        s.push_str(" *v, const ");
+        s.push_str(&cx.prefix);
+        s.push_str("_writer *w)");
+        s.push_str(&attr);
+        s.push_str(";\n");
+        s.push_str("/* `out` must be freed even on failure. */\n");
+        s.push_str(&cx.prefix);
+        s.push_str("_status ");
+        s.push_str(&name);
+        s.push_str("_decode(const char *json, size_t len, const ");
+        s.push_str(&cx.prefix);
+        s.push_str("_allocator *a, ");
+        s.push_str(&name);
+        s.push_str(" *out)");
+        s.push_str(&attr);
+        s.push_str(";\n");
+        s.push_str("void ");
+        s.push_str(&name);
+        s.push_str("_free(");
+        s.push_str(&name);
+        s.push_str(" *v, const ");
+        s.push_str(&cx.prefix);
+        s.push_str("_allocator *a)");
+        s.push_str(&attr);
+        s.push_str(";\n");
+        s.push_str("\n");
+    }
+
+    if cx.opts.handshake {
+        s.push_str("/* Same messages with Swift `IgenHandshake`.\n");
+        s.push_str(" * Filled values refer to static memory and must not be freed. */\n");
+        s.push_str("void ");
+        s.push_str(&cx.prefix);
+        s.push_str("_igen_hello(");
+        s.push_str(&cx.prefix);
+        s.push_str("_IgenHello *hello);\n");
+        s.push_str("/* Empty fingerprint refuses the hello. */\n");
+        s.push_str("void ");
+        s.push_str(&cx.prefix);
+        s.push_str("_igen_accept(const ");
+        s.push_str(&cx.prefix);
+        s.push_str("_IgenHello *hello, ");
+        s.push_str(&cx.prefix);
+        s.push_str("_IgenHelloAck *ack);\n");
+        s.push_str("\n");
+    }
+
+    s.push_str("#ifdef __cplusplus\n");
+    s.push_str("}\n");
+    s.push_str("#endif\n");
+    s.push_str("\n");
+    s.push_str("#endif\n");
+    return s;
+}
+
+/// Defines `ty` and types it contains by value.
+fn gen_definition(cx: &Context, ty: &Type, done: &mut HashSet<String>, s: &mut String) {
+    match ty {
+        Type::Option(ref ty1) => {
+            gen_definition(cx, ty1, done, s);
+            if !done.insert(mangle(ty)) { return }
+            s.push_str("struct ");
+            s.push_str(&c_type(cx, ty));
+            s.push_str(" {\n");
+            s.push_str("    bool has;\n");
+            s.push_str("    ");
+            s.push_str(&c_type(cx, ty1));
+            s.push_str(" value;\n");
+            s.push_str("};\n");
+            s.push_str("\n");
+        },
+        Type::Item(_) => {
+            let def = match cx.find_def(ty) {
+                Some(def) => def,
+                None => return,
+            };
+            if !done.insert(mangle(ty)) { return }
+            for kfield in def_fields(&def) {
+                gen_definition(cx, &kfield.ty, done, s);
+            }
+            match def {
+                Def::Struct(kstruct) => s.push_str(&gen_struct(cx, kstruct)),
+                Def::Enum(kenum) => s.push_str(&gen_enum(cx, kenum)),
+            }
+            s.push_str("\n");
+        },
+        _ => {},
+    }
+}
+
+fn gen_struct(cx: &Context, kstruct: &Struct) -> String {
+    let name = def_type_name(cx, &Def::Struct(kstruct));
+    let mut s = String::new();
+    s.push_str(&fingerprint_macro(cx, &kstruct.name, &kstruct.fingerprint()));
+    s.push_str("struct ");
+    s.push_str(&name);
+    s.push_str(" {\n");
+    s.push_str(&gen_fields(cx, &kstruct.fields, "    "));
+    // Empty structs are not allowed in C.
+    if kstruct.fields.len() == 0 {
+        s.push_str("    char unused;\n");
+    }
+    s.push_str("};\n");
+    return s;
+}
+
+fn gen_enum(cx: &Context, kenum: &Enum) -> String {
+    let name = def_type_name(cx, &Def::Enum(kenum));
+    let mut s = String::new();
+    s.push_str(&fingerprint_macro(cx, &kenum.name, &kenum.fingerprint()));
+    s.push_str("typedef enum {\n");
+    for kvariant in &kenum.variants {
+        s.push_str("    ");
+        s.push_str(&tag_name(cx, kenum, kvariant));
+        s.push_str(&deprecated(&kvariant.deprecation));
+        s.push_str(",\n");
+    }
+    if kenum.variants.len() == 0 {
+        s.push_str("    ");
+        s.push_str(&[&name[..], "_none"].join("").to_uppercase());
+        s.push_str("\n");
+    }
+    s.push_str("} ");
+    s.push_str(&name);
+    s.push_str("_tag;\n");
+    s.push_str("\n");
+    s.push_str("struct ");
+    s.push_str(&name);
+    s.push_str(" {\n");
+    s.push_str("    ");
+    s.push_str(&name);
+    s.push_str("_tag tag;\n");
+    // Unions need at least one member.
+    if kenum.variants.iter().any(|kvariant| kvariant.fields.len() > 0) {
+        s.push_str("    union {\n");
+        for kvariant in &kenum.variants {
+            if kvariant.fields.len() == 0 { continue }
+            s.push_str("        struct {\n");
+            s.push_str(&gen_fields(cx, &kvariant.fields, "            "));
+            s.push_str("        } ");
+            s.push_str(&variant_member_name(cx, kvariant));
+            s.push_str(";\n");
+        }
+        s.push_str("    } as;\n");
+    }
+    s.push_str("};\n");
+    return s;
+}
+
+fn gen_fields(cx: &Context, kfields: &[StructField], indent: &str) -> String {
+    let mut s = String::new();
+    for (i, kfield) in kfields.iter().enumerate() {
+        s.push_str(indent);
+        s.push_str(&c_type(cx, &kfield.ty));
+        s.push_str(" ");
+        s.push_str(&field_name(cx.opts, kfield, i));
+        s.push_str(&deprecated(&kfield.deprecation));
+        s.push_str(";\n");
+    }
+    return s;
+}
+
+fn gen_source(cx: &Context, header: &str) -> String {
+    let mut s = String::new();
+    s.push_str("#include \"");
+    s.push_str(header);
+    s.push_str("\"\n");
+    s.push_str("\n");
+    s.push_str(&substitute(cx, SOURCE_PRELUDE));
+
+    // Prototypes first, because types can be recursive.
+    s.push_str("\n");
+    for ty in &cx.types {
+        s.push_str(&read_signature(cx, ty));
+        s.push_str(";\n");
+        s.push_str(&write_signature(cx, ty));
+        s.push_str(";\n");
+        if needs_free(ty) {
+            s.push_str(&free_signature(cx, ty));
+            s.push_str(";\n");
+        }
+    }
+    for def in &cx.defs {
+        let ty = Type::Item(item_path(def));
+        if cx.types.iter().any(|ty1| mangle(ty1) == mangle(&ty)) { continue }
+        s.push_str(&read_signature(cx, &ty));
+        s.push_str(";\n");
+        s.push_str(&write_signature(cx, &ty));
+        s.push_str(";\n");
+        s.push_str(&free_signature(cx, &ty));
+        s.push_str(";\n");
+    }
+
+    for ty in &cx.types {
+        match ty {
+            Type::Item(_) => {},
+            _ => {
+                s.push_str("\n");
+                s.push_str(&gen_type_codec(cx, ty));
+            },
+        }
+    }
+    for def in &cx.defs {
+        s.push_str("\n");
+        match def {
+            Def::Struct(kstruct) => s.push_str(&gen_struct_codec(cx, kstruct)),
+            Def::Enum(kenum) => s.push_str(&gen_enum_codec(cx, kenum)),
+        }
+        s.push_str("\n");
+        s.push_str(&gen_public_functions(cx, def));
+    }
+    if cx.opts.handshake {
+        s.push_str("\n");
+        s.push_str(&substitute(cx, HANDSHAKE_SOURCE));
+    }
+    return s;
+}
+
+/// Functions of core types, strings, options and vectors.
+fn gen_type_codec(cx: &Context, ty: &Type) -> String {
+    let cty = c_type(cx, ty);
+    let mut s = String::new();
+    s.push_str(&read_signature(cx, ty));
+    s.push_str(" {\n");
+    match ty {
+        Type::Core(CoreType::Bool) => {
+            s.push_str("    return igen_parse_bool(r, out);\n");
+        },
+        Type::Core(CoreType::F32) | Type::Core(CoreType::F64) => {
+            s.push_str("    double v;\n");
+            s.push_str("    IGEN_TRY(igen_parse_double(r, &v));\n");
+            s.push_str("    *out = (");
+            s.push_str(&cty);
+            s.push_str(")v;\n");
+            s.push_str("    return ");
+            s.push_str(&cx.upper_prefix());
+            s.push_str("_OK;\n");
+        },
+        Type::Core(ref core_type) => {
+            let (signed, bits) = integer_info(core_type);
+            if signed {
+                s.push_str("    int64_t v;\n");
+                s.push_str("    IGEN_TRY(igen_parse_int(r, INT");
+                s.push_str(bits);
+                s.push_str("_MIN, INT");
+                s.push_str(bits);
+                s.push_str("_MAX, &v));\n");
+            }
+            else {
+                s.push_str("    uint64_t v;\n");
+                s.push_str("    IGEN_TRY(igen_parse_uint(r, UINT");
+                s.push_str(bits);
+                s.push_str("_MAX, &v));\n");
+            }
+            s.push_str("    *out = (");
+            s.push_str(&cty);
+            s.push_str(")v;\n");
+            s.push_str("    return ");
+            s.push_str(&cx.upper_prefix());
+            s.push_str("_OK;\n");
+        },
+        Type::String => {
+            s.push_str("    return igen_parse_str(r, out);\n");
+        },
+        Type::Option(ref ty1) => {
+            s.push_str("    memset(out, 0, sizeof *out);\n");
+            s.push_str("    if (igen_consume_word(r, \"null\")) return ");
+            s.push_str(&cx.upper_prefix());
+            s.push_str("_OK;\n");
+            s.push_str("    out->has = true;\n");
+            s.push_str("    return igen_read_");
+            s.push_str(&mangle(ty1));
+            s.push_str("(r, &out->value);\n");
+        },
+        Type::Vec(ref ty1) => {
+            let ety = c_type(cx, ty1);
+            s.push_str("    size_t cap = 0;\n");
+            s.push_str("    memset(out, 0, sizeof *out);\n");
+            s.push_str("    IGEN_TRY(igen_expect_type(r, '['));\n");
+            s.push_str("    IGEN_TRY(igen_enter(r));\n");
+            s.push_str("    if (igen_consume(r, ']')) return igen_leave(r, ");
+            s.push_str(&cx.upper_prefix());
+            s.push_str("_OK);\n");
+            s.push_str("    for (;;) {\n");
+            s.push_str("        if (out->len == cap) {\n");
+            s.push_str("            ");
+            s.push_str(&ety);
+            s.push_str(" *data = igen_grow(r->a, out->data, out->len, &cap, sizeof *data);\n");
+            s.push_str("            if (data == NULL) return ");
+            s.push_str(&cx.upper_prefix());
+            s.push_str("_ERR_ALLOC;\n");
+            s.push_str("            out->data = data;\n");
+            s.push_str("        }\n");
+            s.push_str("        /* Counted before reading, so partial elements are freed. */\n");
+            s.push_str("        out->len += 1;\n");
+            s.push_str("        IGEN_TRY(igen_read_");
+            s.push_str(&mangle(ty1));
+            s.push_str("(r, &out->data[out->len - 1]));\n");
+            s.push_str("        if (igen_consume(r, ',')) continue;\n");
+            s.push_str("        return igen_leave(r, igen_expect(r, ']'));\n");
+            s.push_str("    }\n");
+        },
+        Type::Item(_) => {},
+    }
+    s.push_str("}\n");
+
+    s.push_str("\n");
+    s.push_str(&write_signature(cx, ty));
+    s.push_str(" {\n");
+    match ty {
+        Type::Core(CoreType::Bool) => {
+            s.push_str("    return igen_emit_cstr(w, *v ? \"true\" : \"false\");\n");
+        },
+        Type::Core(CoreType::F32) => {
+            s.push_str("    return igen_emit_double(w, *v, 9);\n");
+        },
+        Type::Core(CoreType::F64) => {
+            s.push_str("    return igen_emit_double(w, *v, 17);\n");
+        },
+        Type::Core(ref core_type) => {
+            let (signed, _) = integer_info(core_type);
+            if signed {
+                s.push_str("    return igen_emit_int(w, *v);\n");
+            }
+            else {
+                s.push_str("    return igen_emit_uint(w, *v);\n");
+            }
+        },
+        Type::String => {
+            s.push_str("    return igen_emit_str(w, v);\n");
+        },
+        Type::Option(ref ty1) => {
+            s.push_str("    if (!v->has) return igen_emit_cstr(w, \"null\");\n");
+            s.push_str("    return igen_write_");
+            s.push_str(&mangle(ty1));
+            s.push_str("(w, &v->value);\n");
+        },
+        Type::Vec(ref ty1) => {
+            s.push_str("    size_t i;\n");
+            s.push_str("    IGEN_TRY(igen_emit_cstr(w, \"[\"));\n");
+            s.push_str("    for (i = 0; i < v->len; i++) {\n");
+            s.push_str("        if (i > 0) IGEN_TRY(igen_emit_cstr(w, \",\"));\n");
+            s.push_str("        IGEN_TRY(igen_write_");
+            s.push_str(&mangle(ty1));
+            s.push_str("(w, &v->data[i]));\n");
+            s.push_str("    }\n");
+            s.push_str("    return igen_emit_cstr(w, \"]\");\n");
+        },
+        Type::Item(_) => {},
+    }
+    s.push_str("}\n");
+
+    if needs_free(ty) {
+        s.push_str("\n");
+        s.push_str(&free_signature(cx, ty));
+        s.push_str(" {\n");
+        match ty {
+            Type::String => {
+                s.push_str("    igen_free(a, v->data);\n");
+            },
+            Type::Option(ref ty1) => {
+                s.push_str("    if (v->has) igen_free_");
+                s.push_str(&mangle(ty1));
+                s.push_str("(&v->value, a);\n");
+            },
+            Type::Vec(ref ty1) => {
+                if needs_free(ty1) {
+                    s.push_str("    size_t i;\n");
+                    s.push_str("    for (i = 0; i < v->len; i++) {\n");
+                    s.push_str("        igen_free_");
+                    s.push_str(&mangle(ty1));
+                    s.push_str("(&v->data[i], a);\n");
+                    s.push_str("    }\n");
+                }
+                s.push_str("    igen_free(a, v->data);\n");
+            },
+            _ => {},
+        }
+        s.push_str("    memset(v, 0, sizeof *v);\n");
+        s.push_str("}\n");
+    }
+    return s;
+}
+
+/// Unknown keys are skipped. Missing keys are allowed only for
+/// optional fields and fields with defaults.
+/// Absent optional fields are omitted like Swift.
+fn gen_struct_codec(cx: &Context, kstruct: &Struct) -> String {
+    let ty = Type::Item(item_path(&Def::Struct(kstruct)));
+    let ok = [&cx.upper_prefix(), "_OK"].join("");
+    let mut s = String::new();
+    s.push_str(&read_signature(cx, &ty));
+    s.push_str(" {\n");
+    let required = kstruct.fields.iter().enumerate().filter(|&(_, kfield)| is_required(kfield)).map(|(i, _)| i).collect::<Vec<usize>>();
+    for &i in &required {
+        s.push_str("    bool has");
+        s.push_str(&i.to_string());
+        s.push_str(" = false;\n");
+    }
+    s.push_str("    memset(out, 0, sizeof *out);\n");
+    s.push_str("    IGEN_TRY(igen_expect_type(r, '{'));\n");
+    s.push_str("    IGEN_TRY(igen_enter(r));\n");
+    s.push_str("    if (!igen_consume(r, '}')) for (;;) {\n");
+    s.push_str("        const char *key;\n");
+    s.push_str("        size_t key_len;\n");
+    s.push_str("        IGEN_TRY(igen_parse_raw_string(r, &key, &key_len));\n");
+    s.push_str("        IGEN_TRY(igen_expect(r, ':'));\n");
+    for (i, kfield) in kstruct.fields.iter().enumerate() {
+        let member = ["out->", &field_name(cx.opts, kfield, i)].join("");
+        s.push_str(if i == 0 { "        if (" } else { "        else if (" });
+        s.push_str("igen_raw_is(key, key_len, ");
+        s.push_str(&string_literal(wire_name(&kfield.name)));
+        s.push_str(")) {\n");
+        // Later one wins for duplicate keys.
+        if needs_free(&kfield.ty) {
+            s.push_str("            igen_free_");
+            s.push_str(&mangle(&kfield.ty));
+            s.push_str("(&");
+            s.push_str(&member);
+            s.push_str(", r->a);\n");
+        }
+        s.push_str("            IGEN_TRY(igen_read_");
+        s.push_str(&mangle(&kfield.ty));
+        s.push_str("(r, &");
+        s.push_str(&member);
+        s.push_str("));\n");
+        if is_required(kfield) {
+            s.push_str("            has");
+            s.push_str(&i.to_string());
+            s.push_str(" = true;\n");
+        }
+        s.push_str("        }\n");
+    }
+    if kstruct.fields.len() > 0 {
+        s.push_str("        else {\n");
+        s.push_str("            IGEN_TRY(igen_skip_value(r));\n");
+        s.push_str("        }\n");
+    }
+    else {
+        s.push_str("        IGEN_TRY(igen_skip_value(r));\n");
+    }
+    s.push_str("        if (igen_consume(r, ',')) continue;\n");
+    s.push_str("        IGEN_TRY(igen_expect(r, '}'));\n");
+    s.push_str("        break;\n");
+    s.push_str("    }\n");
+    if required.len() > 0 {
+        let checks = required.iter().map(|i| ["!has", &i.to_string()].join("")).collect::<Vec<String>>();
+        s.push_str("    if (");
+        s.push_str(&checks.join(" || "));
+        s.push_str(") return ");
+        s.push_str(&cx.upper_prefix());
+        s.push_str("_ERR_MISSING;\n");
+    }
+    s.push_str("    return igen_leave(r, ");
+    s.push_str(&ok);
+    s.push_str(");\n");
+    s.push_str("}\n");
+
+    s.push_str("\n");
+    s.push_str(&write_signature(cx, &ty));
+    s.push_str(" {\n");
+    s.push_str("    bool first = true;\n");
+    s.push_str("    IGEN_TRY(igen_emit_cstr(w, \"{\"));\n");
+    for (i, kfield) in kstruct.fields.iter().enumerate() {
+        let member = ["v->", &field_name(cx.opts, kfield, i)].join("");
+        let mut lines = String::new();
+        lines.push_str("IGEN_TRY(igen_emit_key(w, &first, ");
+        lines.push_str(&string_literal(wire_name(&kfield.name)));
+        lines.push_str("));\n");
+        lines.push_str("IGEN_TRY(igen_write_");
+        lines.push_str(&mangle(&kfield.ty));
+        lines.push_str("(w, &");
+        lines.push_str(&member);
+        lines.push_str("));\n");
+        if let Type::Option(_) = kfield.ty {
+            s.push_str("    if (");
+            s.push_str(&member);
+            s.push_str(".has) {\n");
+            s.push_str(&indent(&indent(&lines)));
+            s.push_str("    }\n");
+        }
+        else {
+            s.push_str(&indent(&lines));
+        }
+    }
+    s.push_str("    (void)first;\n");
+    s.push_str("    return igen_emit_cstr(w, \"}\");\n");
+    s.push_str("}\n");
+
+    s.push_str("\n");
+    s.push_str(&free_signature(cx, &ty));
+    s.push_str(" {\n");
+    for (i, kfield) in kstruct.fields.iter().enumerate() {
+        if !needs_free(&kfield.ty) { continue }
+        s.push_str("    igen_free_");
+        s.push_str(&mangle(&kfield.ty));
+        s.push_str("(&v->");
+        s.push_str(&field_name(cx.opts, kfield, i));
+        s.push_str(", a);\n");
+    }
+    s.push_str("    (void)a;\n");
+    s.push_str("    memset(v, 0, sizeof *v);\n");
+    s.push_str("}\n");
+    return s;
+}
+
+/// Encoded as `[name, field0, field1, ...]`.
+/// Trailing elements are skipped. Missing trailing elements are
+/// allowed only for fields with defaults.
+fn gen_enum_codec(cx: &Context, kenum: &Enum) -> String {
+    let ty = Type::Item(item_path(&Def::Enum(kenum)));
+    let upper_prefix = cx.upper_prefix();
+    let mut s = String::new();
+    s.push_str(&read_signature(cx, &ty));
+    s.push_str(" {\n");
+    s.push_str("    const char *name;\n");
+    s.push_str("    size_t name_len;\n");
+    s.push_str("    memset(out, 0, sizeof *out);\n");
+    s.push_str("    IGEN_TRY(igen_expect_type(r, '['));\n");
+    s.push_str("    IGEN_TRY(igen_enter(r));\n");
+    s.push_str("    IGEN_TRY(igen_parse_raw_string(r, &name, &name_len));\n");
+    for (i, kvariant) in kenum.variants.iter().enumerate() {
+        s.push_str(if i == 0 { "    if (" } else { "    else if (" });
+        s.push_str("igen_raw_is(name, name_len, ");
+        s.push_str(&string_literal(wire_name(&kvariant.name)));
+        s.push_str(")) {\n");
+        s.push_str("        out->tag = ");
+        s.push_str(&tag_name(cx, kenum, kvariant));
+        s.push_str(";\n");
+        for (j, kfield) in kvariant.fields.iter().enumerate() {
+            let member = ["out->as.", &variant_member_name(cx, kvariant), ".", &field_name(cx.opts, kfield, j)].join("");
+            let rest_required = kvariant.fields[j..].iter().any(|kfield1| !kfield1.default);
+            s.push_str("        if (!igen_consume(r, ',')) return ");
+            if rest_required {
+                s.push_str(&upper_prefix);
+                s.push_str("_ERR_MISSING;\n");
+            }
+            else {
+                s.push_str("igen_leave(r, igen_expect(r, ']'));\n");
+            }
+            s.push_str("        IGEN_TRY(igen_read_");
+            s.push_str(&mangle(&kfield.ty));
+            s.push_str("(r, &");
+            s.push_str(&member);
+            s.push_str("));\n");
+        }
+        s.push_str("        return igen_leave(r, igen_skip_rest(r));\n");
+        s.push_str("    }\n");
+    }
+    s.push_str("    return ");
+    s.push_str(&upper_prefix);
+    s.push_str("_ERR_VARIANT;\n");
+    s.push_str("}\n");
+
+    s.push_str("\n");
+    s.push_str(&write_signature(cx, &ty));
+    s.push_str(" {\n");
+    s.push_str("    IGEN_TRY(igen_emit_cstr(w, \"[\"));\n");
+    s.push_str("    switch (v->tag) {\n");
+    for kvariant in &kenum.variants {
+        s.push_str("    case ");
+        s.push_str(&tag_name(cx, kenum, kvariant));
+        s.push_str(":\n");
+        s.push_str("        IGEN_TRY(igen_emit_cstr(w, ");
+        s.push_str(&string_literal(&["\"", wire_name(&kvariant.name), "\""].join("")));
+        s.push_str("));\n");
+        for (j, kfield) in kvariant.fields.iter().enumerate() {
+            let member = ["v->as.", &variant_member_name(cx, kvariant), ".", &field_name(cx.opts, kfield, j)].join("");
+            s.push_str("        IGEN_TRY(igen_emit_cstr(w, \",\"));\n");
+            s.push_str("        IGEN_TRY(igen_write_");
+            s.push_str(&mangle(&kfield.ty));
+            s.push_str("(w, &");
+            s.push_str(&member);
+            s.push_str("));\n");
+        }
+        s.push_str("        break;\n");
+    }
+    s.push_str("    default:\n");
+    s.push_str("        return ");
+    s.push_str(&upper_prefix);
+    s.push_str("_ERR_VARIANT;\n");
+    s.push_str("    }\n");
+    s.push_str("    return igen_emit_cstr(w, \"]\");\n");
+    s.push_str("}\n");
+
+    s.push_str("\n");
+    s.push_str(&free_signature(cx, &ty));
+    s.push_str(" {\n");
+    s.push_str("    switch (v->tag) {\n");
+    for kvariant in &kenum.variants {
+        s.push_str("    case ");
+        s.push_str(&tag_name(cx, kenum, kvariant));
+        s.push_str(":\n");
+        for (j, kfield) in kvariant.fields.iter().enumerate() {
+            if !needs_free(&kfield.ty) { continue }
+            s.push_str("        igen_free_");
+            s.push_str(&mangle(&kfield.ty));
+            s.push_str("(&v->as.");
+            s.push_str(&variant_member_name(cx, kvariant));
+            s.push_str(".");
+            s.push_str(&field_name(cx.opts, kfield, j));
+            s.push_str(", a);\n");
+        }
+        s.push_str("        break;\n");
+    }
+    s.push_str("    default:\n");
+    s.push_str("        break;\n");
+    s.push_str("    }\n");
+    s.push_str("    (void)a;\n");
+    s.push_str("    memset(v, 0, sizeof *v);\n");
+    s.push_str("}\n");
+    return s;
+}
+
+fn gen_public_functions(cx: &Context, def: &Def) -> String {
+    let name = def_type_name(cx, def);
+    let m = def_name(def);
+    let p = &cx.prefix;
+    let mut s = String::new();
+    s.push_str(p);
+    s.push_str("_status ");
+    s.push_str(&name);
+    s.push_str("_encode(const ");
+    s.push_str(&name);
+    s.push_str(" *v, const ");
+    s.push_str(p);
+    s.push_str("_writer *w) {\n");
+    s.push_str("    return igen_write_");
+    s.push_str(m);
+    s.push_str("(w, v);\n");
+    s.push_str("}\n");
+    s.push_str("\n");
+    s.push_str(p);
+    s.push_str("_status ");
+    s.push_str(&name);
+    s.push_str("_decode(const char *json, size_t len, const ");
+    s.push_str(p);
+    s.push_str("_allocator *a, ");
+    s.push_str(&name);
+    s.push_str(" *out) {\n");
+    s.push_str("    igen_reader r;\n");
+    s.push_str("    r.p = json;\n");
+    s.push_str("    r.end = json + len;\n");
+    s.push_str("    r.a = a;\n");
+    s.push_str("    r.depth = 0;\n");
+    s.push_str("    IGEN_TRY(igen_read_");
+    s.push_str(m);
+    s.push_str("(&r, out));\n");
+    s.push_str("    igen_skip_ws(&r);\n");
+    s.push_str("    return r.p == r.end ? ");
+    s.push_str(&cx.upper_prefix());
+    s.push_str("_OK : ");
+    s.push_str(&cx.upper_prefix());
+    s.push_str("_ERR_SYNTAX;\n");
+    s.push_str("}\n");
+    s.push_str("\n");
+    s.push_str("void ");
+    s.push_str(&name);
+    s.push_str("_free(");
+    s.push_str(&name);
+    s.push_str(" *v, const ");
+    s.push_str(p);
+    s.push_str("_allocator *a) {\n");
+    s.push_str("    igen_free_");
+    s.push_str(m);
+    s.push_str("(v, a);\n");
+    s.push_str("}\n");
+    return s;
+}
+
+fn read_signature(cx: &Context, ty: &Type) -> String {
+    return ["static ", &cx.prefix, "_status igen_read_", &mangle(ty), "(igen_reader *r, ", &c_type(cx, ty), " *out)"].join("");
+}
+
+fn write_signature(cx: &Context, ty: &Type) -> String {
+    return ["static ", &cx.prefix, "_status igen_write_", &mangle(ty), "(const ", &cx.prefix, "_writer *w, const ", &c_type(cx, ty), " *v)"].join("");
+}
+
+fn free_signature(cx: &Context, ty: &Type) -> String {
+    return ["static void igen_free_", &mangle(ty), "(", &c_type(cx, ty), " *v, const ", &cx.prefix, "_allocator *a)"].join("");
+}
+
+fn is_required(kfield: &StructField) -> bool {
+    if kfield.default { return false }
+    return match kfield.ty {
+        Type::Option(_) => false,
+        _ => true,
+    };
+}
+
+/// Whether values own memory. Items always have free functions.
+fn needs_free(ty: &Type) -> bool {
+    return match ty {
+        Type::Core(_) => false,
+        Type::String => true,
+        Type::Item(_) => true,
+        Type::Option(ref ty1) => needs_free(ty1),
+        Type::Vec(_) => true,
+    };
+}
+
+/// Name of a type in names of generated functions and types.
+/// (`u8`, `str`, `Point`, `opt_str`, `vec_Point`)
+fn mangle(ty: &Type) -> String {
+    return match ty {
+        Type::Core(ref core_type) => core_name(core_type).to_string(),
+        Type::String => "str".to_string(),
+        Type::Item(ref path) => path.0.last().map(|s| wire_name(s).to_string()).unwrap_or("????".to_string()),
+        Type::Option(ref ty1) => ["opt_", &mangle(ty1)].join(""),
+        Type::Vec(ref ty1) => ["vec_", &mangle(ty1)].join(""),
+    };
+}
+
+fn c_type(cx: &Context, ty: &Type) -> String {
+    return match ty {
+        Type::Core(CoreType::Bool) => "bool".to_string(),
+        Type::Core(CoreType::F32) => "float".to_string(),
+        Type::Core(CoreType::F64) => "double".to_string(),
+        Type::Core(ref core_type) => {
+            let (signed, bits) = integer_info(core_type);
+            [if signed { "int" } else { "uint" }, bits, "_t"].join("")
+        },
+        _ => [&cx.prefix, "_", &mangle(ty)].join(""),
+    };
+}
+
+fn core_name(core_type: &CoreType) -> &'static str {
+    use self::CoreType::*;
+    return match core_type {
+        Bool => "bool",
+        U8 => "u8",
+        U16 => "u16",
+        U32 => "u32",
+        U64 => "u64",
+        I8 => "i8",
+        I16 => "i16",
+        I32 => "i32",
+        I64 => "i64",
+        F32 => "f32",
+        F64 => "f64",
+    };
+}
+
+/// Signedness and bits of integer types.
+fn integer_info(core_type: &CoreType) -> (bool, &'static str) {
+    use self::CoreType::*;
+    return match core_type {
+        U8 => (false, "8"),
+        U16 => (false, "16"),
+        U32 => (false, "32"),
+        U64 => (false, "64"),
+        I8 => (true, "8"),
+        I16 => (true, "16"),
+        I32 => (true, "32"),
+        I64 => (true, "64"),
+        Bool | F32 | F64 => (false, "??"),
+    };
+}
+
+fn item_path(def: &Def) -> super::TypePath {
+    return super::TypePath(vec![def_name(def).to_string()]);
+}
+
+fn def_type_name(cx: &Context, def: &Def) -> String {
+    return [&cx.prefix, "_", wire_name(def_name(def))].join("");
+}
+
+/// `PROTO_MESSAGE_PING`
+fn tag_name(cx: &Context, kenum: &Enum, kvariant: &EnumVariant) -> String {
+    let name = [&cx.prefix, "_", &snake_case(wire_name(&kenum.name)), "_", &snake_case(wire_name(&kvariant.name))].join("");
+    return name.to_uppercase();
+}
+
+fn variant_member_name(cx: &Context, kvariant: &EnumVariant) -> String {
+    return Lang::C.variant_name(cx.opts.naming, &kvariant.name);
+}
+
+/// Tuple fields are named by their positions. (`f0`, `f1`, ...)
+fn field_name(opts: &super::codegen::GenOptions, kfield: &StructField, index: usize) -> String {
+    if kfield.name == "" {
+        return ["f", &index.to_string()].join("");
+    }
+    return Lang::C.field_name(opts.naming, &kfield.name);
+}
+
+fn fingerprint_macro(cx: &Context, name: &str, fingerprint: &str) -> String {
+    let name = [&cx.upper_prefix(), "_", &snake_case(wire_name(name)).to_uppercase(), "_FINGERPRINT"].join("");
+    return ["#define ", &name, " \"", fingerprint, "\"\n"].join("");
+}
+
+fn deprecated(deprecation: &Option<Deprecation>) -> String {
+    let deprecation = match deprecation {
+        Some(ref deprecation) => deprecation,
+        None => return String::new(),
+    };
+    let message = deprecation_message(deprecation).unwrap_or("deprecated".to_string());
+    return [" IGEN_DEPRECATED(", &string_literal(&message), ")"].join("");
+}
+
+/// Replaces placeholders of fixed code with the prefix.
+fn substitute(cx: &Context, code: &str) -> String {
+    return code.replace("igenp_", &[&cx.prefix, "_"].join("")).replace("IGENP_", &[&cx.upper_prefix(), "_"].join(""));
+}
+
+fn c_ident(name: &str) -> String {
+    return Lang::C.escape_ident(name);
+}
+
+const HEADER_PRELUDE: &'static str = r#"#if defined(__GNUC__) || defined(__clang__)
+#define IGEN_DEPRECATED(message) __attribute__((deprecated(message)))
+#else
+#define IGEN_DEPRECATED(message)
+#endif
+
+typedef enum {
+    IGENP_OK = 0,
+    /* The writer callback failed. */
+    IGENP_ERR_WRITE,
+    /* Malformed JSON. */
+    IGENP_ERR_SYNTAX,
+    /* A value of unexpected JSON type. */
+    IGENP_ERR_TYPE,
+    /* A number out of range of its type, or not finite. */
+    IGENP_ERR_NUMBER,
+    /* A missing key or element without a default. */
+    IGENP_ERR_MISSING,
+    /* An unknown variant name. */
+    IGENP_ERR_VARIANT,
+    /* The allocator returned NULL. */
+    IGENP_ERR_ALLOC,
+    /* Arrays and objects are nested deeper than IGEN_MAX_DEPTH. */
+    IGENP_ERR_DEPTH,
+} igenp_status;
+
+/* Decoders allocate memory only through this. */
+typedef struct {
+    void *(*alloc)(void *ctx, size_t size);
+    void (*free)(void *ctx, void *ptr);
+    void *ctx;
+} igenp_allocator;
+
+/* Encoders write JSON through this. `write` returns 0 on success. */
+typedef struct {
+    int (*write)(void *ctx, const char *data, size_t len);
+    void *ctx;
+} igenp_writer;
+
+/* UTF-8 without terminator. Decoded strings are NUL-terminated. */
+typedef struct {
+    char *data;
+    size_t len;
+} igenp_str;
+"#;
+
+const SOURCE_PRELUDE: &'static str = r#"#include <inttypes.h>
+#include <math.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#if defined(__GNUC__) || defined(__clang__)
+#pragma GCC diagnostic ignored "-Wdeprecated-declarations"
+#endif
+
+#define IGEN_TRY(expr) do { igenp_status igen_s = (expr); if (igen_s != IGENP_OK) return igen_s; } while (0)
+#define IGEN_MAX_DEPTH 64
+
+typedef struct {
+    const char *p;
+    const char *end;
+    const igenp_allocator *a;
+    unsigned depth;
+} igen_reader;
+
+/* Readers of arrays and objects enter after their opening
+ * bracket. Errors abort decoding, so only successful returns
+ * need to leave. */
+static inline igenp_status igen_enter(igen_reader *r) {
+    if (r->depth >= IGEN_MAX_DEPTH) return IGENP_ERR_DEPTH;
+    r->depth += 1;
+    return IGENP_OK;
+}
+
+static inline igenp_status igen_leave(igen_reader *r, igenp_status s) {
+    r->depth -= 1;
+    return s;
+}
+
+static inline void igen_free(const igenp_allocator *a, void *ptr) {
+    if (ptr != NULL) a->free(a->ctx, ptr);
+}
+
+/* Reallocates `data` with doubled capacity. */
+static inline void *igen_grow(const igenp_allocator *a, void *data, size_t len, size_t *cap, size_t size) {
+    size_t cap1 = *cap == 0 ? 4 : *cap * 2;
+    void *data1;
+    if (cap1 > SIZE_MAX / size) return NULL;
+    data1 = a->alloc(a->ctx, cap1 * size);
+    if (data1 == NULL) return NULL;
+    if (len > 0) memcpy(data1, data, len * size);
+    igen_free(a, data);
+    *cap = cap1;
+    return data1;
+}
+
+static inline void igen_skip_ws(igen_reader *r) {
+    while (r->p < r->end && (*r->p == ' ' || *r->p == '\t' || *r->p == '\n' || *r->p == '\r')) {
+        r->p += 1;
+    }
+}
+
+/* Consumes `c` if it comes after whitespace. */
+static inline bool igen_consume(igen_reader *r, char c) {
+    igen_skip_ws(r);
+    if (r->p < r->end && *r->p == c) {
+        r->p += 1;
+        return true;
+    }
+    return false;
+}
+
+static inline bool igen_consume_word(igen_reader *r, const char *word) {
+    size_t len = strlen(word);
+    igen_skip_ws(r);
+    if ((size_t)(r->end - r->p) >= len && memcmp(r->p, word, len) == 0) {
+        r->p += len;
+        return true;
+    }
+    return false;
+}
+
+static inline igenp_status igen_expect(igen_reader *r, char c) {
+    return igen_consume(r, c) ? IGENP_OK : IGENP_ERR_SYNTAX;
+}
+
+/* Start of a value of expected type. */
+static inline igenp_status igen_expect_type(igen_reader *r, char c) {
+    return igen_consume(r, c) ? IGENP_OK : IGENP_ERR_TYPE;
+}
+
+/* Content of a string without decoding escapes. */
+static inline igenp_status igen_parse_raw_string(igen_reader *r, const char **data, size_t *len) {
+    IGEN_TRY(igen_expect_type(r, '"'));
+    *data = r->p;
+    while (r->p < r->end && *r->p != '"') {
+        if (*r->p == '\\') r->p += 1;
+        r->p += 1;
+    }
+    if (r->p >= r->end) return IGENP_ERR_SYNTAX;
+    *len = (size_t)(r->p - *data);
+    r->p += 1;
+    return IGENP_OK;
+}
+
+static inline bool igen_raw_is(const char *data, size_t len, const char *s) {
+    return strlen(s) == len && memcmp(data, s, len) == 0;
+}
+
+static inline bool igen_parse_hex4(const char *data, size_t len, uint32_t *out) {
+    size_t i;
+    *out = 0;
+    if (len < 4) return false;
+    for (i = 0; i < 4; i++) {
+        char c = data[i];
+        *out <<= 4;
+        if (c >= '0' && c <= '9') *out |= (uint32_t)(c - '0');
+        else if (c >= 'a' && c <= 'f') *out |= (uint32_t)(c - 'a' + 10);
+        else if (c >= 'A' && c <= 'F') *out |= (uint32_t)(c - 'A' + 10);
+        else return false;
+    }
+    return true;
+}
+
+/* Decoded text is never longer than escaped text. */
+static inline igenp_status igen_parse_str(igen_reader *r, igenp_str *out) {
+    const char *raw;
+    size_t raw_len;
+    size_t i;
+    size_t n = 0;
+    char *data;
+    memset(out, 0, sizeof *out);
+    IGEN_TRY(igen_parse_raw_string(r, &raw, &raw_len));
+    data = (char *)r->a->alloc(r->a->ctx, raw_len + 1);
+    if (data == NULL) return IGENP_ERR_ALLOC;
+    for (i = 0; i < raw_len; i++) {
+        uint32_t cp;
+        char c = raw[i];
+        if ((unsigned char)c < 0x20) goto fail;
+        if (c != '\\') {
+            data[n++] = c;
+            continue;
+        }
+        i += 1;
+        switch (raw[i]) {
+        case '"': data[n++] = '"'; continue;
+        case '\\': data[n++] = '\\'; continue;
+        case '/': data[n++] = '/'; continue;
+        case 'b': data[n++] = '\b'; continue;
+        case 'f': data[n++] = '\f'; continue;
+        case 'n': data[n++] = '\n'; continue;
+        case 'r': data[n++] = '\r'; continue;
+        case 't': data[n++] = '\t'; continue;
+        case 'u': break;
+        default: goto fail;
+        }
+        if (!igen_parse_hex4(raw + i + 1, raw_len - i - 1, &cp)) goto fail;
+        i += 4;
+        if (cp >= 0xD800 && cp < 0xDC00) {
+            uint32_t low;
+            if (raw_len - i - 1 < 6 || raw[i + 1] != '\\' || raw[i + 2] != 'u') goto fail;
+            if (!igen_parse_hex4(raw + i + 3, raw_len - i - 3, &low)) goto fail;
+            if (low < 0xDC00 || low >= 0xE000) goto fail;
+            cp = 0x10000 + ((cp - 0xD800) << 10) + (low - 0xDC00);
+            i += 6;
+        }
+        else if (cp >= 0xDC00 && cp < 0xE000) {
+            goto fail;
+        }
+        if (cp < 0x80) {
+            data[n++] = (char)cp;
+        }
+        else if (cp < 0x800) {
+            data[n++] = (char)(0xC0 | (cp >> 6));
+            data[n++] = (char)(0x80 | (cp & 0x3F));
+        }
+        else if (cp < 0x10000) {
+            data[n++] = (char)(0xE0 | (cp >> 12));
+            data[n++] = (char)(0x80 | ((cp >> 6) & 0x3F));
+            data[n++] = (char)(0x80 | (cp & 0x3F));
+        }
+        else {
+            data[n++] = (char)(0xF0 | (cp >> 18));
+            data[n++] = (char)(0x80 | ((cp >> 12) & 0x3F));
+            data[n++] = (char)(0x80 | ((cp >> 6) & 0x3F));
+            data[n++] = (char)(0x80 | (cp & 0x3F));
+        }
+    }
+    data[n] = '\0';
+    out->data = data;
+    out->len = n;
+    return IGENP_OK;
+fail:
+    igen_free(r->a, data);
+    return IGENP_ERR_SYNTAX;
+}
+
+static inline igenp_status igen_parse_bool(igen_reader *r, bool *out) {
+    if (igen_consume_word(r, "true")) *out = true;
+    else if (igen_consume_word(r, "false")) *out = false;
+    else return IGENP_ERR_TYPE;
+    return IGENP_OK;
+}
+
+static inline igenp_status igen_parse_number_token(igen_reader *r, const char **data, size_t *len) {
+    igen_skip_ws(r);
+    *data = r->p;
+    while (r->p < r->end && *r->p != '\0' && strchr("+-.eE0123456789", *r->p) != NULL) {
+        r->p += 1;
+    }
+    *len = (size_t)(r->p - *data);
+    return *len == 0 ? IGENP_ERR_TYPE : IGENP_OK;
+}
+
+static inline igenp_status igen_parse_uint(igen_reader *r, uint64_t max, uint64_t *out) {
+    const char *data;
+    size_t len;
+    size_t i;
+    uint64_t v = 0;
+    IGEN_TRY(igen_parse_number_token(r, &data, &len));
+    for (i = 0; i < len; i++) {
+        uint64_t digit;
+        if (data[i] < '0' || data[i] > '9') return IGENP_ERR_NUMBER;
+        digit = (uint64_t)(data[i] - '0');
+        if (v > (max - digit) / 10) return IGENP_ERR_NUMBER;
+        v = v * 10 + digit;
+    }
+    *out = v;
+    return IGENP_OK;
+}
+
+static inline igenp_status igen_parse_int(igen_reader *r, int64_t min, int64_t max, int64_t *out) {
+    uint64_t v;
+    if (igen_consume(r, '-')) {
+        IGEN_TRY(igen_parse_uint(r, (uint64_t)(-(min + 1)) + 1, &v));
+        *out = v == 0 ? 0 : -(int64_t)(v - 1) - 1;
+        return IGENP_OK;
+    }
+    IGEN_TRY(igen_parse_uint(r, (uint64_t)max, &v));
+    *out = (int64_t)v;
+    return IGENP_OK;
+}
+
+static inline igenp_status igen_parse_double(igen_reader *r, double *out) {
+    const char *data;
+    size_t len;
+    char buf[64];
+    char *end;
+    IGEN_TRY(igen_parse_number_token(r, &data, &len));
+    if (len >= sizeof buf) return IGENP_ERR_NUMBER;
+    memcpy(buf, data, len);
+    buf[len] = '\0';
+    *out = strtod(buf, &end);
+    return end == buf + len ? IGENP_OK : IGENP_ERR_NUMBER;
+}
+
+static igenp_status igen_skip_value(igen_reader *r) {
+    igenp_status s = IGENP_OK;
+    const char *data;
+    size_t len;
+    igen_skip_ws(r);
+    if (r->p >= r->end) return IGENP_ERR_SYNTAX;
+    IGEN_TRY(igen_enter(r));
+    if (*r->p == '"') {
+        s = igen_parse_raw_string(r, &data, &len);
+    }
+    else if (igen_consume(r, '{')) {
+        if (!igen_consume(r, '}')) for (;;) {
+            if ((s = igen_parse_raw_string(r, &data, &len)) != IGENP_OK) break;
+            if ((s = igen_expect(r, ':')) != IGENP_OK) break;
+            if ((s = igen_skip_value(r)) != IGENP_OK) break;
+            if (igen_consume(r, ',')) continue;
+            s = igen_expect(r, '}');
+            break;
+        }
+    }
+    else if (igen_consume(r, '[')) {
+        if (!igen_consume(r, ']')) for (;;) {
+            if ((s = igen_skip_value(r)) != IGENP_OK) break;
+            if (igen_consume(r, ',')) continue;
+            s = igen_expect(r, ']');
+            break;
+        }
+    }
+    else if (!igen_consume_word(r, "true") && !igen_consume_word(r, "false") && !igen_consume_word(r, "null")) {
+        s = igen_parse_number_token(r, &data, &len) == IGENP_OK ? IGENP_OK : IGENP_ERR_SYNTAX;
+    }
+    return igen_leave(r, s);
+}
+
+/* Skips trailing elements of an array and its end. */
+static inline igenp_status igen_skip_rest(igen_reader *r) {
+    while (igen_consume(r, ',')) {
+        IGEN_TRY(igen_skip_value(r));
+    }
+    return igen_expect(r, ']');
+}
+
+static inline igenp_status igen_emit(const igenp_writer *w, const char *data, size_t len) {
+    if (len == 0) return IGENP_OK;
+    return w->write(w->ctx, data, len) == 0 ? IGENP_OK : IGENP_ERR_WRITE;
+}
+
+static inline igenp_status igen_emit_cstr(const igenp_writer *w, const char *s) {
+    return igen_emit(w, s, strlen(s));
+}
+
+static inline igenp_status igen_emit_key(const igenp_writer *w, bool *first, const char *key) {
+    if (!*first) IGEN_TRY(igen_emit_cstr(w, ","));
+    *first = false;
+    IGEN_TRY(igen_emit_cstr(w, "\""));
+    IGEN_TRY(igen_emit_cstr(w, key));
+    return igen_emit_cstr(w, "\":");
+}
+
+static inline igenp_status igen_emit_str(const igenp_writer *w, const igenp_str *s) {
+    size_t i;
+    size_t start = 0;
+    IGEN_TRY(igen_emit_cstr(w, "\""));
+    for (i = 0; i < s->len; i++) {
+        unsigned char c = (unsigned char)s->data[i];
+        char buf[8];
+        if (c >= 0x20 && c != '"' && c != '\\') continue;
+        IGEN_TRY(igen_emit(w, s->data + start, i - start));
+        if (c == '"') IGEN_TRY(igen_emit_cstr(w, "\\\""));
+        else if (c == '\\') IGEN_TRY(igen_emit_cstr(w, "\\\\"));
+        else if (c == '\n') IGEN_TRY(igen_emit_cstr(w, "\\n"));
+        else if (c == '\r') IGEN_TRY(igen_emit_cstr(w, "\\r"));
+        else if (c == '\t') IGEN_TRY(igen_emit_cstr(w, "\\t"));
+        else {
+            snprintf(buf, sizeof buf, "\\u%04x", (unsigned)c);
+            IGEN_TRY(igen_emit_cstr(w, buf));
+        }
+        start = i + 1;
+    }
+    if (s->len > 0) IGEN_TRY(igen_emit(w, s->data + start, s->len - start));
+    return igen_emit_cstr(w, "\"");
+}
+
+static inline igenp_status igen_emit_uint(const igenp_writer *w, uint64_t v) {
+    char buf[24];
+    snprintf(buf, sizeof buf, "%" PRIu64, v);
+    return igen_emit_cstr(w, buf);
+}
+
+static inline igenp_status igen_emit_int(const igenp_writer *w, int64_t v) {
+    char buf[24];
+    snprintf(buf, sizeof buf, "%" PRId64, v);
+    return igen_emit_cstr(w, buf);
+}
+
+/* JSON has no representation of infinity and NaN. */
+static inline igenp_status igen_emit_double(const igenp_writer *w, double v, int digits) {
+    char buf[32];
+    if (!isfinite(v)) return IGENP_ERR_NUMBER;
+    snprintf(buf, sizeof buf, "%.*g", digits, v);
+    return igen_emit_cstr(w, buf);
+}
+"#;
+
+const HANDSHAKE_SOURCE: &'static str = r#"static igenp_str igen_schema_fingerprint = { (char *)IGENP_SCHEMA_FINGERPRINT, sizeof IGENP_SCHEMA_FINGERPRINT - 1 };
+
+void igenp_igen_hello(igenp_IgenHello *hello) {
+    hello->fingerprints.data = &igen_schema_fingerprint;
+    hello->fingerprints.len = 1;
+}
+
+void igenp_igen_accept(const igenp_IgenHello *hello, igenp_IgenHelloAck *ack) {
+    size_t i;
+    memset(ack, 0, sizeof *ack);
+    for (i = 0; i < hello->fingerprints.len; i++) {
+        const igenp_str *f = &hello->fingerprints.data[i];
+        if (f->len == igen_schema_fingerprint.len && memcmp(f->data, igen_schema_fingerprint.data, f->len) == 0) {
+            ack->fingerprint.has = true;
+            ack->fingerprint.value = igen_schema_fingerprint;
+            return;
+        }
+    }
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use schema::Module;
+    use schema::codegen::GenOptions;
+
+    /// Header and source.
+    fn files() -> (String, String) {
+        let kmod: Module = serde_json::from_str(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Struct": {"name": "Size", "visibility": "Public", "fields": [
+                {"name": "w", "ty": {"Core": "I32"}, "default": true}]}},
+            {"Struct": {"name": "Shape", "visibility": "Public", "fields": [
+                {"name": "default", "ty": "String"},
+                {"name": "size", "ty": {"Item": ["p", "Size"]}, "default": true},
+                {"name": "data", "ty": {"Vec": {"Core": "U8"}}},
+                {"name": "note", "ty": {"Option": "String"}}]}},
+            {"Enum": {"name": "Command", "visibility": "Public", "variants": [
+                {"name": "Stop", "fields": []},
+                {"name": "Resize", "fields": [
+                    {"name": "", "ty": {"Item": ["p", "Size"]}},
+                    {"name": "", "ty": {"Vec": "String"}, "default": true}]}]}}]}"#).unwrap();
+        let mut files = kmod.gen_c_files(&GenOptions::new());
+        let source = files.pop().unwrap();
+        let header = files.pop().unwrap();
+        assert_eq!(header.0.to_string_lossy(), "p.h");
+        assert_eq!(source.0.to_string_lossy(), "p.c");
+        return (header.1, source.1);
+    }
+
+    #[test]
+    fn escapes_keywords_but_keeps_wire_names() {
+        let (header, source) = files();
+        assert!(header.contains(concat!(
+            "struct p_Shape {\n",
+            "    p_str default_;\n",
+            "    p_Size size;\n",
+            "    p_vec_u8 data;\n",
+            "    p_opt_str note;\n",
+            "};\n")));
+        assert!(source.contains("        if (igen_raw_is(key, key_len, \"default\")) {\n"));
+        assert!(source.contains("    IGEN_TRY(igen_emit_key(w, &first, \"default\"));\n"));
+    }
+
+    #[test]
+    fn decodes_objects_with_required_keys_and_zero_defaults() {
+        let (_, source) = files();
+        assert!(source.contains(concat!(
+            "static p_status igen_read_Shape(igen_reader *r, p_Shape *out) {\n",
+            "    bool has0 = false;\n",
+            "    bool has2 = false;\n",
+            "    memset(out, 0, sizeof *out);\n")));
+        // A repeated key replaces the value without leaking it.
+        assert!(source.contains(concat!(
+            "        else if (igen_raw_is(key, key_len, \"size\")) {\n",
+            "            igen_free_Size(&out->size, r->a);\n",
+            "            IGEN_TRY(igen_read_Size(r, &out->size));\n",
+            "        }\n")));
+        assert!(source.contains(concat!(
+            "        else {\n",
+            "            IGEN_TRY(igen_skip_value(r));\n",
+            "        }\n")));
+        assert!(source.contains(concat!(
+            "    if (!has0 || !has2) return P_ERR_MISSING;\n",
+            "    return igen_leave(r, P_OK);\n")));
+    }
+
+    #[test]
+    fn decodes_variants_with_missing_trailing_defaults() {
+        let (_, source) = files();
+        assert!(source.contains(concat!(
+            "    else if (igen_raw_is(name, name_len, \"Resize\")) {\n",
+            "        out->tag = P_COMMAND_RESIZE;\n",
+            "        if (!igen_consume(r, ',')) return P_ERR_MISSING;\n",
+            "        IGEN_TRY(igen_read_Size(r, &out->as.resize.f0));\n",
+            "        if (!igen_consume(r, ',')) return igen_leave(r, igen_expect(r, ']'));\n",
+            "        IGEN_TRY(igen_read_vec_str(r, &out->as.resize.f1));\n",
+            "        return igen_leave(r, igen_skip_rest(r));\n",
+            "    }\n",
+            "    return P_ERR_VARIANT;\n")));
+    }
+
+    #[test]
+    fn encodes_variants_as_name_and_fields() {
+        let (_, source) = files();
+        assert!(source.contains(concat!(
+            "    case P_COMMAND_RESIZE:\n",
+            "        IGEN_TRY(igen_emit_cstr(w, \"\\\"Resize\\\"\"));\n",
+            "        IGEN_TRY(igen_emit_cstr(w, \",\"));\n",
+            "        IGEN_TRY(igen_write_Size(w, &v->as.resize.f0));\n",
+            "        IGEN_TRY(igen_emit_cstr(w, \",\"));\n",
+            "        IGEN_TRY(igen_write_vec_str(w, &v->as.resize.f1));\n",
+            "        break;\n")));
+        assert!(source.contains(concat!(
+            "    if (v->note.has) {\n",
+            "        IGEN_TRY(igen_emit_key(w, &first, \"note\"));\n")));
+    }
+
+    #[test]
+    fn frees_owned_members_of_active_variants() {
+        let (_, source) = files();
+        assert!(source.contains(concat!(
+            "static void igen_free_Command(p_Command *v, const p_allocator *a) {\n",
+            "    switch (v->tag) {\n",
+            "    case P_COMMAND_STOP:\n",
+            "        break;\n",
+            "    case P_COMMAND_RESIZE:\n",
+            "        igen_free_Size(&v->as.resize.f0, a);\n",
+            "        igen_free_vec_str(&v->as.resize.f1, a);\n",
+            "        break;\n")));
+    }
+
+    #[test]
+    fn limits_nesting_in_readers() {
+        let (_, source) = files();
+        assert!(source.contains("    IGEN_TRY(igen_expect_type(r, '{'));\n    IGEN_TRY(igen_enter(r));\n"));
+        assert!(source.contains("    IGEN_TRY(igen_expect_type(r, '['));\n    IGEN_TRY(igen_enter(r));\n"));
+        assert!(source.contains("    if (r->depth >= IGEN_MAX_DEPTH) return P_ERR_DEPTH;\n"));
+    }
+}
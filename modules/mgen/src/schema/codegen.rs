@@ -6,54 +6,129 @@ use super::Item;
 use super::Type;
 use super::CoreType;
 use super::TypePath;
+use super::Visibility;
+use super::Deprecation;
+use super::ident::Lang;
+use super::ident::wire_name;
+use super::ident::string_literal;
+use super::naming::Naming;
+
+/// Options shared by all backends.
+#[derive(Debug, Clone)]
+pub struct GenOptions {
+    pub naming: Naming,
+    /// Generates `Hello`/`HelloAck` messages and the schema
+    /// fingerprint for version negotiation.
+    pub handshake: bool,
+    /// Package name or import path of generated code for
+    /// languages which need one.
+    pub package: Option<String>,
+    /// Top-level enums of messages which the documented peer
+    /// receives, for languages which describe channels.
+    pub inbound: Vec<String>,
+    /// Top-level enums of messages which the documented peer
+    /// sends.
+    pub outbound: Vec<String>,
+}
+
+impl GenOptions {
+    pub fn new() -> GenOptions {
+        return GenOptions { naming: Naming::Idiomatic, handshake: false, package: None, inbound: Vec::new(), outbound: Vec::new() };
+    }
+}
 
 impl Module {
-    pub fn gen_swift_code(&self) -> String {
+    pub fn gen_swift_code(&self, opts: &GenOptions) -> String {
         let mut ss = Vec::<String>::new();
         for kitem in &self.items {
-            ss.push(kitem.gen_swift_code());
+            if kitem.visibility() != Visibility::Public { continue }
+            ss.push(kitem.gen_swift_code(opts));
         }
         return ss.join("\n\n\n");
     }
 }
+impl Module {
+    /// Fingerprint of this module as a root.
+    pub fn gen_swift_fingerprint(&self) -> String {
+        let mut s = String::new();
+        s.push_str("enum IgenSchema {\n");
+        s.push_str("    static let fingerprint = ");
+        s.push_str(&string_literal(&self.fingerprint()));
+        s.push_str("\n");
+        s.push_str("}\n");
+        return s;
+    }
+    /// Version negotiation messages for this module as a root.
+    ///
+    /// A peer sends `Hello` with fingerprints of schemas it can
+    /// speak, preferred one first. The other peer replies
+    /// `HelloAck` with the fingerprint it chose, or `nil` to refuse.
+    pub fn gen_swift_handshake(&self) -> String {
+        let mut s = String::new();
+        s.push_str("enum IgenHandshake {\n");
+        s.push_str("    static let fingerprint = IgenSchema.fingerprint\n");
+        s.push_str("    struct Hello: Codable {\n");
+        s.push_str("        var fingerprints: Array<String>\n");
+        s.push_str("    }\n");
+        s.push_str("    struct HelloAck: Codable {\n");
+        s.push_str("        var fingerprint: Optional<String>\n");
+        s.push_str("    }\n");
+        s.push_str("    static func hello() -> Hello {\n");
+        s.push_str("        return Hello(fingerprints: [fingerprint])\n");
+        s.push_str("    }\n");
+        s.push_str("    static func accept(_ hello: Hello) -> HelloAck {\n");
+        s.push_str("        let ok = hello.fingerprints.contains(fingerprint)\n");
+        s.push_str("        return HelloAck(fingerprint: ok ? fingerprint : nil)\n");
+        s.push_str("    }\n");
+        s.push_str("}\n");
+        return s;
+    }
+}
 impl Item {
-    pub fn gen_swift_code(&self) -> String {
+    pub fn gen_swift_code(&self, opts: &GenOptions) -> String {
         let mut s = String::new();
         match self {
             Item::Module(ref kmod) => {
-                s.push_str(&kmod.gen_swift_code());
+                s.push_str(&kmod.gen_swift_code(opts));
             },
             Item::Enum(ref kenum) => {
-                s.push_str(&kenum.gen_swift_code());
+                s.push_str(&kenum.gen_swift_code(opts));
             },
             Item::Struct(ref kstruct) => {
-                s.push_str(&kstruct.gen_swift_code());
+                s.push_str(&kstruct.gen_swift_code(opts));
             },
         }
         return s;
     }
 }
 impl Enum {
-    pub fn gen_swift_code(&self) -> String {
+    pub fn gen_swift_code(&self, opts: &GenOptions) -> String {
         let mut s = String::new();
+        s.push_str(&swift_deprecation(&self.deprecation, ""));
         s.push_str("enum ");
-        s.push_str(&self.name);
+        s.push_str(&swift_ident(&self.name));
         s.push_str(": Codable {\n");
-        s.push_str(&self.gen_members());
-        s.push_str(&self.gen_decoding());
-        s.push_str(&self.gen_encoding());
+        s.push_str(&swift_fingerprint_member(&self.fingerprint()));
+        s.push_str(&self.gen_members(opts));
+        s.push_str(&self.gen_decoding(opts));
+        s.push_str(&self.gen_encoding(opts));
         s.push_str("}\n");
         return s;
     }
 }
 impl Struct {
-    pub fn gen_swift_code(&self) -> String {
+    pub fn gen_swift_code(&self, opts: &GenOptions) -> String {
         let mut s = String::new();
+        s.push_str(&swift_deprecation(&self.deprecation, ""));
         s.push_str("struct ");
-        s.push_str(&self.name);
+        s.push_str(&swift_ident(&self.name));
         s.push_str(": Codable {\n");
-        s.push_str(&self.gen_members());
+        s.push_str(&swift_fingerprint_member(&self.fingerprint()));
+        s.push_str(&self.gen_members(opts));
+        s.push_str(&self.gen_default_value(opts));
+        s.push_str(&self.gen_coding_keys(opts));
         s.push_str("}\n");
+        s.push_str(&self.gen_codec_extension(opts));
         return s;
     }
 }
@@ -68,11 +143,12 @@ impl Struct {
 
 
 impl Enum {
-    fn gen_members(&self) -> String {
+    fn gen_members(&self, opts: &GenOptions) -> String {
         let mut s = String::new();
         for kvariant in &self.variants {
+            s.push_str(&swift_deprecation(&kvariant.deprecation, "    "));
             s.push_str("    case ");
-            s.push_str(&kvariant.name);
+            s.push_str(&Lang::Swift.variant_name(opts.naming, &kvariant.name));
             if kvariant.fields.len() > 0 {
                 s.push_str("(");
             }
@@ -82,7 +158,7 @@ impl Enum {
                     ss.push(kfield.ty.gen_swift_code());
                 }
                 else {
-                    ss.push([&kfield.name, ": ", &kfield.ty.gen_swift_code()].join(""));
+                    ss.push([&Lang::Swift.field_name(opts.naming, &kfield.name), ": ", &kfield.ty.gen_swift_code()].join(""));
                 }
             }
             s.push_str(&ss.join(", "));
@@ -93,14 +169,24 @@ impl Enum {
         }
         return s;
     }
-    fn gen_encoding(&self) -> String {
+    /// Codec of an enum with deprecated variants refers to them, so
+    /// it is deprecated too. Generic calls through `Codable` do
+    /// not warn.
+    fn gen_codec_deprecation(&self, indent: &str) -> String {
+        if self.deprecation.is_none() && self.variants.iter().any(|kvariant| kvariant.deprecation.is_some()) {
+            return [indent, SWIFT_CODEC_DEPRECATION].join("");
+        }
+        return String::new();
+    }
+    fn gen_encoding(&self, opts: &GenOptions) -> String {
         let mut s = String::new();
+        s.push_str(&self.gen_codec_deprecation("    "));
         s.push_str("    func encode(to encoder: Encoder) throws {\n");
         s.push_str("        var c = encoder.unkeyedContainer()\n");
         s.push_str("        switch self {\n");
         for kvariant in &self.variants {
             s.push_str("        case .");
-            s.push_str(&kvariant.name);
+            s.push_str(&Lang::Swift.variant_name(opts.naming, &kvariant.name));
             if kvariant.fields.len() > 0 {
                 s.push_str("(");
             }
@@ -116,7 +202,7 @@ impl Enum {
                 s.push_str("):\n");
             }
             s.push_str("            try c.encode(\"");
-            s.push_str(&kvariant.name);
+            s.push_str(wire_name(&kvariant.name));
             s.push_str("\")\n");
             for i in 0..kvariant.fields.len() {
                 s.push_str("            try c.encode(f");
@@ -128,18 +214,19 @@ impl Enum {
         s.push_str("    }\n");
         return s;
     }
-    fn gen_decoding(&self) -> String {
+    fn gen_decoding(&self, opts: &GenOptions) -> String {
         let mut s = String::new();
+        s.push_str(&self.gen_codec_deprecation("    "));
         s.push_str("    init(from decoder: Decoder) throws {\n");
         s.push_str("        var dec = try decoder.unkeyedContainer()\n");
         s.push_str("        let n = try dec.decode(String.self)\n");
         s.push_str("        switch n {\n");
         for kvariant in &self.variants {
             s.push_str("        case \"");
-            s.push_str(&kvariant.name);
+            s.push_str(wire_name(&kvariant.name));
             s.push_str("\":\n");
             s.push_str("            self = .");
-            s.push_str(&kvariant.name);
+            s.push_str(&Lang::Swift.variant_name(opts.naming, &kvariant.name));
             if kvariant.fields.len() > 0 {
                 s.push_str("(");
             }
@@ -147,7 +234,19 @@ impl Enum {
             let mut c = 0;
             for kfield in &kvariant.fields {
                 c += 1;
-                s.push_str("                try dec.decode(");
+                s.push_str("                ");
+                if kfield.name != "" {
+                    s.push_str(&Lang::Swift.field_name(opts.naming, &kfield.name));
+                    s.push_str(": ");
+                }
+                // `try` must come first to cover the whole expression.
+                s.push_str("try ");
+                if kfield.default {
+                    s.push_str("dec.isAtEnd ? ");
+                    s.push_str(&kfield.ty.gen_swift_default_value());
+                    s.push_str(" : ");
+                }
+                s.push_str("dec.decode(");
                 s.push_str(&kfield.ty.gen_swift_code());
                 s.push_str(".self)");
                 let delimeter = if c < kvariant.fields.len() { "," } else { ")" };
@@ -165,31 +264,154 @@ impl Enum {
 
 
 impl Struct {
-    fn gen_members(&self) -> String {
+    fn gen_members(&self, opts: &GenOptions) -> String {
         let mut s = String::new();
         for kfield in &self.fields {
+            s.push_str(&swift_deprecation(&kfield.deprecation, "    "));
             s.push_str("    ");
             s.push_str("var "); 
-            s.push_str(&kfield.name);
+            s.push_str(&Lang::Swift.field_name(opts.naming, &kfield.name));
             s.push_str(&": ");
             s.push_str(&kfield.ty.gen_swift_code());
             s.push_str("\n");
         }
         return s;
     }
-    fn gen_encoding(&self) -> String {
+    /// Instance with all fields set to their defaults.
+    /// Fields of other structs refer this for their defaults.
+    fn gen_default_value(&self, opts: &GenOptions) -> String {
+        let mut s = String::new();
+        if !self.has_default() {
+            return s;
+        }
+        s.push_str("    static var igenDefault: ");
+        s.push_str(&swift_ident(&self.name));
+        s.push_str(" {\n");
+        s.push_str("        return ");
+        s.push_str(&swift_ident(&self.name));
+        s.push_str("(");
+        let mut ss = Vec::<String>::new();
+        for kfield in &self.fields {
+            ss.push([&Lang::Swift.field_name(opts.naming, &kfield.name), ": ", &kfield.ty.gen_swift_default_value()].join(""));
+        }
+        s.push_str(&ss.join(", "));
+        s.push_str(")\n");
+        s.push_str("    }\n");
+        return s;
+    }
+    /// Maps Swift property names to wire names.
+    /// Synthesized `Codable` uses property names as keys, so this
+    /// is needed only if they differ, or the decoder is written
+    /// manually for defaults.
+    fn gen_coding_keys(&self, opts: &GenOptions) -> String {
+        let mut s = String::new();
+        let renamed = self.fields.iter().any(|kfield| {
+            let name = Lang::Swift.field_name(opts.naming, &kfield.name);
+            return name.trim_matches('`') != wire_name(&kfield.name);
+        });
+        let defaulted = self.fields.iter().any(|kfield| kfield.default);
+        if !renamed && !defaulted && !self.has_deprecated_fields() {
+            return s;
+        }
+        s.push_str("    enum CodingKeys: String, CodingKey {\n");
+        for kfield in &self.fields {
+            s.push_str("        case ");
+            s.push_str(&Lang::Swift.field_name(opts.naming, &kfield.name));
+            s.push_str(" = \"");
+            s.push_str(wire_name(&kfield.name));
+            s.push_str("\"\n");
+        }
+        s.push_str("    }\n");
+        return s;
+    }
+    fn has_deprecated_fields(&self) -> bool {
+        return self.fields.iter().any(|kfield| kfield.deprecation.is_some());
+    }
+    /// Decoder which fills missing keys with defaults.
+    /// Unknown keys are ignored like synthesized one.
+    /// This is in an extension to keep the memberwise initializer.
+    ///
+    /// Codec of a struct with deprecated fields is written
+    /// manually and deprecated, so consumers get no warnings from
+    /// generated code. Generic calls through `Codable` do not warn.
+    fn gen_codec_extension(&self, opts: &GenOptions) -> String {
+        let mut s = String::new();
+        let defaulted = self.fields.iter().any(|kfield| kfield.default);
+        let deprecated = self.deprecation.is_some() || self.has_deprecated_fields();
+        if !defaulted && !self.has_deprecated_fields() {
+            return s;
+        }
+        if deprecated {
+            s.push_str(SWIFT_CODEC_DEPRECATION);
+        }
+        s.push_str("extension ");
+        s.push_str(&swift_ident(&self.name));
+        s.push_str(" {\n");
+        s.push_str("    init(from decoder: Decoder) throws {\n");
+        s.push_str("        let c = try decoder.container(keyedBy: CodingKeys.self)\n");
+        for kfield in &self.fields {
+            let name = Lang::Swift.field_name(opts.naming, &kfield.name);
+            s.push_str("        ");
+            s.push_str(&name);
+            if kfield.default {
+                s.push_str(" = try c.decodeIfPresent(");
+                s.push_str(&kfield.ty.gen_swift_code());
+                s.push_str(".self, forKey: .");
+                s.push_str(&name);
+                s.push_str(") ?? ");
+                s.push_str(&kfield.ty.gen_swift_default_value());
+            }
+            else {
+                s.push_str(" = try c.decode(");
+                s.push_str(&kfield.ty.gen_swift_code());
+                s.push_str(".self, forKey: .");
+                s.push_str(&name);
+                s.push_str(")");
+            }
+            s.push_str("\n");
+        }
+        s.push_str("    }\n");
+        if self.has_deprecated_fields() {
+            s.push_str(&self.gen_keyed_encoding(opts));
+        }
+        s.push_str("}\n");
+        return s;
+    }
+    /// Same with synthesized one. Absent options are omitted.
+    fn gen_keyed_encoding(&self, opts: &GenOptions) -> String {
+        let mut s = String::new();
+        s.push_str("    func encode(to encoder: Encoder) throws {\n");
+        s.push_str("        var c = encoder.container(keyedBy: CodingKeys.self)\n");
+        for kfield in &self.fields {
+            let name = Lang::Swift.field_name(opts.naming, &kfield.name);
+            let method = match kfield.ty {
+                Type::Option(_) => "encodeIfPresent",
+                _ => "encode",
+            };
+            s.push_str("        try c.");
+            s.push_str(method);
+            s.push_str("(");
+            s.push_str(&name);
+            s.push_str(", forKey: .");
+            s.push_str(&name);
+            s.push_str(")\n");
+        }
+        s.push_str("    }\n");
+        return s;
+    }
+    fn gen_encoding(&self, opts: &GenOptions) -> String {
         let mut s = String::new();
         s.push_str("func toJSON(_ v: ");
-        s.push_str(&self.name);
+        s.push_str(&swift_ident(&self.name));
         s.push_str(") -> Any {\n");
         s.push_str("    return [\n");
         for ref field in &self.fields {
             s.push_str("            ");
             s.push_str("\"");
-            s.push_str(&field.name);
+            s.push_str(wire_name(&field.name));
             s.push_str("\"");
             s.push_str(": toJSON(v.");
-            s.push_str(&field.name);
+            s.push_str(&Lang::Swift.field_name(opts.naming, &field.name));
             s.push_str("),");
             s.push_str("\n");
         }
@@ -197,22 +419,22 @@ impl Struct {
         s.push_str("}\n");
         return s;
     }
-    fn gen_decoding(&self) -> String {
+    fn gen_decoding(&self, opts: &GenOptions) -> String {
         let mut s = String::new();
         s.push_str("func fromJSON(_ j: Any) -> ");
-        s.push_str(&self.name);
+        s.push_str(&swift_ident(&self.name));
         s.push_str(" {\n");
         s.push_str("    let o = j as! [String: Any]\n");
         s.push_str("    let r = ");
-        s.push_str(&self.name);
+        s.push_str(&swift_ident(&self.name));
         s.push_str("(\n");
         let mut s1s = Vec::<String>::new();
         for ref field in &self.fields {
             let mut s1 = String::new();
             s1.push_str("        ");
-            s1.push_str(&field.name);
+            s1.push_str(&Lang::Swift.field_name(opts.naming, &field.name));
             s1.push_str(": fromJSON(o[\"");
-            s1.push_str(&field.name);
+            s1.push_str(wire_name(&field.name));
             s1.push_str("\"]!)");
             s1s.push(s1);
         }
@@ -255,8 +477,168 @@ impl Type {
     }
 }
 
+impl Type {
+    /// Swift expression of `Default::default()` of this type.
+    pub fn gen_swift_default_value(&self) -> String {
+        return match self {
+            Type::Core(CoreType::Bool) => "false".to_string(),
+            Type::Core(_) => "0".to_string(),
+            Type::String => "\"\"".to_string(),
+            Type::Item(ref path) => [&path.gen_swift_code(), ".igenDefault"].join(""),
+            Type::Option(_) => "nil".to_string(),
+            Type::Vec(_) => "[]".to_string(),
+        };
+    }
+}
+
 impl TypePath {
     pub fn gen_swift_code(&self) -> String {
-        return self.0.last().unwrap_or(&"????".to_string()).to_string();
+        return match self.0.last() {
+            Some(ref name) => swift_ident(name),
+            None => "????".to_string(),
+        };
+    }
+}
+
+/// Attribute of generated codecs which refer to deprecated members.
+const SWIFT_CODEC_DEPRECATION: &'static str = "@available(*, deprecated, message: \"Generated codec refers to deprecated members.\")\n";
+
+/// `@available` line with `indent`, or empty string.
+fn swift_deprecation(deprecation: &Option<Deprecation>, indent: &str) -> String {
+    let mut s = String::new();
+    let deprecation = match deprecation {
+        Some(ref deprecation) => deprecation,
+        None => return s,
+    };
+    s.push_str(indent);
+    s.push_str("@available(*, deprecated");
+    if let Some(ref message) = deprecation_message(deprecation) {
+        s.push_str(", message: ");
+        s.push_str(&string_literal(message));
+    }
+    s.push_str(")\n");
+    return s;
+}
+
+/// Note with version, or `None` if both are missing.
+/// Languages without a slot for version get it in the message.
+pub fn deprecation_message(deprecation: &Deprecation) -> Option<String> {
+    return match (&deprecation.note, &deprecation.since) {
+        (Some(ref note), Some(ref since)) => Some(format!("{} (since {})", note, since)),
+        (Some(ref note), None) => Some(note.clone()),
+        (None, Some(ref since)) => Some(format!("since {}", since)),
+        (None, None) => None,
+    };
+}
+
+/// Static members are not encoded, so this does not affect
+/// synthesized `Codable`.
+fn swift_fingerprint_member(fingerprint: &str) -> String {
+    return ["    static let igenFingerprint = \"", fingerprint, "\"\n"].join("");
+}
+
+fn swift_ident(name: &str) -> String {
+    return Lang::Swift.escape_ident(name);
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use schema::Module;
+    use schema::Item;
+    use schema::codegen::GenOptions;
+    use schema::tests::sample_module;
+
+    #[test]
+    fn embeds_fingerprints_of_items_and_root() {
+        let kmod = sample_module();
+        let code = kmod.gen_swift_code(&GenOptions::new());
+        for kitem in &kmod.items {
+            let (name, fingerprint) = match kitem {
+                Item::Struct(ref kstruct) => (&kstruct.name, kstruct.fingerprint()),
+                Item::Enum(ref kenum) => (&kenum.name, kenum.fingerprint()),
+                Item::Module(_) => continue,
+            };
+            assert!(code.contains(&[": Codable {\n    static let igenFingerprint = \"", &fingerprint, "\"\n"].join("")), "{}", name);
+        }
+        assert_eq!(kmod.gen_swift_fingerprint(), ["enum IgenSchema {\n    static let fingerprint = \"", &kmod.fingerprint(), "\"\n}\n"].join(""));
+    }
+
+    #[test]
+    fn decodes_missing_fields_with_defaults() {
+        let kmod: Module = serde_json::from_str(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Struct": {"name": "Size", "visibility": "Public", "fields": [
+                {"name": "w", "ty": {"Core": "I32"}, "default": true}]}},
+            {"Struct": {"name": "Rect", "visibility": "Public", "fields": [
+                {"name": "name", "ty": "String"},
+                {"name": "size", "ty": {"Item": ["p", "Size"]}, "default": true},
+                {"name": "tags", "ty": {"Vec": "String"}, "default": true}]}}]}"#).unwrap();
+        let code = kmod.gen_swift_code(&GenOptions::new());
+        assert!(code.contains(concat!(
+            "    static var igenDefault: Size {\n",
+            "        return Size(w: 0)\n",
+            "    }\n")));
+        assert!(code.contains(concat!(
+            "extension Rect {\n",
+            "    init(from decoder: Decoder) throws {\n",
+            "        let c = try decoder.container(keyedBy: CodingKeys.self)\n",
+            "        name = try c.decode(String.self, forKey: .name)\n",
+            "        size = try c.decodeIfPresent(Size.self, forKey: .size) ?? Size.igenDefault\n",
+            "        tags = try c.decodeIfPresent(Array<String>.self, forKey: .tags) ?? []\n",
+            "    }\n",
+            "}\n")));
+    }
+
+    #[test]
+    fn indents_codecs_of_enums_without_deprecated_variants() {
+        let code = sample_module().gen_swift_code(&GenOptions::new());
+        assert!(code.contains("\n    init(from decoder: Decoder) throws {\n"));
+        assert!(code.contains("\n    func encode(to encoder: Encoder) throws {\n"));
+        assert!(!code.contains("        init(from decoder"));
+        assert!(!code.contains("        func encode(to encoder"));
+        assert!(!code.contains("@available"));
+    }
+
+    #[test]
+    fn deprecates_codecs_which_refer_to_deprecated_members() {
+        let kmod: Module = serde_json::from_str(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Enum": {"name": "Message", "visibility": "Public", "variants": [
+                {"name": "Ping", "fields": []},
+                {"name": "Pong", "fields": [], "deprecation": {"note": "use Ping", "since": "1.2"}}]}},
+            {"Struct": {"name": "Point", "visibility": "Public", "fields": [
+                {"name": "x", "ty": {"Core": "I32"}},
+                {"name": "label", "ty": {"Option": "String"}, "deprecation": {"note": null, "since": null}}]}},
+            {"Enum": {"name": "Old", "visibility": "Public", "deprecation": {"note": null, "since": "1.0"}, "variants": [
+                {"name": "Gone", "fields": [], "deprecation": {"note": null, "since": null}}]}}]}"#).unwrap();
+        let code = kmod.gen_swift_code(&GenOptions::new());
+        let codec = "    @available(*, deprecated, message: \"Generated codec refers to deprecated members.\")\n";
+        assert!(code.contains(concat!(
+            "    case ping\n",
+            "    @available(*, deprecated, message: \"use Ping (since 1.2)\")\n",
+            "    case pong\n")));
+        assert!(code.contains(&[codec, "    init(from decoder: Decoder) throws {\n"].join("")));
+        assert!(code.contains(&[codec, "    func encode(to encoder: Encoder) throws {\n"].join("")));
+        assert!(code.contains(concat!(
+            "    @available(*, deprecated)\n",
+            "    var label: Optional<String>\n")));
+        assert!(code.contains(concat!(
+            "@available(*, deprecated, message: \"Generated codec refers to deprecated members.\")\n",
+            "extension Point {\n",
+            "    init(from decoder: Decoder) throws {\n",
+            "        let c = try decoder.container(keyedBy: CodingKeys.self)\n",
+            "        x = try c.decode(Int32.self, forKey: .x)\n",
+            "        label = try c.decode(Optional<String>.self, forKey: .label)\n",
+            "    }\n",
+            "    func encode(to encoder: Encoder) throws {\n",
+            "        var c = encoder.container(keyedBy: CodingKeys.self)\n",
+            "        try c.encode(x, forKey: .x)\n",
+            "        try c.encodeIfPresent(label, forKey: .label)\n",
+            "    }\n",
+            "}\n")));
+        // A deprecated enum warns by itself, so its codec is not marked.
+        assert!(code.contains(concat!(
+            "@available(*, deprecated, message: \"since 1.0\")\n",
+            "enum Old: Codable {\n")));
+        assert_eq!(code.matches(codec).count(), 2);
     }
 }
@@ -0,0 +1,453 @@
+
+use std::collections::BTreeMap;
+use std::fmt;
+use super::Module;
+use super::Item;
+use super::StructField;
+use super::Type;
+use super::CoreType;
+use super::Visibility;
+
+/// Compatibility of a change with peers built from the old schema.
+///
+/// - Old readers: old peers decoding data encoded by new peers.
+/// - Old writers: new peers decoding data encoded by old peers.
+///
+/// This follows the JSON wire format. Item names and field
+/// names of enum variants do not appear on the wire, so renaming
+/// them is compatible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Compat {
+    Compatible,
+    Breaking,
+}
+
+#[derive(Debug, Clone)]
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ChangeKind {
+    ItemAdded,
+    ItemRemoved,
+    ItemRenamed { from: Vec<String> },
+    /// Changed from `struct` to `enum` or vice versa.
+    ItemKindChanged,
+    FieldAdded { field: String, ty: String },
+    FieldRemoved { field: String, ty: String },
+    FieldRenamed { from: String, to: String },
+    FieldTypeChanged { field: String, from: String, to: String },
+    VariantAdded { variant: String },
+    VariantRemoved { variant: String },
+    /// A variant or field reuses a name reserved in the old
+    /// schema. Peers older than the reservation may still use the
+    /// name with a different payload.
+    ReservedNameReused { name: String },
+}
+
+#[derive(Debug, Clone)]
+#[derive(Serialize)]
+pub struct Change {
+    /// Path to the changed item in the new schema, or in the old
+    /// schema for removed items. Includes variant name for changes
+    /// in variant fields.
+    pub path: Vec<String>,
+    #[serde(flatten)]
+    pub kind: ChangeKind,
+    pub old_readers: Compat,
+    pub old_writers: Compat,
+}
+
+#[derive(Debug, Clone)]
+#[derive(Serialize)]
+pub struct Report {
+    pub changes: Vec<Change>,
+}
+
+impl Report {
+    pub fn is_breaking(&self) -> bool {
+        return self.changes.iter().any(|change| {
+            return change.old_readers == Compat::Breaking || change.old_writers == Compat::Breaking;
+        });
+    }
+}
+
+type Renames = BTreeMap<Vec<String>, Vec<String>>;
+
+impl Module {
+    /// Classifies all changes from `old` to `self`.
+    /// Only exported items are compared.
+    pub fn compare(&self, old: &Module) -> Report {
+        let mut old_items = BTreeMap::<Vec<String>, &Item>::new();
+        let mut new_items = BTreeMap::<Vec<String>, &Item>::new();
+        collect_items(old, &mut Vec::new(), &mut old_items);
+        collect_items(self, &mut Vec::new(), &mut new_items);
+
+        // Removed and added items with same definition are renames.
+        let mut renames = Renames::new();
+        for (old_path, old_item) in &old_items {
+            if new_items.contains_key(old_path) { continue }
+            for (new_path, new_item) in &new_items {
+                if old_items.contains_key(new_path) { continue }
+                if renames.values().any(|path| path == new_path) { continue }
+                if same_definition(old_item, new_item) {
+                    renames.insert(old_path.clone(), new_path.clone());
+                    break;
+                }
+            }
+        }
+
+        let mut changes = Vec::<Change>::new();
+        for (old_path, old_item) in &old_items {
+            if let Some(new_path) = renames.get(old_path) {
+                let kind = ChangeKind::ItemRenamed { from: old_path.clone() };
+                changes.push(change(new_path, kind, Compat::Compatible, Compat::Compatible));
+                continue;
+            }
+            match new_items.get(old_path) {
+                None => {
+                    // New peers never write it, but cannot read it.
+                    changes.push(change(old_path, ChangeKind::ItemRemoved, Compat::Compatible, Compat::Breaking));
+                },
+                Some(new_item) => {
+                    compare_items(old_path, old_item, new_item, &renames, &mut changes);
+                },
+            }
+        }
+        for new_path in new_items.keys() {
+            if old_items.contains_key(new_path) { continue }
+            if renames.values().any(|path| path == new_path) { continue }
+            changes.push(change(new_path, ChangeKind::ItemAdded, Compat::Compatible, Compat::Compatible));
+        }
+        return Report { changes: changes };
+    }
+}
+
+fn change(path: &Vec<String>, kind: ChangeKind, old_readers: Compat, old_writers: Compat) -> Change {
+    return Change { path: path.clone(), kind: kind, old_readers: old_readers, old_writers: old_writers };
+}
+
+fn collect_items<'a>(kmod: &'a Module, path: &mut Vec<String>, map: &mut BTreeMap<Vec<String>, &'a Item>) {
+    if kmod.visibility != Visibility::Public { return }
+    path.push(kmod.name.clone());
+    for kitem in &kmod.items {
+        if kitem.visibility() != Visibility::Public { continue }
+        let name = match kitem {
+            Item::Module(ref kmod1) => {
+                collect_items(kmod1, path, map);
+                continue;
+            },
+            Item::Enum(ref kenum) => &kenum.name,
+            Item::Struct(ref kstruct) => &kstruct.name,
+        };
+        let mut item_path = path.clone();
+        item_path.push(name.clone());
+        map.insert(item_path, kitem);
+    }
+    path.pop();
+}
+
+fn same_definition(old: &Item, new: &Item) -> bool {
+    return match (old, new) {
+        (Item::Enum(ref old), Item::Enum(ref new)) => old.variants == new.variants,
+        (Item::Struct(ref old), Item::Struct(ref new)) => old.fields == new.fields,
+        _ => false,
+    };
+}
+
+fn compare_items(path: &Vec<String>, old: &Item, new: &Item, renames: &Renames, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Item::Struct(ref old), Item::Struct(ref new)) => {
+            compare_struct_fields(path, &old.fields, &new.fields, renames, changes);
+            let names = new.fields.iter().map(|f| &f.name).collect::<Vec<&String>>();
+            compare_reserved(path, &old.reserved, &names, changes);
+        },
+        (Item::Enum(ref old), Item::Enum(ref new)) => {
+            for old_variant in &old.variants {
+                let mut variant_path = path.clone();
+                variant_path.push(old_variant.name.clone());
+                match new.variants.iter().find(|v| v.name == old_variant.name) {
+                    None => {
+                        let kind = ChangeKind::VariantRemoved { variant: old_variant.name.clone() };
+                        changes.push(change(path, kind, Compat::Compatible, Compat::Breaking));
+                    },
+                    Some(new_variant) => {
+                        compare_variant_fields(&variant_path, &old_variant.fields, &new_variant.fields, renames, changes);
+                    },
+                }
+            }
+            for new_variant in &new.variants {
+                if old.variants.iter().any(|v| v.name == new_variant.name) { continue }
+                // Old decoders fail on unknown variants.
+                let kind = ChangeKind::VariantAdded { variant: new_variant.name.clone() };
+                changes.push(change(path, kind, Compat::Breaking, Compat::Compatible));
+            }
+            let names = new.variants.iter().map(|v| &v.name).collect::<Vec<&String>>();
+            compare_reserved(path, &old.reserved, &names, changes);
+        },
+        _ => {
+            changes.push(change(path, ChangeKind::ItemKindChanged, Compat::Breaking, Compat::Breaking));
+        },
+    }
+}
+
+fn compare_reserved(path: &Vec<String>, old_reserved: &[String], new_names: &[&String], changes: &mut Vec<Change>) {
+    for name in new_names {
+        if !old_reserved.contains(name) { continue }
+        let kind = ChangeKind::ReservedNameReused { name: name.to_string() };
+        changes.push(change(path, kind, Compat::Breaking, Compat::Breaking));
+    }
+}
+
+/// Struct fields are keyed by name on the wire.
+/// Decoders ignore unknown keys, and accept missing keys only
+/// for `Option` fields and fields with defaults.
+fn compare_struct_fields(path: &Vec<String>, old: &[StructField], new: &[StructField], renames: &Renames, changes: &mut Vec<Change>) {
+    for old_field in old {
+        match new.iter().find(|f| f.name == old_field.name) {
+            None => {
+                let old_readers = if can_be_missing(old_field) { Compat::Compatible } else { Compat::Breaking };
+                let kind = ChangeKind::FieldRemoved { field: old_field.name.clone(), ty: old_field.ty.to_string() };
+                changes.push(change(path, kind, old_readers, Compat::Compatible));
+            },
+            Some(new_field) => {
+                if same_type(&old_field.ty, &new_field.ty, renames) { continue }
+                let (old_readers, old_writers) = type_change_compat(&old_field.ty, &new_field.ty, renames);
+                let kind = ChangeKind::FieldTypeChanged { field: old_field.name.clone(), from: old_field.ty.to_string(), to: new_field.ty.to_string() };
+                changes.push(change(path, kind, old_readers, old_writers));
+            },
+        }
+    }
+    for new_field in new {
+        if old.iter().any(|f| f.name == new_field.name) { continue }
+        let old_writers = if can_be_missing(new_field) { Compat::Compatible } else { Compat::Breaking };
+        let kind = ChangeKind::FieldAdded { field: new_field.name.clone(), ty: new_field.ty.to_string() };
+        changes.push(change(path, kind, Compat::Compatible, old_writers));
+    }
+}
+
+/// Enum variant fields are positional on the wire.
+/// Decoders ignore trailing elements, but fail on missing ones
+/// unless they have defaults.
+fn compare_variant_fields(path: &Vec<String>, old: &[StructField], new: &[StructField], renames: &Renames, changes: &mut Vec<Change>) {
+    for i in 0..old.len().min(new.len()) {
+        let old_field = &old[i];
+        let new_field = &new[i];
+        if old_field.name != new_field.name {
+            let kind = ChangeKind::FieldRenamed { from: field_label(old_field, i), to: field_label(new_field, i) };
+            changes.push(change(path, kind, Compat::Compatible, Compat::Compatible));
+        }
+        if !same_type(&old_field.ty, &new_field.ty, renames) {
+            let (old_readers, old_writers) = type_change_compat(&old_field.ty, &new_field.ty, renames);
+            let kind = ChangeKind::FieldTypeChanged { field: field_label(new_field, i), from: old_field.ty.to_string(), to: new_field.ty.to_string() };
+            changes.push(change(path, kind, old_readers, old_writers));
+        }
+    }
+    for i in new.len()..old.len() {
+        let old_readers = if old[i].default { Compat::Compatible } else { Compat::Breaking };
+        let kind = ChangeKind::FieldRemoved { field: field_label(&old[i], i), ty: old[i].ty.to_string() };
+        changes.push(change(path, kind, old_readers, Compat::Compatible));
+    }
+    for i in old.len()..new.len() {
+        let old_writers = if new[i].default { Compat::Compatible } else { Compat::Breaking };
+        let kind = ChangeKind::FieldAdded { field: field_label(&new[i], i), ty: new[i].ty.to_string() };
+        changes.push(change(path, kind, Compat::Compatible, old_writers));
+    }
+}
+
+fn field_label(kfield: &StructField, index: usize) -> String {
+    return if kfield.name == "" { index.to_string() } else { kfield.name.clone() };
+}
+
+fn can_be_missing(kfield: &StructField) -> bool {
+    return kfield.default || is_optional(&kfield.ty);
+}
+
+fn is_optional(ty: &Type) -> bool {
+    return match ty {
+        Type::Option(_) => true,
+        _ => false,
+    };
+}
+
+/// `T` -> `Option<T>` can be read by new peers, but old peers
+/// cannot read `null`. `Option<T>` -> `T` is the opposite.
+fn type_change_compat(old: &Type, new: &Type, renames: &Renames) -> (Compat, Compat) {
+    if let Type::Option(ref new1) = new {
+        if same_type(old, new1, renames) {
+            return (Compat::Breaking, Compat::Compatible);
+        }
+    }
+    if let Type::Option(ref old1) = old {
+        if same_type(old1, new, renames) {
+            return (Compat::Compatible, Compat::Breaking);
+        }
+    }
+    return (Compat::Breaking, Compat::Breaking);
+}
+
+fn same_type(old: &Type, new: &Type, renames: &Renames) -> bool {
+    return match (old, new) {
+        (Type::Core(ref old), Type::Core(ref new)) => old == new,
+        (Type::String, Type::String) => true,
+        (Type::Option(ref old), Type::Option(ref new)) => same_type(old, new, renames),
+        (Type::Vec(ref old), Type::Vec(ref new)) => same_type(old, new, renames),
+        (Type::Item(ref old), Type::Item(ref new)) => {
+            let old_path = renames.get(&old.0).unwrap_or(&old.0);
+            old_path == &new.0
+        },
+        _ => false,
+    };
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Core(ref core_type) => write!(f, "{}", core_type),
+            Type::String => write!(f, "String"),
+            Type::Item(ref path) => write!(f, "{}", path.0.join("::")),
+            Type::Option(ref ty) => write!(f, "Option<{}>", ty),
+            Type::Vec(ref ty) => write!(f, "Vec<{}>", ty),
+        }
+    }
+}
+
+impl fmt::Display for CoreType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            CoreType::Bool => "bool",
+            CoreType::U8 => "u8",
+            CoreType::U16 => "u16",
+            CoreType::U32 => "u32",
+            CoreType::U64 => "u64",
+            CoreType::I8 => "i8",
+            CoreType::I16 => "i16",
+            CoreType::I32 => "i32",
+            CoreType::I64 => "i64",
+            CoreType::F32 => "f32",
+            CoreType::F64 => "f64",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl fmt::Display for Compat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Compat::Compatible => write!(f, "compatible"),
+            Compat::Breaking => write!(f, "breaking"),
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let desc = match self.kind {
+            ChangeKind::ItemAdded => "item added".to_string(),
+            ChangeKind::ItemRemoved => "item removed".to_string(),
+            ChangeKind::ItemRenamed { ref from } => format!("item renamed from `{}`", from.join("::")),
+            ChangeKind::ItemKindChanged => "item kind changed".to_string(),
+            ChangeKind::FieldAdded { ref field, ref ty } => format!("field `{}: {}` added", field, ty),
+            ChangeKind::FieldRemoved { ref field, ref ty } => format!("field `{}: {}` removed", field, ty),
+            ChangeKind::FieldRenamed { ref from, ref to } => format!("field `{}` renamed to `{}`", from, to),
+            ChangeKind::FieldTypeChanged { ref field, ref from, ref to } => format!("field `{}` changed from `{}` to `{}`", field, from, to),
+            ChangeKind::VariantAdded { ref variant } => format!("variant `{}` added", variant),
+            ChangeKind::VariantRemoved { ref variant } => format!("variant `{}` removed", variant),
+            ChangeKind::ReservedNameReused { ref name } => format!("reserved name `{}` reused", name),
+        };
+        write!(f, "{}: {} (old readers: {}, old writers: {})", self.path.join("::"), desc, self.old_readers, self.old_writers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use schema::Module;
+
+    /// Compares `old` and `new` items of a root module `p`.
+    fn changes(old: &str, new: &str) -> Vec<String> {
+        let module = |items: &str| -> Module {
+            let json = [r#"{"name": "p", "visibility": "Public", "items": ["#, items, "]}"].join("");
+            return serde_json::from_str(&json).unwrap();
+        };
+        let report = module(new).compare(&module(old));
+        return report.changes.iter().map(|change| change.to_string()).collect();
+    }
+
+    #[test]
+    fn classifies_struct_field_changes() {
+        let old = r#"{"Struct": {"name": "S", "visibility": "Public", "fields": [
+            {"name": "a", "ty": {"Core": "I32"}},
+            {"name": "b", "ty": "String"}]}}"#;
+        let new = r#"{"Struct": {"name": "S", "visibility": "Public", "fields": [
+            {"name": "a", "ty": {"Option": {"Core": "I32"}}},
+            {"name": "c", "ty": "String", "default": true},
+            {"name": "d", "ty": "String"}]}}"#;
+        assert_eq!(changes(old, new), vec![
+            "p::S: field `a` changed from `i32` to `Option<i32>` (old readers: breaking, old writers: compatible)".to_string(),
+            "p::S: field `b: String` removed (old readers: breaking, old writers: compatible)".to_string(),
+            "p::S: field `c: String` added (old readers: compatible, old writers: compatible)".to_string(),
+            "p::S: field `d: String` added (old readers: compatible, old writers: breaking)".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn classifies_variant_changes() {
+        let old = r#"{"Enum": {"name": "E", "visibility": "Public", "variants": [
+            {"name": "A", "fields": [{"name": "", "ty": {"Core": "I32"}}]},
+            {"name": "B", "fields": []}]}}"#;
+        let new = r#"{"Enum": {"name": "E", "visibility": "Public", "variants": [
+            {"name": "A", "fields": [{"name": "x", "ty": {"Core": "I32"}}, {"name": "y", "ty": "String", "default": true}]},
+            {"name": "C", "fields": []}]}}"#;
+        assert_eq!(changes(old, new), vec![
+            "p::E::A: field `0` renamed to `x` (old readers: compatible, old writers: compatible)".to_string(),
+            "p::E::A: field `y: String` added (old readers: compatible, old writers: compatible)".to_string(),
+            "p::E: variant `B` removed (old readers: compatible, old writers: breaking)".to_string(),
+            "p::E: variant `C` added (old readers: breaking, old writers: compatible)".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn follows_renamed_items() {
+        let old = r#"{"Struct": {"name": "Point", "visibility": "Public", "fields": [{"name": "x", "ty": {"Core": "I32"}}]}},
+            {"Struct": {"name": "Line", "visibility": "Public", "fields": [{"name": "a", "ty": {"Item": ["p", "Point"]}}]}}"#;
+        let new = r#"{"Struct": {"name": "Vertex", "visibility": "Public", "fields": [{"name": "x", "ty": {"Core": "I32"}}]}},
+            {"Struct": {"name": "Line", "visibility": "Public", "fields": [{"name": "a", "ty": {"Item": ["p", "Vertex"]}}]}}"#;
+        assert_eq!(changes(old, new), vec![
+            "p::Vertex: item renamed from `p::Point` (old readers: compatible, old writers: compatible)".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn classifies_item_changes() {
+        let old = r#"{"Struct": {"name": "S", "visibility": "Public", "fields": []}},
+            {"Struct": {"name": "T", "visibility": "Public", "fields": [{"name": "x", "ty": "String"}]}}"#;
+        let new = r#"{"Enum": {"name": "S", "visibility": "Public", "variants": []}},
+            {"Struct": {"name": "U", "visibility": "Public", "fields": [{"name": "y", "ty": "String"}]}}"#;
+        assert_eq!(changes(old, new), vec![
+            "p::S: item kind changed (old readers: breaking, old writers: breaking)".to_string(),
+            "p::T: item removed (old readers: compatible, old writers: breaking)".to_string(),
+            "p::U: item added (old readers: compatible, old writers: compatible)".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn reports_reuse_of_reserved_names() {
+        let old = r#"{"Struct": {"name": "S", "visibility": "Public", "reserved": ["b"], "fields": [
+            {"name": "a", "ty": "String", "default": true}]}},
+            {"Enum": {"name": "E", "visibility": "Public", "reserved": ["B"], "variants": [
+            {"name": "A", "fields": []}]}}"#;
+        let new = r#"{"Struct": {"name": "S", "visibility": "Public", "fields": [
+            {"name": "a", "ty": "String", "default": true},
+            {"name": "b", "ty": "String", "default": true}]}},
+            {"Enum": {"name": "E", "visibility": "Public", "variants": [
+            {"name": "A", "fields": []},
+            {"name": "B", "fields": []}]}}"#;
+        assert_eq!(changes(old, new), vec![
+            "p::E: variant `B` added (old readers: breaking, old writers: compatible)".to_string(),
+            "p::E: reserved name `B` reused (old readers: breaking, old writers: breaking)".to_string(),
+            "p::S: field `b: String` added (old readers: compatible, old writers: compatible)".to_string(),
+            "p::S: reserved name `b` reused (old readers: breaking, old writers: breaking)".to_string(),
+        ]);
+    }
+}
@@ -0,0 +1,873 @@
+use std::collections::HashSet;
+use super::Module;
+use super::Struct;
+use super::Enum;
+use super::EnumVariant;
+use super::StructField;
+use super::Item;
+use super::Type;
+use super::CoreType;
+use super::TypePath;
+use super::Visibility;
+use super::Deprecation;
+use super::ident::Lang;
+use super::ident::wire_name;
+use super::ident::string_literal;
+use super::ident::indent;
+use super::naming::Naming;
+use super::naming::snake_case;
+use super::codegen::GenOptions;
+use super::codegen::deprecation_message;
+
+/// C++17 backend.
+///
+/// Modules become namespaces, and structs become aggregates with
+/// default member initializers. An enum becomes a struct with a
+/// nested struct for each variant and a `std::variant` of them
+/// in `value`. An alias of `std::variant` cannot be declared
+/// ahead, and enums can refer to themselves through vectors.
+///
+/// Every type has `to_json`/`from_json` found by argument
+/// dependent lookup of nlohmann/json, so `nlohmann::json` reads
+/// and writes the wire format of Swift code.
+///
+/// Types are defined after types they contain by value, and
+/// namespaces are reopened as needed.
+impl Module {
+    pub fn gen_cpp_code(&self, opts: &GenOptions) -> String {
+        let handshake = handshake_structs();
+        let mut cx = Context { kroot: self, opts: opts, defs: Vec::new() };
+        collect_defs(self, &[], &mut cx.defs);
+        if opts.handshake {
+            cx.defs.push(Def { mods: Vec::new(), item: DefItem::Struct(&handshake.0) });
+            cx.defs.push(Def { mods: Vec::new(), item: DefItem::Struct(&handshake.1) });
+        }
+
+        let mut s = String::new();
+        s.push_str("// Code generated by mgen. DO NOT EDIT.\n");
+        s.push_str("#pragma once\n");
+        s.push_str("\n");
+        s.push_str("#include <cmath>\n");
+        s.push_str("#include <cstdint>\n");
+        s.push_str("#include <limits>\n");
+        s.push_str("#include <optional>\n");
+        s.push_str("#include <stdexcept>\n");
+        s.push_str("#include <string>\n");
+        s.push_str("#include <string_view>\n");
+        s.push_str("#include <utility>\n");
+        s.push_str("#include <variant>\n");
+        s.push_str("#include <vector>\n");
+        s.push_str("\n");
+        s.push_str("#include <nlohmann/json.hpp>\n");
+        s.push_str("\n");
+        s.push_str("#if defined(__GNUC__) || defined(__clang__)\n");
+        s.push_str("#pragma GCC diagnostic push\n");
+        s.push_str("#pragma GCC diagnostic ignored \"-Wdeprecated-declarations\"\n");
+        s.push_str("#endif\n");
+        s.push_str("\n");
+
+        let root = vec![namespace_name(opts, &self.name)];
+        let mut blocks = Vec::<(Vec<String>, String)>::new();
+        blocks.push((root.clone(), gen_error()));
+        s.push_str(&gen_namespaces(&blocks));
+        s.push_str("\n");
+
+        blocks.clear();
+        for def in &cx.defs {
+            blocks.push((namespace(&cx, def), ["struct ", &def_ident(def), ";\n"].join("")));
+        }
+        s.push_str(&gen_namespaces(&merge(&blocks)));
+        s.push_str("\n");
+
+        // Types held by value must be complete, so they are
+        // defined first.
+        blocks = Vec::new();
+        let mut done = HashSet::<usize>::new();
+        for i in 0..cx.defs.len() {
+            gen_definition(&cx, i, &mut done, &mut blocks);
+        }
+        blocks.push((root.clone(), gen_fingerprint(self)));
+        if opts.handshake {
+            blocks.push((root.clone(), gen_handshake(&cx, &handshake)));
+        }
+        s.push_str(&gen_namespaces(&blocks));
+        s.push_str("\n");
+
+        blocks.clear();
+        for def in &cx.defs {
+            blocks.push((namespace(&cx, def), gen_prototypes(def)));
+        }
+        s.push_str(&gen_namespaces(&merge(&blocks)));
+        s.push_str("\n");
+
+        let mut detail = root.clone();
+        detail.push("igen_detail".to_string());
+        blocks.clear();
+        blocks.push((detail, substitute(&cx, DETAIL)));
+        s.push_str(&gen_namespaces(&blocks));
+
+        blocks.clear();
+        for def in &cx.defs {
+            let code = match def.item {
+                DefItem::Struct(kstruct) => gen_struct_functions(&cx, kstruct),
+                DefItem::Enum(kenum) => gen_enum_functions(&cx, kenum),
+            };
+            blocks.push((namespace(&cx, def), code));
+        }
+        s.push_str("\n");
+        s.push_str(&gen_namespaces(&blocks));
+        s.push_str("\n");
+        s.push_str("#if defined(__GNUC__) || defined(__clang__)\n");
+        s.push_str("#pragma GCC diagnostic pop\n");
+        s.push_str("#endif\n");
+        return s;
+    }
+}
+
+struct Context<'a> {
+    kroot: &'a Module,
+    opts: &'a GenOptions,
+    /// Public items in the root module and its submodules.
+    defs: Vec<Def<'a>>,
+}
+
+/// A struct or an enum with its module path from the root.
+struct Def<'a> {
+    mods: Vec<String>,
+    item: DefItem<'a>,
+}
+
+#[derive(Clone, Copy)]
+enum DefItem<'a> {
+    Struct(&'a Struct),
+    Enum(&'a Enum),
+}
+
+/// Same messages with Swift `IgenHandshake`.
+fn handshake_structs() -> (Struct, Struct) {
+    let mut hello = Struct::new();
+    hello.name = "IgenHello".to_string();
+    hello.fields.push(StructField { name: "fingerprints".to_string(), ty: Type::Vec(Box::new(Type::String)), default: false, deprecation: None, tag: None });
+    let mut hello_ack = Struct::new();
+    hello_ack.name = "IgenHelloAck".to_string();
+    hello_ack.fields.push(StructField { name: "fingerprint".to_string(), ty: Type::Option(Box::new(Type::String)), default: false, deprecation: None, tag: None });
+    return (hello, hello_ack);
+}
+
+fn collect_defs<'a>(kmod: &'a Module, mods: &[String], defs: &mut Vec<Def<'a>>) {
+    for kitem in &kmod.items {
+        if kitem.visibility() != Visibility::Public { continue }
+        match kitem {
+            Item::Module(ref kmod1) => {
+                let mut mods1 = mods.to_vec();
+                mods1.push(kmod1.name.clone());
+                collect_defs(kmod1, &mods1, defs);
+            },
+            Item::Enum(ref kenum) => defs.push(Def { mods: mods.to_vec(), item: DefItem::Enum(kenum) }),
+            Item::Struct(ref kstruct) => defs.push(Def { mods: mods.to_vec(), item: DefItem::Struct(kstruct) }),
+        }
+    }
+}
+
+fn def_name<'a>(def: &Def<'a>) -> &'a str {
+    return match def.item {
+        DefItem::Struct(kstruct) => &kstruct.name,
+        DefItem::Enum(kenum) => &kenum.name,
+    };
+}
+
+fn def_ident(def: &Def) -> String {
+    return cpp_ident(def_name(def));
+}
+
+/// Fields of a struct or all variants.
+fn def_fields<'a>(def: &Def<'a>) -> Vec<&'a StructField> {
+    return match def.item {
+        DefItem::Struct(kstruct) => kstruct.fields.iter().collect(),
+        DefItem::Enum(kenum) => kenum.variants.iter().flat_map(|kvariant| kvariant.fields.iter()).collect(),
+    };
+}
+
+/// Namespace path of generated code. (`["proto", "sub"]`)
+fn namespace(cx: &Context, def: &Def) -> Vec<String> {
+    let mut ns = vec![namespace_name(cx.opts, &cx.kroot.name)];
+    for m in &def.mods {
+        ns.push(namespace_name(cx.opts, m));
+    }
+    return ns;
+}
+
+/// Index of the definition referred by `path` if it is in the
+/// root module.
+fn resolve(cx: &Context, path: &TypePath) -> Option<usize> {
+    let segments = &path.0;
+    if segments.len() == 0 {
+        return None;
+    }
+    let name = &segments[segments.len() - 1];
+    for i in 0..segments.len() - 1 {
+        if segments[i] != cx.kroot.name { continue }
+        let mods = &segments[i + 1..segments.len() - 1];
+        let found = cx.defs.iter().position(|def| &def.mods[..] == mods && def_name(def) == name);
+        if found.is_some() {
+            return found;
+        }
+    }
+    return None;
+}
+
+/// Defines the definition at `index` after definitions it
+/// contains by value. Vectors can hold incomplete types.
+fn gen_definition(cx: &Context, index: usize, done: &mut HashSet<usize>, blocks: &mut Vec<(Vec<String>, String)>) {
+    if !done.insert(index) { return }
+    let def = &cx.defs[index];
+    for kfield in def_fields(def) {
+        let mut ty = &kfield.ty;
+        while let Type::Option(ref ty1) = ty {
+            ty = ty1;
+        }
+        if let Type::Item(ref path) = ty {
+            if let Some(index1) = resolve(cx, path) {
+                gen_definition(cx, index1, done, blocks);
+            }
+        }
+    }
+    let code = match def.item {
+        DefItem::Struct(kstruct) => gen_struct(cx, kstruct),
+        DefItem::Enum(kenum) => gen_enum(cx, kenum),
+    };
+    blocks.push((namespace(cx, def), code));
+}
+
+/// Wraps each code in its namespace. Consecutive codes in the
+/// same namespace share a namespace block.
+fn gen_namespaces(blocks: &[(Vec<String>, String)]) -> String {
+    let mut s = String::new();
+    let mut i = 0;
+    while i < blocks.len() {
+        let ns = &blocks[i].0;
+        let mut codes = Vec::<&str>::new();
+        while i < blocks.len() && &blocks[i].0 == ns {
+            codes.push(&blocks[i].1);
+            i += 1;
+        }
+        if s.len() > 0 {
+            s.push_str("\n");
+        }
+        s.push_str("namespace ");
+        s.push_str(&ns.join("::"));
+        s.push_str(" {\n");
+        s.push_str("\n");
+        s.push_str(&codes.join("\n"));
+        s.push_str("\n");
+        s.push_str("}\n");
+    }
+    return s;
+}
+
+/// Joins codes of each namespace without blank lines.
+fn merge(blocks: &[(Vec<String>, String)]) -> Vec<(Vec<String>, String)> {
+    let mut r = Vec::<(Vec<String>, String)>::new();
+    for &(ref ns, ref code) in blocks {
+        if let Some(last) = r.last_mut() {
+            if &last.0 == ns {
+                last.1.push_str(code);
+                continue;
+            }
+        }
+        r.push((ns.clone(), code.clone()));
+    }
+    return r;
+}
+
+fn gen_error() -> String {
+    let mut s = String::new();
+    s.push_str("/// Thrown for values which do not match the wire format.\n");
+    s.push_str("struct IgenJsonError : std::runtime_error {\n");
+    s.push_str("    using std::runtime_error::runtime_error;\n");
+    s.push_str("};\n");
+    return s;
+}
+
+fn gen_fingerprint(kroot: &Module) -> String {
+    let mut s = String::new();
+    s.push_str("struct IgenSchema {\n");
+    s.push_str("    static constexpr std::string_view fingerprint = \"");
+    s.push_str(&kroot.fingerprint());
+    s.push_str("\";\n");
+    s.push_str("};\n");
+    return s;
+}
+
+/// Same messages with Swift `IgenHandshake`.
+fn gen_handshake(cx: &Context, handshake: &(Struct, Struct)) -> String {
+    let fingerprints = field_name(cx.opts, &handshake.0.fields[0], 0);
+    let fingerprint = field_name(cx.opts, &handshake.1.fields[0], 0);
+    let mut s = String::new();
+    s.push_str("struct IgenHandshake {\n");
+    s.push_str("    static constexpr std::string_view fingerprint = IgenSchema::fingerprint;\n");
+    s.push_str("\n");
+    s.push_str("    static IgenHello hello() {\n");
+    s.push_str("        IgenHello hello;\n");
+    s.push_str("        hello.");
+    s.push_str(&fingerprints);
+    s.push_str(".emplace_back(fingerprint);\n");
+    s.push_str("        return hello;\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    static IgenHelloAck accept(const IgenHello &hello) {\n");
+    s.push_str("        IgenHelloAck ack;\n");
+    s.push_str("        for (const auto &f : hello.");
+    s.push_str(&fingerprints);
+    s.push_str(") {\n");
+    s.push_str("            if (f == fingerprint) {\n");
+    s.push_str("                ack.");
+    s.push_str(&fingerprint);
+    s.push_str(".emplace(fingerprint);\n");
+    s.push_str("                break;\n");
+    s.push_str("            }\n");
+    s.push_str("        }\n");
+    s.push_str("        return ack;\n");
+    s.push_str("    }\n");
+    s.push_str("};\n");
+    return s;
+}
+
+fn gen_struct(cx: &Context, kstruct: &Struct) -> String {
+    let mut s = String::new();
+    s.push_str("struct ");
+    s.push_str(&deprecated(&kstruct.deprecation));
+    s.push_str(&cpp_ident(&kstruct.name));
+    s.push_str(" {\n");
+    s.push_str(&fingerprint_member(&kstruct.fingerprint()));
+    if kstruct.fields.len() > 0 {
+        s.push_str("\n");
+    }
+    s.push_str(&gen_fields(cx, &kstruct.fields));
+    s.push_str("};\n");
+    return s;
+}
+
+fn gen_enum(cx: &Context, kenum: &Enum) -> String {
+    let mut s = String::new();
+    s.push_str("struct ");
+    s.push_str(&deprecated(&kenum.deprecation));
+    s.push_str(&cpp_ident(&kenum.name));
+    s.push_str(" {\n");
+    s.push_str(&fingerprint_member(&kenum.fingerprint()));
+    s.push_str("\n");
+    for kvariant in &kenum.variants {
+        s.push_str("    struct ");
+        s.push_str(&deprecated(&kvariant.deprecation));
+        s.push_str(&variant_name(cx.opts, kvariant));
+        if kvariant.fields.len() == 0 {
+            s.push_str(" {};\n");
+            continue;
+        }
+        s.push_str(" {\n");
+        s.push_str(&indent(&gen_fields(cx, &kvariant.fields)));
+        s.push_str("    };\n");
+    }
+    if kenum.variants.len() > 0 {
+        s.push_str("\n");
+    }
+    s.push_str("    ");
+    s.push_str(&variant_type(cx, kenum));
+    s.push_str(" value;\n");
+    s.push_str("};\n");
+    return s;
+}
+
+/// `std::variant` needs at least one alternative.
+fn variant_type(cx: &Context, kenum: &Enum) -> String {
+    if kenum.variants.len() == 0 {
+        return "std::variant<std::monostate>".to_string();
+    }
+    let names = kenum.variants.iter().map(|kvariant| variant_name(cx.opts, kvariant)).collect::<Vec<String>>();
+    return ["std::variant<", &names.join(", "), ">"].join("");
+}
+
+/// Value initialization is same with `Default::default()` of
+/// Rust.
+fn gen_fields(cx: &Context, kfields: &[StructField]) -> String {
+    let mut s = String::new();
+    for (i, kfield) in kfields.iter().enumerate() {
+        s.push_str("    ");
+        s.push_str(&deprecated(&kfield.deprecation));
+        s.push_str(&cpp_type(cx, &kfield.ty));
+        s.push_str(" ");
+        s.push_str(&field_name(cx.opts, kfield, i));
+        s.push_str("{};\n");
+    }
+    return s;
+}
+
+fn gen_prototypes(def: &Def) -> String {
+    let name = def_ident(def);
+    let mut s = String::new();
+    s.push_str("inline void to_json(nlohmann::json &j, const ");
+    s.push_str(&name);
+    s.push_str(" &v);\n");
+    s.push_str("inline void from_json(const nlohmann::json &j, ");
+    s.push_str(&name);
+    s.push_str(" &v);\n");
+    return s;
+}
+
+/// Unknown keys are skipped. Missing keys are allowed only for
+/// optional fields and fields with defaults.
+/// Empty optional fields are omitted like Swift.
+fn gen_struct_functions(cx: &Context, kstruct: &Struct) -> String {
+    let name = cpp_ident(&kstruct.name);
+    let mut s = String::new();
+    s.push_str("inline void to_json(nlohmann::json &j, const ");
+    s.push_str(&name);
+    s.push_str(" &v) {\n");
+    s.push_str("    j = nlohmann::json::object();\n");
+    for (i, kfield) in kstruct.fields.iter().enumerate() {
+        let value = ["v.", &field_name(cx.opts, kfield, i)].join("");
+        s.push_str("    ");
+        if let Type::Option(_) = kfield.ty {
+            s.push_str("if (");
+            s.push_str(&value);
+            s.push_str(") ");
+        }
+        s.push_str("j[");
+        s.push_str(&string_literal(wire_name(&kfield.name)));
+        s.push_str("] = igen_detail::write(");
+        s.push_str(&value);
+        s.push_str(");\n");
+    }
+    s.push_str("}\n");
+    s.push_str("\n");
+    s.push_str("inline void from_json(const nlohmann::json &j, ");
+    s.push_str(&name);
+    s.push_str(" &v) {\n");
+    s.push_str("    igen_detail::expect(j.is_object(), \"expected object for ");
+    s.push_str(wire_name(&kstruct.name));
+    s.push_str("\");\n");
+    s.push_str("    v = ");
+    s.push_str(&name);
+    s.push_str("{};\n");
+    for (i, kfield) in kstruct.fields.iter().enumerate() {
+        let key = string_literal(wire_name(&kfield.name));
+        let value = ["v.", &field_name(cx.opts, kfield, i)].join("");
+        if is_required(kfield) {
+            s.push_str("    igen_detail::expect(j.contains(");
+            s.push_str(&key);
+            s.push_str("), \"missing key ");
+            s.push_str(wire_name(&kfield.name));
+            s.push_str("\");\n");
+            s.push_str("    igen_detail::read(j.at(");
+            s.push_str(&key);
+            s.push_str("), ");
+            s.push_str(&value);
+            s.push_str(");\n");
+        }
+        else {
+            s.push_str("    if (j.contains(");
+            s.push_str(&key);
+            s.push_str(")) igen_detail::read(j.at(");
+            s.push_str(&key);
+            s.push_str("), ");
+            s.push_str(&value);
+            s.push_str(");\n");
+        }
+    }
+    s.push_str("}\n");
+    return s;
+}
+
+/// Encoded as `[name, field0, field1, ...]`.
+/// Trailing elements are skipped. Missing trailing elements are
+/// allowed only for fields with defaults.
+fn gen_enum_functions(cx: &Context, kenum: &Enum) -> String {
+    let name = cpp_ident(&kenum.name);
+    let mut s = String::new();
+    s.push_str("inline void to_json(nlohmann::json &j, const ");
+    s.push_str(&name);
+    s.push_str(" &v) {\n");
+    s.push_str("    j = nlohmann::json::array();\n");
+    s.push_str("    switch (v.value.index()) {\n");
+    for (i, kvariant) in kenum.variants.iter().enumerate() {
+        s.push_str("    case ");
+        s.push_str(&i.to_string());
+        s.push_str(": {\n");
+        if kvariant.fields.len() > 0 {
+            s.push_str("        const auto &x = std::get<");
+            s.push_str(&i.to_string());
+            s.push_str(">(v.value);\n");
+        }
+        s.push_str("        j.push_back(");
+        s.push_str(&string_literal(wire_name(&kvariant.name)));
+        s.push_str(");\n");
+        for (j, kfield) in kvariant.fields.iter().enumerate() {
+            s.push_str("        j.push_back(igen_detail::write(x.");
+            s.push_str(&field_name(cx.opts, kfield, j));
+            s.push_str("));\n");
+        }
+        s.push_str("        break;\n");
+        s.push_str("    }\n");
+    }
+    s.push_str("    default:\n");
+    s.push_str("        throw IgenJsonError(\"no variant of ");
+    s.push_str(wire_name(&kenum.name));
+    s.push_str("\");\n");
+    s.push_str("    }\n");
+    s.push_str("}\n");
+    s.push_str("\n");
+    s.push_str("inline void from_json(const nlohmann::json &j, ");
+    s.push_str(&name);
+    s.push_str(" &v) {\n");
+    s.push_str("    igen_detail::expect(j.is_array() && j.size() > 0 && j[0].is_string(), \"expected array for ");
+    s.push_str(wire_name(&kenum.name));
+    s.push_str("\");\n");
+    s.push_str("    const auto &name = j[0].get_ref<const std::string &>();\n");
+    for (i, kvariant) in kenum.variants.iter().enumerate() {
+        let variant = [&name[..], "::", &variant_name(cx.opts, kvariant)].join("");
+        s.push_str(if i == 0 { "    if (name == " } else { "    else if (name == " });
+        s.push_str(&string_literal(wire_name(&kvariant.name)));
+        s.push_str(") {\n");
+        if kvariant.fields.len() == 0 {
+            s.push_str("        v.value = ");
+            s.push_str(&variant);
+            s.push_str("{};\n");
+            s.push_str("        return;\n");
+            s.push_str("    }\n");
+            continue;
+        }
+        s.push_str("        ");
+        s.push_str(&variant);
+        s.push_str(" x;\n");
+        for (j, kfield) in kvariant.fields.iter().enumerate() {
+            let index = (j + 1).to_string();
+            let value = ["x.", &field_name(cx.opts, kfield, j)].join("");
+            if kfield.default {
+                s.push_str("        if (j.size() > ");
+                s.push_str(&index);
+                s.push_str(") igen_detail::read(j[");
+                s.push_str(&index);
+                s.push_str("], ");
+                s.push_str(&value);
+                s.push_str(");\n");
+            }
+            else {
+                s.push_str("        igen_detail::expect(j.size() > ");
+                s.push_str(&index);
+                s.push_str(", \"missing field ");
+                s.push_str(&j.to_string());
+                s.push_str(" of ");
+                s.push_str(wire_name(&kvariant.name));
+                s.push_str("\");\n");
+                s.push_str("        igen_detail::read(j[");
+                s.push_str(&index);
+                s.push_str("], ");
+                s.push_str(&value);
+                s.push_str(");\n");
+            }
+        }
+        s.push_str("        v.value = std::move(x);\n");
+        s.push_str("        return;\n");
+        s.push_str("    }\n");
+    }
+    s.push_str("    throw IgenJsonError(\"unknown variant of ");
+    s.push_str(wire_name(&kenum.name));
+    s.push_str(": \" + name);\n");
+    s.push_str("}\n");
+    return s;
+}
+
+fn is_required(kfield: &StructField) -> bool {
+    if kfield.default { return false }
+    return match kfield.ty {
+        Type::Option(_) => false,
+        _ => true,
+    };
+}
+
+fn cpp_type(cx: &Context, ty: &Type) -> String {
+    return match ty {
+        Type::Core(ref core_type) => {
+            use self::CoreType::*;
+            match core_type {
+                Bool => "bool".to_string(),
+                U8 => "std::uint8_t".to_string(),
+                U16 => "std::uint16_t".to_string(),
+                U32 => "std::uint32_t".to_string(),
+                U64 => "std::uint64_t".to_string(),
+                I8 => "std::int8_t".to_string(),
+                I16 => "std::int16_t".to_string(),
+                I32 => "std::int32_t".to_string(),
+                I64 => "std::int64_t".to_string(),
+                F32 => "float".to_string(),
+                F64 => "double".to_string(),
+            }
+        },
+        Type::String => "std::string".to_string(),
+        Type::Item(ref path) => type_path(cx, path),
+        Type::Option(ref ty1) => ["std::optional<", &cpp_type(cx, ty1), ">"].join(""),
+        Type::Vec(ref ty1) => ["std::vector<", &cpp_type(cx, ty1), ">"].join(""),
+    };
+}
+
+/// Fully qualified path if the item is in the root module,
+/// because nested variant structs can shadow other types.
+/// Otherwise only the last segment is used like Swift.
+fn type_path(cx: &Context, path: &TypePath) -> String {
+    if let Some(index) = resolve(cx, path) {
+        let def = &cx.defs[index];
+        return ["::", &namespace(cx, def).join("::"), "::", &def_ident(def)].join("");
+    }
+    return match path.0.last() {
+        Some(name) => cpp_ident(name),
+        None => "????".to_string(),
+    };
+}
+
+fn namespace_name(opts: &GenOptions, name: &str) -> String {
+    let name = wire_name(name);
+    return match opts.naming {
+        Naming::Preserve => cpp_ident(name),
+        Naming::Idiomatic => cpp_ident(&snake_case(name)),
+    };
+}
+
+fn variant_name(opts: &GenOptions, kvariant: &EnumVariant) -> String {
+    return Lang::Cpp.variant_name(opts.naming, &kvariant.name);
+}
+
+/// Tuple fields are named by their positions. (`f0`, `f1`, ...)
+fn field_name(opts: &GenOptions, kfield: &StructField, index: usize) -> String {
+    if kfield.name == "" {
+        return ["f", &index.to_string()].join("");
+    }
+    return Lang::Cpp.field_name(opts.naming, &kfield.name);
+}
+
+fn fingerprint_member(fingerprint: &str) -> String {
+    return ["    static constexpr std::string_view igen_fingerprint = \"", fingerprint, "\";\n"].join("");
+}
+
+fn deprecated(deprecation: &Option<Deprecation>) -> String {
+    let deprecation = match deprecation {
+        Some(ref deprecation) => deprecation,
+        None => return String::new(),
+    };
+    return match deprecation_message(deprecation) {
+        Some(message) => ["[[deprecated(", &string_literal(&message), ")]] "].join(""),
+        None => "[[deprecated]] ".to_string(),
+    };
+}
+
+/// Replaces placeholders of fixed code with the root namespace.
+fn substitute(cx: &Context, code: &str) -> String {
+    return code.replace("IGEN_ROOT", &["::", &namespace_name(cx.opts, &cx.kroot.name)].join(""));
+}
+
+fn cpp_ident(name: &str) -> String {
+    return Lang::Cpp.escape_ident(name);
+}
+
+/// Conversions of field values. Generated types are converted
+/// by their `to_json`/`from_json`.
+const DETAIL: &'static str = r#"inline void expect(bool ok, const char *message) {
+    if (!ok) throw IGEN_ROOT::IgenJsonError(message);
+}
+
+template <typename T>
+struct Codec {
+    static nlohmann::json write(const T &v) { return nlohmann::json(v); }
+    static T read(const nlohmann::json &j) { return j.get<T>(); }
+};
+
+template <>
+struct Codec<bool> {
+    static nlohmann::json write(bool v) { return v; }
+    static bool read(const nlohmann::json &j) {
+        expect(j.is_boolean(), "expected boolean");
+        return j.get<bool>();
+    }
+};
+
+template <typename T>
+struct IntegerCodec {
+    static nlohmann::json write(T v) { return v; }
+    static T read(const nlohmann::json &j) {
+        expect(j.is_number_integer(), "expected integer");
+        if (j.is_number_unsigned() || j.get<std::int64_t>() >= 0) {
+            auto v = j.get<std::uint64_t>();
+            expect(v <= static_cast<std::uint64_t>(std::numeric_limits<T>::max()), "integer out of range");
+            return static_cast<T>(v);
+        }
+        auto v = j.get<std::int64_t>();
+        expect(std::numeric_limits<T>::is_signed && v >= static_cast<std::int64_t>(std::numeric_limits<T>::min()), "integer out of range");
+        return static_cast<T>(v);
+    }
+};
+
+template <> struct Codec<std::uint8_t> : IntegerCodec<std::uint8_t> {};
+template <> struct Codec<std::uint16_t> : IntegerCodec<std::uint16_t> {};
+template <> struct Codec<std::uint32_t> : IntegerCodec<std::uint32_t> {};
+template <> struct Codec<std::uint64_t> : IntegerCodec<std::uint64_t> {};
+template <> struct Codec<std::int8_t> : IntegerCodec<std::int8_t> {};
+template <> struct Codec<std::int16_t> : IntegerCodec<std::int16_t> {};
+template <> struct Codec<std::int32_t> : IntegerCodec<std::int32_t> {};
+template <> struct Codec<std::int64_t> : IntegerCodec<std::int64_t> {};
+
+/// JSON has no representation of infinity and NaN.
+template <typename T>
+struct FloatCodec {
+    static nlohmann::json write(T v) {
+        if (!std::isfinite(v)) throw IGEN_ROOT::IgenJsonError("number is not finite");
+        return v;
+    }
+    static T read(const nlohmann::json &j) {
+        expect(j.is_number(), "expected number");
+        return j.get<T>();
+    }
+};
+
+template <> struct Codec<float> : FloatCodec<float> {};
+template <> struct Codec<double> : FloatCodec<double> {};
+
+template <>
+struct Codec<std::string> {
+    static nlohmann::json write(const std::string &v) { return v; }
+    static std::string read(const nlohmann::json &j) {
+        expect(j.is_string(), "expected string");
+        return j.get<std::string>();
+    }
+};
+
+template <typename T>
+struct Codec<std::optional<T>> {
+    static nlohmann::json write(const std::optional<T> &v) {
+        if (!v) return nullptr;
+        return Codec<T>::write(*v);
+    }
+    static std::optional<T> read(const nlohmann::json &j) {
+        if (j.is_null()) return std::nullopt;
+        return Codec<T>::read(j);
+    }
+};
+
+template <typename T>
+struct Codec<std::vector<T>> {
+    static nlohmann::json write(const std::vector<T> &v) {
+        auto j = nlohmann::json::array();
+        for (const auto &e : v) j.push_back(Codec<T>::write(e));
+        return j;
+    }
+    static std::vector<T> read(const nlohmann::json &j) {
+        expect(j.is_array(), "expected array");
+        std::vector<T> v;
+        v.reserve(j.size());
+        for (const auto &e : j) v.push_back(Codec<T>::read(e));
+        return v;
+    }
+};
+
+template <typename T>
+nlohmann::json write(const T &v) {
+    return Codec<T>::write(v);
+}
+
+template <typename T>
+void read(const nlohmann::json &j, T &out) {
+    out = Codec<T>::read(j);
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use schema::Module;
+    use schema::codegen::GenOptions;
+
+    fn code() -> String {
+        let kmod: Module = serde_json::from_str(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Module": {"name": "geo", "visibility": "Public", "items": [
+                {"Struct": {"name": "Size", "visibility": "Public", "fields": [
+                    {"name": "w", "ty": {"Core": "U8"}, "default": true}]}}]}},
+            {"Struct": {"name": "Shape", "visibility": "Public", "fields": [
+                {"name": "class", "ty": "String"},
+                {"name": "size", "ty": {"Item": ["p", "geo", "Size"]}, "default": true},
+                {"name": "data", "ty": {"Vec": {"Option": {"Core": "U64"}}}},
+                {"name": "note", "ty": {"Option": "String"}}]}},
+            {"Enum": {"name": "Command", "visibility": "Public", "variants": [
+                {"name": "Stop", "fields": []},
+                {"name": "Resize", "fields": [
+                    {"name": "", "ty": {"Item": ["p", "geo", "Size"]}},
+                    {"name": "", "ty": {"Vec": "String"}, "default": true}]}]}}]}"#).unwrap();
+        return kmod.gen_cpp_code(&GenOptions::new());
+    }
+
+    #[test]
+    fn defines_contained_types_first_in_reopened_namespaces() {
+        let code = code();
+        let size = code.find("namespace p::geo {\n\nstruct Size {\n").unwrap();
+        let shape = code.find("namespace p {\n\nstruct Shape {\n").unwrap();
+        assert!(size < shape);
+        assert!(code.contains(concat!(
+            "    std::string class_{};\n",
+            "    ::p::geo::Size size{};\n",
+            "    std::vector<std::optional<std::uint64_t>> data{};\n",
+            "    std::optional<std::string> note{};\n")));
+        assert!(code.contains(concat!(
+            "    struct Stop {};\n",
+            "    struct Resize {\n",
+            "        ::p::geo::Size f0{};\n",
+            "        std::vector<std::string> f1{};\n",
+            "    };\n",
+            "\n",
+            "    std::variant<Stop, Resize> value;\n")));
+    }
+
+    #[test]
+    fn decodes_objects_with_defaults_and_required_keys() {
+        let code = code();
+        assert!(code.contains(concat!(
+            "    v = Shape{};\n",
+            "    igen_detail::expect(j.contains(\"class\"), \"missing key class\");\n",
+            "    igen_detail::read(j.at(\"class\"), v.class_);\n",
+            "    if (j.contains(\"size\")) igen_detail::read(j.at(\"size\"), v.size);\n",
+            "    igen_detail::expect(j.contains(\"data\"), \"missing key data\");\n",
+            "    igen_detail::read(j.at(\"data\"), v.data);\n",
+            "    if (j.contains(\"note\")) igen_detail::read(j.at(\"note\"), v.note);\n",
+            "}\n")));
+        assert!(code.contains(concat!(
+            "    j[\"data\"] = igen_detail::write(v.data);\n",
+            "    if (v.note) j[\"note\"] = igen_detail::write(v.note);\n")));
+    }
+
+    #[test]
+    fn checks_integer_ranges_and_finite_floats() {
+        let code = code();
+        assert!(code.contains("template <> struct Codec<std::uint8_t> : IntegerCodec<std::uint8_t> {};\n"));
+        assert!(code.contains("            expect(v <= static_cast<std::uint64_t>(std::numeric_limits<T>::max()), \"integer out of range\");\n"));
+        assert!(code.contains("        if (!std::isfinite(v)) throw ::p::IgenJsonError(\"number is not finite\");\n"));
+    }
+
+    #[test]
+    fn decodes_variants_with_missing_trailing_defaults() {
+        let code = code();
+        assert!(code.contains(concat!(
+            "    else if (name == \"Resize\") {\n",
+            "        Command::Resize x;\n",
+            "        igen_detail::expect(j.size() > 1, \"missing field 0 of Resize\");\n",
+            "        igen_detail::read(j[1], x.f0);\n",
+            "        if (j.size() > 2) igen_detail::read(j[2], x.f1);\n",
+            "        v.value = std::move(x);\n",
+            "        return;\n",
+            "    }\n",
+            "    throw IgenJsonError(\"unknown variant of Command: \" + name);\n")));
+        assert!(code.contains(concat!(
+            "    case 1: {\n",
+            "        const auto &x = std::get<1>(v.value);\n",
+            "        j.push_back(\"Resize\");\n",
+            "        j.push_back(igen_detail::write(x.f0));\n",
+            "        j.push_back(igen_detail::write(x.f1));\n",
+            "        break;\n",
+            "    }\n")));
+    }
+}
@@ -0,0 +1,696 @@
+
+use super::Module;
+use super::Struct;
+use super::Enum;
+use super::EnumVariant;
+use super::StructField;
+use super::Item;
+use super::Type;
+use super::CoreType;
+use super::TypePath;
+use super::Visibility;
+use super::Deprecation;
+use super::ident::Lang;
+use super::ident::wire_name;
+use super::ident::string_literal;
+use super::ident::indent;
+use super::naming::Naming;
+use super::naming::upper_camel_case;
+use super::codegen::GenOptions;
+use super::codegen::deprecation_message;
+
+/// C# backend.
+///
+/// Modules become nested static classes, structs become sealed
+/// records, and enums become abstract records with a nested
+/// sealed record for each variant. Every record has a nested
+/// `System.Text.Json` converter which implements the wire format
+/// of Swift code.
+///
+/// Type references are written as fully qualified `global::`
+/// paths, because variant records and properties can shadow
+/// other types.
+impl Module {
+    pub fn gen_csharp_code(&self, opts: &GenOptions) -> String {
+        let cx = Context { kroot: self, opts: opts };
+        let mut s = String::new();
+        s.push_str("#nullable enable\n");
+        s.push_str("#pragma warning disable CS0612, CS0618\n");
+        s.push_str("\n");
+        s.push_str("using System;\n");
+        s.push_str("using System.Collections.Generic;\n");
+        s.push_str("using System.Text.Json;\n");
+        s.push_str("using System.Text.Json.Serialization;\n");
+        s.push_str("\n");
+        s.push_str(&gen_module(&cx, self, None));
+        return s;
+    }
+}
+
+struct Context<'a> {
+    kroot: &'a Module,
+    opts: &'a GenOptions,
+}
+
+/// `parent` is the parent module and its class name.
+fn gen_module(cx: &Context, kmod: &Module, parent: Option<(&Module, &str)>) -> String {
+    let is_root = parent.is_none();
+    let class_name = module_class_name(cx, kmod, parent);
+    let mut ss = Vec::<String>::new();
+    for kitem in &kmod.items {
+        if kitem.visibility() != Visibility::Public { continue }
+        ss.push(match kitem {
+            Item::Module(ref kmod1) => gen_module(cx, kmod1, Some((kmod, &class_name))),
+            Item::Enum(ref kenum) => gen_enum(cx, kenum),
+            Item::Struct(ref kstruct) => gen_struct(cx, kstruct),
+        });
+    }
+    if is_root {
+        ss.push(gen_fingerprint(cx.kroot));
+        if cx.opts.handshake {
+            ss.push(gen_handshake());
+        }
+    }
+    let mut s = String::new();
+    s.push_str("public static partial class ");
+    s.push_str(&class_name);
+    s.push_str("\n");
+    s.push_str("{\n");
+    s.push_str(&indent(&ss.join("\n")));
+    s.push_str("}\n");
+    return s;
+}
+
+fn gen_fingerprint(kroot: &Module) -> String {
+    let mut s = String::new();
+    s.push_str("public static class IgenSchema\n");
+    s.push_str("{\n");
+    s.push_str("    public const string Fingerprint = \"");
+    s.push_str(&kroot.fingerprint());
+    s.push_str("\";\n");
+    s.push_str("}\n");
+    return s;
+}
+
+/// Same messages with Swift `IgenHandshake`.
+fn gen_handshake() -> String {
+    let mut s = String::new();
+    s.push_str("public static class IgenHandshake\n");
+    s.push_str("{\n");
+    s.push_str("    public const string Fingerprint = IgenSchema.Fingerprint;\n");
+    s.push_str("\n");
+    s.push_str("    public sealed record Hello(\n");
+    s.push_str("        [property: JsonPropertyName(\"fingerprints\")] List<string> Fingerprints);\n");
+    s.push_str("\n");
+    s.push_str("    public sealed record HelloAck(\n");
+    s.push_str("        [property: JsonPropertyName(\"fingerprint\"), JsonIgnore(Condition = JsonIgnoreCondition.WhenWritingNull)] string? Fingerprint);\n");
+    s.push_str("\n");
+    s.push_str("    public static Hello MakeHello() => new Hello(new List<string> { Fingerprint });\n");
+    s.push_str("\n");
+    s.push_str("    public static HelloAck Accept(Hello hello) =>\n");
+    s.push_str("        new HelloAck(hello.Fingerprints.Contains(Fingerprint) ? Fingerprint : null);\n");
+    s.push_str("}\n");
+    return s;
+}
+
+fn gen_struct(cx: &Context, kstruct: &Struct) -> String {
+    let name = csharp_ident(&kstruct.name);
+    let mut s = String::new();
+    s.push_str(&obsolete(&kstruct.deprecation, ""));
+    s.push_str("[JsonConverter(typeof(");
+    s.push_str(&name);
+    s.push_str(".IgenJsonConverter))]\n");
+    s.push_str("public sealed record ");
+    s.push_str(&name);
+    s.push_str("(");
+    let params = gen_parameters(cx, &kstruct.fields, &name);
+    if params.len() > 0 {
+        s.push_str("\n    ");
+        s.push_str(&params.join(",\n    "));
+    }
+    s.push_str(")\n");
+    s.push_str("{\n");
+    s.push_str(&fingerprint_member(&kstruct.fingerprint()));
+    if kstruct.has_default() {
+        s.push_str("    public static ");
+        s.push_str(&name);
+        s.push_str(" IgenDefault => new ");
+        s.push_str(&name);
+        s.push_str("(");
+        let values = kstruct.fields.iter().map(|kfield| default_value(cx, &kfield.ty)).collect::<Vec<String>>();
+        s.push_str(&values.join(", "));
+        s.push_str(");\n");
+    }
+    s.push_str("\n");
+    s.push_str(&indent(&gen_struct_converter(cx, kstruct)));
+    s.push_str("}\n");
+    return s;
+}
+
+/// Unknown keys are skipped. Missing keys are allowed only for
+/// nullable fields and fields with defaults.
+/// `null` of nullable fields is omitted like Swift.
+fn gen_struct_converter(cx: &Context, kstruct: &Struct) -> String {
+    let name = csharp_ident(&kstruct.name);
+    let mut s = String::new();
+    s.push_str("public sealed class IgenJsonConverter : JsonConverter<");
+    s.push_str(&name);
+    s.push_str(">\n");
+    s.push_str("{\n");
+    s.push_str("    public override ");
+    s.push_str(&name);
+    s.push_str(" Read(ref Utf8JsonReader reader, global::System.Type typeToConvert, JsonSerializerOptions options)\n");
+    s.push_str("    {\n");
+    s.push_str("        if (reader.TokenType != JsonTokenType.StartObject) throw new JsonException(\"expected object for ");
+    s.push_str(wire_name(&kstruct.name));
+    s.push_str("\");\n");
+    for (i, kfield) in kstruct.fields.iter().enumerate() {
+        let ty = csharp_type(cx, &kfield.ty);
+        s.push_str("        ");
+        s.push_str(&ty);
+        s.push_str(" f");
+        s.push_str(&i.to_string());
+        s.push_str(" = ");
+        if kfield.default {
+            s.push_str(&default_value(cx, &kfield.ty));
+        }
+        else {
+            s.push_str("default!");
+        }
+        s.push_str(";\n");
+        if is_required(kfield) {
+            s.push_str("        bool has");
+            s.push_str(&i.to_string());
+            s.push_str(" = false;\n");
+        }
+    }
+    s.push_str("        while (reader.Read() && reader.TokenType != JsonTokenType.EndObject)\n");
+    s.push_str("        {\n");
+    s.push_str("            string? key = reader.GetString();\n");
+    s.push_str("            reader.Read();\n");
+    s.push_str("            switch (key)\n");
+    s.push_str("            {\n");
+    for (i, kfield) in kstruct.fields.iter().enumerate() {
+        s.push_str("                case ");
+        s.push_str(&string_literal(wire_name(&kfield.name)));
+        s.push_str(":\n");
+        s.push_str("                    f");
+        s.push_str(&i.to_string());
+        s.push_str(" = ");
+        s.push_str(&deserialize(cx, &kfield.ty));
+        s.push_str(";\n");
+        if is_required(kfield) {
+            s.push_str("                    has");
+            s.push_str(&i.to_string());
+            s.push_str(" = true;\n");
+        }
+        s.push_str("                    break;\n");
+    }
+    s.push_str("                default:\n");
+    s.push_str("                    reader.Skip();\n");
+    s.push_str("                    break;\n");
+    s.push_str("            }\n");
+    s.push_str("        }\n");
+    for (i, kfield) in kstruct.fields.iter().enumerate() {
+        if !is_required(kfield) { continue }
+        s.push_str("        if (!has");
+        s.push_str(&i.to_string());
+        s.push_str(") throw new JsonException(\"missing key ");
+        s.push_str(wire_name(&kfield.name));
+        s.push_str("\");\n");
+    }
+    s.push_str("        return new ");
+    s.push_str(&name);
+    s.push_str("(");
+    let args = (0..kstruct.fields.len()).map(|i| ["f", &i.to_string()].join("")).collect::<Vec<String>>();
+    s.push_str(&args.join(", "));
+    s.push_str(");\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    public override void Write(Utf8JsonWriter writer, ");
+    s.push_str(&name);
+    s.push_str(" value, JsonSerializerOptions options)\n");
+    s.push_str("    {\n");
+    s.push_str("        writer.WriteStartObject();\n");
+    for (i, kfield) in kstruct.fields.iter().enumerate() {
+        let value = ["value.", &field_name(cx.opts, kfield, i, &name)].join("");
+        let mut line = String::new();
+        line.push_str("writer.WritePropertyName(");
+        line.push_str(&string_literal(wire_name(&kfield.name)));
+        line.push_str("); ");
+        line.push_str(&serialize(cx, &kfield.ty, &value));
+        s.push_str("        ");
+        if let Type::Option(_) = kfield.ty {
+            s.push_str("if (");
+            s.push_str(&value);
+            s.push_str(" != null) { ");
+            s.push_str(&line);
+            s.push_str(" }\n");
+        }
+        else {
+            s.push_str(&line);
+            s.push_str("\n");
+        }
+    }
+    s.push_str("        writer.WriteEndObject();\n");
+    s.push_str("    }\n");
+    s.push_str("}\n");
+    return s;
+}
+
+fn gen_enum(cx: &Context, kenum: &Enum) -> String {
+    let name = csharp_ident(&kenum.name);
+    let mut s = String::new();
+    s.push_str(&obsolete(&kenum.deprecation, ""));
+    s.push_str("[JsonConverter(typeof(");
+    s.push_str(&name);
+    s.push_str(".IgenJsonConverter))]\n");
+    s.push_str("public abstract record ");
+    s.push_str(&name);
+    s.push_str("\n");
+    s.push_str("{\n");
+    s.push_str(&fingerprint_member(&kenum.fingerprint()));
+    s.push_str("\n");
+    s.push_str("    private ");
+    s.push_str(&name);
+    s.push_str("() {}\n");
+    s.push_str("\n");
+    for kvariant in &kenum.variants {
+        s.push_str(&obsolete(&kvariant.deprecation, "    "));
+        let variant = variant_name(cx.opts, kvariant, &name);
+        s.push_str("    public sealed record ");
+        s.push_str(&variant);
+        s.push_str("(");
+        s.push_str(&gen_parameters(cx, &kvariant.fields, &variant).join(", "));
+        s.push_str(") : ");
+        s.push_str(&name);
+        s.push_str(";\n");
+    }
+    s.push_str("\n");
+    s.push_str(&indent(&gen_enum_converter(cx, kenum)));
+    s.push_str("}\n");
+    return s;
+}
+
+/// Encoded as `[name, field0, field1, ...]`.
+/// Trailing elements are skipped. Missing trailing elements are
+/// allowed only for fields with defaults.
+fn gen_enum_converter(cx: &Context, kenum: &Enum) -> String {
+    let name = csharp_ident(&kenum.name);
+    let mut s = String::new();
+    s.push_str("public sealed class IgenJsonConverter : JsonConverter<");
+    s.push_str(&name);
+    s.push_str(">\n");
+    s.push_str("{\n");
+    s.push_str("    public override ");
+    s.push_str(&name);
+    s.push_str(" Read(ref Utf8JsonReader reader, global::System.Type typeToConvert, JsonSerializerOptions options)\n");
+    s.push_str("    {\n");
+    s.push_str("        if (reader.TokenType != JsonTokenType.StartArray) throw new JsonException(\"expected array for ");
+    s.push_str(wire_name(&kenum.name));
+    s.push_str("\");\n");
+    s.push_str("        reader.Read();\n");
+    s.push_str("        string? n = reader.GetString();\n");
+    s.push_str("        reader.Read();\n");
+    s.push_str("        ");
+    s.push_str(&name);
+    s.push_str(" r;\n");
+    s.push_str("        switch (n)\n");
+    s.push_str("        {\n");
+    for kvariant in &kenum.variants {
+        s.push_str("            case ");
+        s.push_str(&string_literal(wire_name(&kvariant.name)));
+        s.push_str(":\n");
+        s.push_str("            {\n");
+        for (i, kfield) in kvariant.fields.iter().enumerate() {
+            let f = ["f", &i.to_string()].join("");
+            s.push_str("                ");
+            s.push_str(&csharp_type(cx, &kfield.ty));
+            s.push_str(" ");
+            s.push_str(&f);
+            s.push_str(" = ");
+            if kfield.default {
+                s.push_str(&default_value(cx, &kfield.ty));
+            }
+            else {
+                s.push_str("default!");
+            }
+            s.push_str(";\n");
+            s.push_str("                if (reader.TokenType != JsonTokenType.EndArray) { ");
+            s.push_str(&f);
+            s.push_str(" = ");
+            s.push_str(&deserialize(cx, &kfield.ty));
+            s.push_str("; reader.Read(); }\n");
+            if !kfield.default {
+                s.push_str("                else throw new JsonException(\"missing field ");
+                s.push_str(&i.to_string());
+                s.push_str(" of ");
+                s.push_str(wire_name(&kvariant.name));
+                s.push_str("\");\n");
+            }
+        }
+        s.push_str("                r = new ");
+        s.push_str(&variant_name(cx.opts, kvariant, &name));
+        s.push_str("(");
+        let args = (0..kvariant.fields.len()).map(|i| ["f", &i.to_string()].join("")).collect::<Vec<String>>();
+        s.push_str(&args.join(", "));
+        s.push_str(");\n");
+        s.push_str("                break;\n");
+        s.push_str("            }\n");
+    }
+    s.push_str("            default:\n");
+    s.push_str("                throw new JsonException(\"unknown variant of ");
+    s.push_str(wire_name(&kenum.name));
+    s.push_str(": \" + n);\n");
+    s.push_str("        }\n");
+    s.push_str("        while (reader.TokenType != JsonTokenType.EndArray)\n");
+    s.push_str("        {\n");
+    s.push_str("            reader.Skip();\n");
+    s.push_str("            reader.Read();\n");
+    s.push_str("        }\n");
+    s.push_str("        return r;\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    public override void Write(Utf8JsonWriter writer, ");
+    s.push_str(&name);
+    s.push_str(" value, JsonSerializerOptions options)\n");
+    s.push_str("    {\n");
+    s.push_str("        writer.WriteStartArray();\n");
+    s.push_str("        switch (value)\n");
+    s.push_str("        {\n");
+    for kvariant in &kenum.variants {
+        let variant = variant_name(cx.opts, kvariant, &name);
+        s.push_str("            case ");
+        s.push_str(&variant);
+        s.push_str(if kvariant.fields.len() > 0 { " v:\n" } else { " _:\n" });
+        s.push_str("                writer.WriteStringValue(");
+        s.push_str(&string_literal(wire_name(&kvariant.name)));
+        s.push_str(");\n");
+        for (i, kfield) in kvariant.fields.iter().enumerate() {
+            let value = ["v.", &field_name(cx.opts, kfield, i, &variant)].join("");
+            s.push_str("                ");
+            s.push_str(&serialize(cx, &kfield.ty, &value));
+            s.push_str("\n");
+        }
+        s.push_str("                break;\n");
+    }
+    s.push_str("            default:\n");
+    s.push_str("                throw new JsonException(\"unknown variant of ");
+    s.push_str(wire_name(&kenum.name));
+    s.push_str("\");\n");
+    s.push_str("        }\n");
+    s.push_str("        writer.WriteEndArray();\n");
+    s.push_str("    }\n");
+    s.push_str("}\n");
+    return s;
+}
+
+/// Parameters of a positional record named `record`.
+fn gen_parameters(cx: &Context, kfields: &[StructField], record: &str) -> Vec<String> {
+    let mut ss = Vec::<String>::new();
+    for (i, kfield) in kfields.iter().enumerate() {
+        let mut s = String::new();
+        if let Some(ref deprecation) = kfield.deprecation {
+            s.push_str("[property: ");
+            s.push_str(&obsolete_attribute(deprecation));
+            s.push_str("] ");
+        }
+        s.push_str(&csharp_type(cx, &kfield.ty));
+        s.push_str(" ");
+        s.push_str(&field_name(cx.opts, kfield, i, record));
+        ss.push(s);
+    }
+    return ss;
+}
+
+/// Reads a value at current token. The reader stays on the last
+/// token of the value.
+fn deserialize(cx: &Context, ty: &Type) -> String {
+    return ["JsonSerializer.Deserialize<", &csharp_type(cx, ty), ">(ref reader, options)!"].join("");
+}
+
+fn serialize(cx: &Context, ty: &Type, value: &str) -> String {
+    return ["JsonSerializer.Serialize<", &csharp_type(cx, ty), ">(writer, ", value, ", options);"].join("");
+}
+
+fn is_required(kfield: &StructField) -> bool {
+    if kfield.default { return false }
+    return match kfield.ty {
+        Type::Option(_) => false,
+        _ => true,
+    };
+}
+
+fn csharp_type(cx: &Context, ty: &Type) -> String {
+    return match ty {
+        Type::Core(ref core_type) => {
+            use self::CoreType::*;
+            match core_type {
+                Bool => "bool".to_string(),
+                U8 => "byte".to_string(),
+                U16 => "ushort".to_string(),
+                U32 => "uint".to_string(),
+                U64 => "ulong".to_string(),
+                I8 => "sbyte".to_string(),
+                I16 => "short".to_string(),
+                I32 => "int".to_string(),
+                I64 => "long".to_string(),
+                F32 => "float".to_string(),
+                F64 => "double".to_string(),
+            }
+        },
+        Type::String => "string".to_string(),
+        Type::Item(ref path) => type_path(cx, path),
+        // `T??` is not valid. Nested options are indistinguishable
+        // on the wire anyway.
+        Type::Option(ref ty1) => match **ty1 {
+            Type::Option(_) => csharp_type(cx, ty1),
+            _ => [&csharp_type(cx, ty1), "?"].join(""),
+        },
+        Type::Vec(ref ty1) => ["List<", &csharp_type(cx, ty1), ">"].join(""),
+    };
+}
+
+fn default_value(cx: &Context, ty: &Type) -> String {
+    return match ty {
+        Type::Core(CoreType::Bool) => "false".to_string(),
+        Type::Core(_) => "0".to_string(),
+        Type::String => "\"\"".to_string(),
+        Type::Item(ref path) => [&type_path(cx, path), ".IgenDefault"].join(""),
+        Type::Option(_) => "null".to_string(),
+        Type::Vec(ref ty1) => ["new List<", &csharp_type(cx, ty1), ">()"].join(""),
+    };
+}
+
+/// Path in generated code if the item is in the root module.
+/// Otherwise only the last segment is used like Swift.
+fn type_path(cx: &Context, path: &TypePath) -> String {
+    let segments = &path.0;
+    if segments.len() == 0 {
+        return "????".to_string();
+    }
+    let name = &segments[segments.len() - 1];
+    for i in 0..segments.len() - 1 {
+        if segments[i] != cx.kroot.name { continue }
+        let mods = segments[i + 1..segments.len() - 1].iter().map(|s| &s[..]).collect::<Vec<&str>>();
+        let kmod = match cx.kroot.find_module(&mods) {
+            Some(kmod) => kmod,
+            None => continue,
+        };
+        let found = kmod.items.iter().any(|kitem| match kitem {
+            Item::Enum(ref kenum) => &kenum.name == name,
+            Item::Struct(ref kstruct) => &kstruct.name == name,
+            Item::Module(_) => false,
+        });
+        if !found { continue }
+        let mut ss = vec![module_class_name(cx, cx.kroot, None)];
+        for j in 0..mods.len() {
+            let kparent = cx.kroot.find_module(&mods[..j]).unwrap();
+            let kmod1 = kparent.find_module(&mods[j..j + 1]).unwrap();
+            let class_name = module_class_name(cx, kmod1, Some((kparent, &ss[j])));
+            ss.push(class_name);
+        }
+        ss.push(csharp_ident(name));
+        return ["global::", &ss.join(".")].join("");
+    }
+    return csharp_ident(name);
+}
+
+fn module_name(opts: &GenOptions, name: &str) -> String {
+    let name = wire_name(name);
+    return match opts.naming {
+        Naming::Preserve => csharp_ident(name),
+        Naming::Idiomatic => csharp_ident(&upper_camel_case(name)),
+    };
+}
+
+/// A member cannot have the name of its enclosing type in C#.
+/// (CS0542) Such names get a trailing underscore.
+fn member_name(name: String, enclosing: &str) -> String {
+    if name == enclosing {
+        return [&name[..], "_"].join("");
+    }
+    return name;
+}
+
+/// Module classes contain items and other module classes, so a
+/// class which has the name of its parent, a sibling item or a
+/// child gets a trailing underscore. Types keep their names.
+fn module_class_name(cx: &Context, kmod: &Module, parent: Option<(&Module, &str)>) -> String {
+    let name = module_name(cx.opts, &kmod.name);
+    let item_name = |kitem: &Item| match kitem {
+        Item::Module(ref kmod1) => module_name(cx.opts, &kmod1.name),
+        Item::Enum(ref kenum) => csharp_ident(&kenum.name),
+        Item::Struct(ref kstruct) => csharp_ident(&kstruct.name),
+    };
+    let mut clashes = kmod.items.iter().any(|kitem| item_name(kitem) == name);
+    if let Some((kparent, parent_name)) = parent {
+        clashes = clashes || parent_name == name;
+        clashes = clashes || kparent.items.iter().any(|kitem| match kitem {
+            Item::Module(_) => false,
+            _ => item_name(kitem) == name,
+        });
+    }
+    if clashes {
+        return [&name[..], "_"].join("");
+    }
+    return name;
+}
+
+fn variant_name(opts: &GenOptions, kvariant: &EnumVariant, enclosing: &str) -> String {
+    return member_name(Lang::CSharp.variant_name(opts.naming, &kvariant.name), enclosing);
+}
+
+/// Tuple fields are named by their positions. (`F0`, `F1`, ...)
+fn field_name(opts: &GenOptions, kfield: &StructField, index: usize, enclosing: &str) -> String {
+    if kfield.name == "" {
+        return ["F", &index.to_string()].join("");
+    }
+    return member_name(Lang::CSharp.field_name(opts.naming, &kfield.name), enclosing);
+}
+
+fn fingerprint_member(fingerprint: &str) -> String {
+    return ["    public const string IgenFingerprint = \"", fingerprint, "\";\n"].join("");
+}
+
+fn obsolete(deprecation: &Option<Deprecation>, indent: &str) -> String {
+    return match deprecation {
+        Some(ref deprecation) => [indent, "[", &obsolete_attribute(deprecation), "]\n"].join(""),
+        None => String::new(),
+    };
+}
+
+fn obsolete_attribute(deprecation: &Deprecation) -> String {
+    return match deprecation_message(deprecation) {
+        Some(message) => ["Obsolete(", &string_literal(&message), ")"].join(""),
+        None => "Obsolete".to_string(),
+    };
+}
+
+fn csharp_ident(name: &str) -> String {
+    return Lang::CSharp.escape_ident(name);
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use schema::Module;
+    use schema::codegen::GenOptions;
+
+    fn module() -> Module {
+        return serde_json::from_str(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Struct": {"name": "Size", "visibility": "Public", "fields": [
+                {"name": "w", "ty": {"Core": "I32"}, "default": true}]}},
+            {"Struct": {"name": "Shape", "visibility": "Public", "fields": [
+                {"name": "class", "ty": "String"},
+                {"name": "size", "ty": {"Item": ["p", "Size"]}, "default": true},
+                {"name": "tags", "ty": {"Vec": {"Option": "String"}}},
+                {"name": "note", "ty": {"Option": "String"}}]}},
+            {"Enum": {"name": "Command", "visibility": "Public", "variants": [
+                {"name": "Stop", "fields": []},
+                {"name": "Resize", "fields": [
+                    {"name": "", "ty": {"Item": ["p", "Size"]}},
+                    {"name": "", "ty": {"Core": "U64"}, "default": true}]}]}}]}"#).unwrap();
+    }
+
+    #[test]
+    fn decodes_objects_with_defaults_and_required_keys() {
+        let code = module().gen_csharp_code(&GenOptions::new());
+        assert!(code.contains("        public static Size IgenDefault => new Size(0);\n"));
+        assert!(code.contains(concat!(
+            "                string f0 = default!;\n",
+            "                bool has0 = false;\n",
+            "                global::P.Size f1 = global::P.Size.IgenDefault;\n",
+            "                List<string?> f2 = default!;\n",
+            "                bool has2 = false;\n",
+            "                string? f3 = default!;\n")));
+        assert!(code.contains(concat!(
+            "                        default:\n",
+            "                            reader.Skip();\n",
+            "                            break;\n",
+            "                    }\n",
+            "                }\n",
+            "                if (!has0) throw new JsonException(\"missing key class\");\n",
+            "                if (!has2) throw new JsonException(\"missing key tags\");\n",
+            "                return new Shape(f0, f1, f2, f3);\n")));
+    }
+
+    #[test]
+    fn omits_absent_options_of_structs() {
+        let code = module().gen_csharp_code(&GenOptions::new());
+        assert!(code.contains(concat!(
+            "                writer.WritePropertyName(\"tags\"); JsonSerializer.Serialize<List<string?>>(writer, value.Tags, options);\n",
+            "                if (value.Note != null) { writer.WritePropertyName(\"note\"); JsonSerializer.Serialize<string?>(writer, value.Note, options); }\n")));
+    }
+
+    #[test]
+    fn decodes_variants_from_arrays() {
+        let code = module().gen_csharp_code(&GenOptions::new());
+        assert!(code.contains("        public sealed record Resize(global::P.Size F0, ulong F1) : Command;\n"));
+        // Missing trailing fields with defaults are allowed, and
+        // extra fields from newer writers are skipped.
+        assert!(code.contains(concat!(
+            "                        global::P.Size f0 = default!;\n",
+            "                        if (reader.TokenType != JsonTokenType.EndArray) { f0 = JsonSerializer.Deserialize<global::P.Size>(ref reader, options)!; reader.Read(); }\n",
+            "                        else throw new JsonException(\"missing field 0 of Resize\");\n",
+            "                        ulong f1 = 0;\n",
+            "                        if (reader.TokenType != JsonTokenType.EndArray) { f1 = JsonSerializer.Deserialize<ulong>(ref reader, options)!; reader.Read(); }\n",
+            "                        r = new Resize(f0, f1);\n")));
+        assert!(code.contains(concat!(
+            "                    default:\n",
+            "                        throw new JsonException(\"unknown variant of Command: \" + n);\n",
+            "                }\n",
+            "                while (reader.TokenType != JsonTokenType.EndArray)\n",
+            "                {\n",
+            "                    reader.Skip();\n")));
+    }
+
+    #[test]
+    fn encodes_variants_as_name_and_fields() {
+        let code = module().gen_csharp_code(&GenOptions::new());
+        assert!(code.contains(concat!(
+            "                    case Stop _:\n",
+            "                        writer.WriteStringValue(\"Stop\");\n",
+            "                        break;\n",
+            "                    case Resize v:\n",
+            "                        writer.WriteStringValue(\"Resize\");\n",
+            "                        JsonSerializer.Serialize<global::P.Size>(writer, v.F0, options);\n",
+            "                        JsonSerializer.Serialize<ulong>(writer, v.F1, options);\n",
+            "                        break;\n")));
+    }
+
+    #[test]
+    fn renames_members_named_like_enclosing_types() {
+        let kmod: Module = serde_json::from_str(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Module": {"name": "message", "visibility": "Public", "items": [
+                {"Struct": {"name": "Message", "visibility": "Public", "fields": []}}]}},
+            {"Struct": {"name": "Id", "visibility": "Public", "fields": [
+                {"name": "id", "ty": {"Item": ["p", "message", "Message"]}}]}},
+            {"Enum": {"name": "Shape", "visibility": "Public", "variants": [
+                {"name": "Shape", "fields": []}]}}]}"#).unwrap();
+        let code = kmod.gen_csharp_code(&GenOptions::new());
+        assert!(code.contains("    public static partial class Message_\n"));
+        assert!(code.contains("        global::P.Message_.Message Id_)\n"));
+        assert!(code.contains("        public sealed record Shape_() : Shape;\n"));
+    }
+}
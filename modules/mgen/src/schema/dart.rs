@@ -0,0 +1,574 @@
+use super::Module;
+use super::Struct;
+use super::Enum;
+use super::EnumVariant;
+use super::StructField;
+use super::Item;
+use super::Type;
+use super::CoreType;
+use super::TypePath;
+use super::Visibility;
+use super::Deprecation;
+use super::ident::Lang;
+use super::ident::wire_name;
+use super::codegen::GenOptions;
+use super::codegen::deprecation_message;
+
+/// Dart backend.
+///
+/// Structs become immutable classes. Enums become a sealed class
+/// with a final subclass for each variant. Each class has
+/// `toJson` and `fromJson` which convert from/to JSON values of
+/// `dart:convert` in the same wire format of Swift code.
+///
+/// Modules are flattened like Swift code. Dart 3 or later is
+/// required for sealed classes and patterns.
+impl Module {
+    pub fn gen_dart_code(&self, opts: &GenOptions) -> String {
+        let mut ss = Vec::<String>::new();
+        for kitem in &self.items {
+            if kitem.visibility() != Visibility::Public { continue }
+            ss.push(kitem.gen_dart_code(opts));
+        }
+        return ss.join("\n");
+    }
+    /// Header and helpers needed by the code of this module as a
+    /// root.
+    pub fn gen_dart_prelude(&self) -> String {
+        let mut s = String::new();
+        s.push_str("// ignore_for_file: deprecated_member_use_from_same_package\n");
+        s.push_str("\n");
+        s.push_str("Object? _igenKey(Map<String, Object?> j, String key, String type) {\n");
+        s.push_str("  if (!j.containsKey(key)) {\n");
+        s.push_str("    throw FormatException('igen: missing key `$key` of $type');\n");
+        s.push_str("  }\n");
+        s.push_str("  return j[key];\n");
+        s.push_str("}\n");
+        s.push_str("\n");
+        s.push_str("Object? _igenElement(List<Object?> j, int index, String type) {\n");
+        s.push_str("  if (j.length <= index) {\n");
+        s.push_str("    throw FormatException('igen: missing field ${index - 1} of $type');\n");
+        s.push_str("  }\n");
+        s.push_str("  return j[index];\n");
+        s.push_str("}\n");
+        s.push_str("\n");
+        s.push_str("int? _igenEncodeU64(int? v) {\n");
+        s.push_str("  if (v != null && v < 0) {\n");
+        s.push_str("    throw ArgumentError.value(v, 'u64', 'igen: negative value');\n");
+        s.push_str("  }\n");
+        s.push_str("  return v;\n");
+        s.push_str("}\n");
+        s.push_str("\n");
+        s.push_str("int _igenDecodeU64(Object? j) {\n");
+        s.push_str("  if (j is! int || j < 0) {\n");
+        s.push_str("    throw FormatException('igen: $j is not a u64 in range of int');\n");
+        s.push_str("  }\n");
+        s.push_str("  return j;\n");
+        s.push_str("}\n");
+        return s;
+    }
+    pub fn gen_dart_fingerprint(&self) -> String {
+        let mut s = String::new();
+        s.push_str("abstract final class IgenSchema {\n");
+        s.push_str("  static const fingerprint = '");
+        s.push_str(&self.fingerprint());
+        s.push_str("';\n");
+        s.push_str("}\n");
+        return s;
+    }
+    /// Same messages with Swift `IgenHandshake`.
+    /// Dart has no nested classes, so the messages are top-level
+    /// classes generated from equivalent schema structs.
+    pub fn gen_dart_handshake(&self, opts: &GenOptions) -> String {
+        let mut hello = Struct::new();
+        hello.name = "IgenHello".to_string();
+        hello.fields.push(StructField { name: "fingerprints".to_string(), ty: Type::Vec(Box::new(Type::String)), default: false, deprecation: None, tag: None });
+        let mut hello_ack = Struct::new();
+        hello_ack.name = "IgenHelloAck".to_string();
+        hello_ack.fields.push(StructField { name: "fingerprint".to_string(), ty: Type::Option(Box::new(Type::String)), default: false, deprecation: None, tag: None });
+        let fingerprints = dart_field_name(opts, &hello.fields[0], 0);
+        let fingerprint = dart_field_name(opts, &hello_ack.fields[0], 0);
+
+        let mut s = String::new();
+        s.push_str("abstract final class IgenHandshake {\n");
+        s.push_str("  static const fingerprint = IgenSchema.fingerprint;\n");
+        s.push_str("\n");
+        s.push_str("  static IgenHello hello() => const IgenHello(");
+        s.push_str(&fingerprints);
+        s.push_str(": [fingerprint]);\n");
+        s.push_str("\n");
+        s.push_str("  /// `null` fingerprint refuses the hello.\n");
+        s.push_str("  static IgenHelloAck accept(IgenHello hello) => IgenHelloAck(\n");
+        s.push_str("      ");
+        s.push_str(&fingerprint);
+        s.push_str(": hello.");
+        s.push_str(&fingerprints);
+        s.push_str(".contains(fingerprint) ? fingerprint : null);\n");
+        s.push_str("}\n");
+        s.push_str("\n");
+        s.push_str(&hello.gen_dart_code(opts));
+        s.push_str("\n");
+        s.push_str(&hello_ack.gen_dart_code(opts));
+        return s;
+    }
+}
+impl Item {
+    pub fn gen_dart_code(&self, opts: &GenOptions) -> String {
+        return match self {
+            Item::Module(ref kmod) => kmod.gen_dart_code(opts),
+            Item::Enum(ref kenum) => kenum.gen_dart_code(opts),
+            Item::Struct(ref kstruct) => kstruct.gen_dart_code(opts),
+        };
+    }
+}
+impl Enum {
+    pub fn gen_dart_code(&self, opts: &GenOptions) -> String {
+        let name = dart_ident(&self.name);
+        let mut s = String::new();
+        s.push_str(&dart_deprecation(&self.deprecation, ""));
+        s.push_str("sealed class ");
+        s.push_str(&name);
+        s.push_str(" {\n");
+        s.push_str(&dart_fingerprint_member(&self.fingerprint()));
+        s.push_str("\n");
+        s.push_str("  const ");
+        s.push_str(&name);
+        s.push_str("();\n");
+        s.push_str("\n");
+        s.push_str("  Object? toJson();\n");
+        s.push_str("\n");
+        s.push_str("  factory ");
+        s.push_str(&name);
+        s.push_str(".fromJson(Object? json) {\n");
+        s.push_str("    final j = json as List<Object?>;\n");
+        s.push_str("    final n = _igenElement(j, 0, ");
+        s.push_str(&dart_string_literal(wire_name(&self.name)));
+        s.push_str(") as String;\n");
+        s.push_str("    switch (n) {\n");
+        for kvariant in &self.variants {
+            s.push_str("      case ");
+            s.push_str(&dart_string_literal(wire_name(&kvariant.name)));
+            s.push_str(":\n");
+            s.push_str("        return ");
+            s.push_str(&self.dart_variant_class_name(opts, kvariant));
+            s.push_str(".fromJson(j);\n");
+        }
+        s.push_str("    }\n");
+        s.push_str("    throw FormatException(");
+        s.push_str(&dart_string_literal(&["igen: unknown variant of ", wire_name(&self.name), ": "].join("")));
+        s.push_str(" + n);\n");
+        s.push_str("  }\n");
+        s.push_str("}\n");
+        for kvariant in &self.variants {
+            s.push_str("\n");
+            s.push_str(&self.gen_dart_variant(opts, kvariant));
+        }
+        return s;
+    }
+}
+impl Struct {
+    pub fn gen_dart_code(&self, opts: &GenOptions) -> String {
+        let name = dart_ident(&self.name);
+        let mut s = String::new();
+        s.push_str(&dart_deprecation(&self.deprecation, ""));
+        s.push_str("final class ");
+        s.push_str(&name);
+        s.push_str(" {\n");
+        s.push_str(&dart_fingerprint_member(&self.fingerprint()));
+        if self.fields.len() > 0 {
+            s.push_str("\n");
+        }
+        s.push_str(&gen_dart_fields(opts, &self.fields));
+        s.push_str("\n");
+        s.push_str(&gen_dart_constructor(opts, &name, &self.fields));
+        if self.has_default() {
+            s.push_str("\n");
+            s.push_str("  static ");
+            s.push_str(&name);
+            s.push_str(" igenDefault() => ");
+            s.push_str(&name);
+            s.push_str("(");
+            let mut ss = Vec::<String>::new();
+            for (i, kfield) in self.fields.iter().enumerate() {
+                ss.push([&dart_field_name(opts, kfield, i), ": ", &kfield.ty.gen_dart_default_value()].join(""));
+            }
+            s.push_str(&ss.join(", "));
+            s.push_str(");\n");
+        }
+        s.push_str("\n");
+        s.push_str("  Map<String, Object?> toJson() => {\n");
+        for (i, kfield) in self.fields.iter().enumerate() {
+            let value = dart_field_name(opts, kfield, i);
+            let key = dart_string_literal(wire_name(&kfield.name));
+            s.push_str("        ");
+            // Swift omits `nil` of optional properties.
+            if let Type::Option(_) = kfield.ty {
+                s.push_str("if (");
+                s.push_str(&value);
+                s.push_str(" != null) ");
+            }
+            s.push_str(&key);
+            s.push_str(": ");
+            s.push_str(&kfield.ty.gen_dart_encoding(&value, false, 0));
+            s.push_str(",\n");
+        }
+        s.push_str("      };\n");
+        s.push_str("\n");
+        s.push_str("  factory ");
+        s.push_str(&name);
+        s.push_str(".fromJson(Object? json) {\n");
+        s.push_str("    final j = json as Map<String, Object?>;\n");
+        s.push_str("    return ");
+        s.push_str(&name);
+        s.push_str("(\n");
+        for (i, kfield) in self.fields.iter().enumerate() {
+            let key = dart_string_literal(wire_name(&kfield.name));
+            s.push_str("      ");
+            s.push_str(&dart_field_name(opts, kfield, i));
+            s.push_str(": ");
+            // Unknown keys are ignored. Missing keys are allowed
+            // only for optional fields and fields with defaults.
+            if kfield.default {
+                let value = ["j[", &key, "]"].join("");
+                s.push_str("j.containsKey(");
+                s.push_str(&key);
+                s.push_str(") ? ");
+                s.push_str(&kfield.ty.gen_dart_decoding(&value, 0));
+                s.push_str(" : ");
+                s.push_str(&kfield.ty.gen_dart_default_value());
+            }
+            else if let Type::Option(_) = kfield.ty {
+                let value = ["j[", &key, "]"].join("");
+                s.push_str(&kfield.ty.gen_dart_decoding(&value, 0));
+            }
+            else {
+                let value = ["_igenKey(j, ", &key, ", ", &dart_string_literal(wire_name(&self.name)), ")"].join("");
+                s.push_str(&kfield.ty.gen_dart_decoding(&value, 0));
+            }
+            s.push_str(",\n");
+        }
+        s.push_str("    );\n");
+        s.push_str("  }\n");
+        s.push_str("}\n");
+        return s;
+    }
+}
+
+impl Enum {
+    fn dart_variant_class_name(&self, opts: &GenOptions, kvariant: &EnumVariant) -> String {
+        let name = [wire_name(&self.name), &Lang::Dart.variant_name(opts.naming, &kvariant.name)].join("");
+        return dart_ident(&name);
+    }
+    /// Encoded as `[name, field0, field1, ...]`.
+    fn gen_dart_variant(&self, opts: &GenOptions, kvariant: &EnumVariant) -> String {
+        let name = self.dart_variant_class_name(opts, kvariant);
+        let mut s = String::new();
+        s.push_str(&dart_deprecation(&kvariant.deprecation, ""));
+        s.push_str("final class ");
+        s.push_str(&name);
+        s.push_str(" extends ");
+        s.push_str(&dart_ident(&self.name));
+        s.push_str(" {\n");
+        s.push_str(&gen_dart_fields(opts, &kvariant.fields));
+        if kvariant.fields.len() > 0 {
+            s.push_str("\n");
+        }
+        s.push_str(&gen_dart_constructor(opts, &name, &kvariant.fields));
+        s.push_str("\n");
+        s.push_str("  @override\n");
+        s.push_str("  List<Object?> toJson() => [");
+        let mut ss = vec![dart_string_literal(wire_name(&kvariant.name))];
+        for (i, kfield) in kvariant.fields.iter().enumerate() {
+            let value = dart_field_name(opts, kfield, i);
+            ss.push(kfield.ty.gen_dart_encoding(&value, false, 0));
+        }
+        s.push_str(&ss.join(", "));
+        s.push_str("];\n");
+        s.push_str("\n");
+        // Trailing elements are ignored. Missing trailing elements
+        // are allowed only for fields with defaults.
+        s.push_str("  factory ");
+        s.push_str(&name);
+        s.push_str(".fromJson(List<Object?> j) {\n");
+        s.push_str("    return ");
+        s.push_str(&name);
+        s.push_str("(");
+        if kvariant.fields.len() > 0 {
+            s.push_str("\n");
+        }
+        for (i, kfield) in kvariant.fields.iter().enumerate() {
+            let index = (i + 1).to_string();
+            s.push_str("      ");
+            if kfield.name != "" {
+                s.push_str(&dart_field_name(opts, kfield, i));
+                s.push_str(": ");
+            }
+            if kfield.default {
+                let value = ["j[", &index, "]"].join("");
+                s.push_str("j.length > ");
+                s.push_str(&index);
+                s.push_str(" ? ");
+                s.push_str(&kfield.ty.gen_dart_decoding(&value, 0));
+                s.push_str(" : ");
+                s.push_str(&kfield.ty.gen_dart_default_value());
+            }
+            else {
+                let value = ["_igenElement(j, ", &index, ", ", &dart_string_literal(wire_name(&kvariant.name)), ")"].join("");
+                s.push_str(&kfield.ty.gen_dart_decoding(&value, 0));
+            }
+            s.push_str(",\n");
+        }
+        if kvariant.fields.len() > 0 {
+            s.push_str("    ");
+        }
+        s.push_str(");\n");
+        s.push_str("  }\n");
+        s.push_str("}\n");
+        return s;
+    }
+}
+
+fn gen_dart_fields(opts: &GenOptions, kfields: &[StructField]) -> String {
+    let mut s = String::new();
+    for (i, kfield) in kfields.iter().enumerate() {
+        s.push_str(&dart_deprecation(&kfield.deprecation, "  "));
+        s.push_str("  final ");
+        s.push_str(&kfield.ty.gen_dart_code());
+        s.push_str(" ");
+        s.push_str(&dart_field_name(opts, kfield, i));
+        s.push_str(";\n");
+    }
+    return s;
+}
+
+/// Tuple fields are positional, and named fields are named
+/// parameters. Optional fields can be omitted.
+fn gen_dart_constructor(opts: &GenOptions, name: &str, kfields: &[StructField]) -> String {
+    let mut positional = Vec::<String>::new();
+    let mut named = Vec::<String>::new();
+    for (i, kfield) in kfields.iter().enumerate() {
+        let param = ["this.", &dart_field_name(opts, kfield, i)].join("");
+        if kfield.name == "" {
+            positional.push(param);
+            continue;
+        }
+        match kfield.ty {
+            Type::Option(_) => named.push(param),
+            _ => named.push(["required ", &param].join("")),
+        }
+    }
+    if named.len() > 0 {
+        positional.push(["{", &named.join(", "), "}"].join(""));
+    }
+    return ["  const ", name, "(", &positional.join(", "), ");\n"].join("");
+}
+
+/// Tuple fields are named by their positions. (`f0`, `f1`, ...)
+fn dart_field_name(opts: &GenOptions, kfield: &StructField, index: usize) -> String {
+    if kfield.name == "" {
+        return ["f", &index.to_string()].join("");
+    }
+    return Lang::Dart.field_name(opts.naming, &kfield.name);
+}
+
+impl Type {
+    /// `u64` is `int` too, so values from 2^63 are rejected when
+    /// they are decoded or encoded. (negative `int`)
+    pub fn gen_dart_code(&self) -> String {
+        return match self {
+            Type::Core(CoreType::Bool) => "bool".to_string(),
+            Type::Core(CoreType::F32) => "double".to_string(),
+            Type::Core(CoreType::F64) => "double".to_string(),
+            Type::Core(_) => "int".to_string(),
+            Type::String => "String".to_string(),
+            Type::Item(ref path) => path.gen_dart_code(),
+            // Nested options are indistinguishable on the wire.
+            Type::Option(ref ty) => match **ty {
+                Type::Option(_) => ty.gen_dart_code(),
+                _ => [&ty.gen_dart_code(), "?"].join(""),
+            },
+            Type::Vec(ref ty) => ["List<", &ty.gen_dart_code(), ">"].join(""),
+        };
+    }
+    pub fn gen_dart_default_value(&self) -> String {
+        return match self {
+            Type::Core(CoreType::Bool) => "false".to_string(),
+            Type::Core(CoreType::F32) => "0.0".to_string(),
+            Type::Core(CoreType::F64) => "0.0".to_string(),
+            Type::Core(_) => "0".to_string(),
+            Type::String => "''".to_string(),
+            Type::Item(ref path) => [&path.gen_dart_code(), ".igenDefault()"].join(""),
+            Type::Option(_) => "null".to_string(),
+            Type::Vec(_) => "const []".to_string(),
+        };
+    }
+    /// Expression converting `value` into JSON value.
+    /// `nullable` uses null-aware access for values of options.
+    /// `depth` makes names of closure parameters unique.
+    fn gen_dart_encoding(&self, value: &str, nullable: bool, depth: usize) -> String {
+        let access = if nullable { "?." } else { "." };
+        return match self {
+            Type::Core(CoreType::U64) => ["_igenEncodeU64(", value, ")"].join(""),
+            Type::Core(_) => value.to_string(),
+            Type::String => value.to_string(),
+            Type::Item(_) => [value, access, "toJson()"].join(""),
+            Type::Option(ref ty) => ty.gen_dart_encoding(value, true, depth),
+            Type::Vec(ref ty) => {
+                let x = ["x", &depth.to_string()].join("");
+                let value1 = ty.gen_dart_encoding(&x, false, depth + 1);
+                if value1 == x { return value.to_string() }
+                [value, access, "map((", &x, ") => ", &value1, ").toList()"].join("")
+            },
+        };
+    }
+    /// Expression converting JSON `value` into this type.
+    fn gen_dart_decoding(&self, value: &str, depth: usize) -> String {
+        let x = ["x", &depth.to_string()].join("");
+        return match self {
+            Type::Core(CoreType::Bool) => [value, " as bool"].join(""),
+            Type::Core(CoreType::F32) => ["(", value, " as num).toDouble()"].join(""),
+            Type::Core(CoreType::F64) => ["(", value, " as num).toDouble()"].join(""),
+            Type::Core(CoreType::U64) => ["_igenDecodeU64(", value, ")"].join(""),
+            Type::Core(_) => [value, " as int"].join(""),
+            Type::String => [value, " as String"].join(""),
+            Type::Item(ref path) => [&path.gen_dart_code(), ".fromJson(", value, ")"].join(""),
+            Type::Option(ref ty) => {
+                ["switch (", value, ") { null => null, final ", &x, " => ", &ty.gen_dart_decoding(&x, depth + 1), " }"].join("")
+            },
+            Type::Vec(ref ty) => {
+                ["[for (final ", &x, " in ", value, " as List<Object?>) ", &ty.gen_dart_decoding(&x, depth + 1), "]"].join("")
+            },
+        };
+    }
+}
+
+impl TypePath {
+    pub fn gen_dart_code(&self) -> String {
+        return match self.0.last() {
+            Some(ref name) => dart_ident(name),
+            None => "????".to_string(),
+        };
+    }
+}
+
+fn dart_fingerprint_member(fingerprint: &str) -> String {
+    return ["  static const igenFingerprint = '", fingerprint, "';\n"].join("");
+}
+
+fn dart_deprecation(deprecation: &Option<Deprecation>, indent: &str) -> String {
+    let deprecation = match deprecation {
+        Some(ref deprecation) => deprecation,
+        None => return String::new(),
+    };
+    let message = deprecation_message(deprecation).unwrap_or("deprecated".to_string());
+    return [indent, "@Deprecated(", &dart_string_literal(&message), ")\n"].join("");
+}
+
+/// Single quoted. `$` starts interpolation in Dart.
+fn dart_string_literal(s: &str) -> String {
+    let mut r = String::new();
+    r.push('\'');
+    for c in s.chars() {
+        match c {
+            '\'' => r.push_str("\\'"),
+            '\\' => r.push_str("\\\\"),
+            '$' => r.push_str("\\$"),
+            '\n' => r.push_str("\\n"),
+            '\r' => r.push_str("\\r"),
+            '\t' => r.push_str("\\t"),
+            _ => r.push(c),
+        }
+    }
+    r.push('\'');
+    return r;
+}
+
+fn dart_ident(name: &str) -> String {
+    return Lang::Dart.escape_ident(name);
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use schema::Module;
+    use schema::codegen::GenOptions;
+
+    fn module() -> Module {
+        return serde_json::from_str(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Struct": {"name": "Size", "visibility": "Public", "fields": [
+                {"name": "w", "ty": {"Core": "I32"}, "default": true}]}},
+            {"Struct": {"name": "Shape", "visibility": "Public", "fields": [
+                {"name": "class", "ty": "String"},
+                {"name": "size", "ty": {"Item": ["p", "Size"]}, "default": true},
+                {"name": "bytes", "ty": {"Vec": {"Option": {"Core": "U64"}}}},
+                {"name": "ratio", "ty": {"Core": "F64"}},
+                {"name": "note", "ty": {"Option": "String"}}]}},
+            {"Enum": {"name": "Command", "visibility": "Public", "variants": [
+                {"name": "Stop", "fields": []},
+                {"name": "Resize", "fields": [
+                    {"name": "", "ty": {"Item": ["p", "Size"]}},
+                    {"name": "", "ty": {"Vec": "String"}, "default": true}]}]}}]}"#).unwrap();
+    }
+
+    #[test]
+    fn decodes_objects_with_defaults_and_required_keys() {
+        let code = module().gen_dart_code(&GenOptions::new());
+        assert!(code.contains("  static Size igenDefault() => Size(w: 0);\n"));
+        assert!(code.contains("  const Shape({required this.class_, required this.size, required this.bytes, required this.ratio, this.note});\n"));
+        assert!(code.contains(concat!(
+            "    return Shape(\n",
+            "      class_: _igenKey(j, 'class', 'Shape') as String,\n",
+            "      size: j.containsKey('size') ? Size.fromJson(j['size']) : Size.igenDefault(),\n",
+            "      bytes: [for (final x0 in _igenKey(j, 'bytes', 'Shape') as List<Object?>) switch (x0) { null => null, final x1 => _igenDecodeU64(x1) }],\n",
+            "      ratio: (_igenKey(j, 'ratio', 'Shape') as num).toDouble(),\n",
+            "      note: switch (j['note']) { null => null, final x0 => x0 as String },\n",
+            "    );\n")));
+    }
+
+    #[test]
+    fn omits_absent_options_of_structs() {
+        let code = module().gen_dart_code(&GenOptions::new());
+        assert!(code.contains(concat!(
+            "  Map<String, Object?> toJson() => {\n",
+            "        'class': class_,\n",
+            "        'size': size.toJson(),\n",
+            "        'bytes': bytes.map((x0) => _igenEncodeU64(x0)).toList(),\n",
+            "        'ratio': ratio,\n",
+            "        if (note != null) 'note': note,\n",
+            "      };\n")));
+    }
+
+    #[test]
+    fn decodes_variants_with_missing_trailing_defaults() {
+        let code = module().gen_dart_code(&GenOptions::new());
+        assert!(code.contains("  List<Object?> toJson() => ['Resize', f0.toJson(), f1];\n"));
+        assert!(code.contains(concat!(
+            "    return CommandResize(\n",
+            "      Size.fromJson(_igenElement(j, 1, 'Resize')),\n",
+            "      j.length > 2 ? [for (final x0 in j[2] as List<Object?>) x0 as String] : const [],\n",
+            "    );\n")));
+        assert!(code.contains(concat!(
+            "    final n = _igenElement(j, 0, 'Command') as String;\n",
+            "    switch (n) {\n",
+            "      case 'Stop':\n",
+            "        return CommandStop.fromJson(j);\n",
+            "      case 'Resize':\n",
+            "        return CommandResize.fromJson(j);\n",
+            "    }\n",
+            "    throw FormatException('igen: unknown variant of Command: ' + n);\n")));
+    }
+
+    #[test]
+    fn checks_range_of_u64() {
+        let kmod: Module = serde_json::from_str(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Struct": {"name": "Size", "visibility": "Public", "fields": [
+                {"name": "bytes", "ty": {"Core": "U64"}}]}}]}"#).unwrap();
+        let code = kmod.gen_dart_code(&GenOptions::new());
+        assert!(code.contains("        'bytes': _igenEncodeU64(bytes),\n"));
+        assert!(code.contains("_igenDecodeU64(_igenKey(j, 'bytes', 'Size'))"));
+        assert!(kmod.gen_dart_prelude().contains(concat!(
+            "int _igenDecodeU64(Object? j) {\n",
+            "  if (j is! int || j < 0) {\n",
+            "    throw FormatException('igen: $j is not a u64 in range of int');\n")));
+    }
+}
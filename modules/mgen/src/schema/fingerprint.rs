@@ -0,0 +1,152 @@
+
+use super::Module;
+use super::Item;
+use super::Enum;
+use super::Struct;
+use super::StructField;
+use super::Visibility;
+
+/// Fingerprints of exported schema.
+///
+/// A fingerprint is a hash of canonical text form of an item.
+/// Canonical text contains only structural shape: names, field
+/// order and types. Module fingerprint includes only exported
+/// items sorted by name, so reordering items or adding private
+/// items does not change it.
+///
+/// Hash function is 64-bit FNV-1a which is simple enough to be
+/// implemented in any language and never changes.
+impl Module {
+    pub fn fingerprint(&self) -> String {
+        return fnv1a64_hex(&self.canonical_text());
+    }
+    pub fn canonical_text(&self) -> String {
+        let mut s = String::new();
+        s.push_str("mod ");
+        s.push_str(&self.name);
+        s.push_str("{");
+        let mut kitems = self.items.iter()
+            .filter(|kitem| kitem.visibility() == Visibility::Public)
+            .collect::<Vec<&Item>>();
+        kitems.sort_by_key(|kitem| item_name(kitem));
+        for kitem in kitems {
+            s.push_str(&kitem.canonical_text());
+        }
+        s.push_str("}");
+        return s;
+    }
+}
+impl Item {
+    pub fn fingerprint(&self) -> String {
+        return fnv1a64_hex(&self.canonical_text());
+    }
+    pub fn canonical_text(&self) -> String {
+        return match self {
+            Item::Module(ref kmod) => kmod.canonical_text(),
+            Item::Enum(ref kenum) => kenum.canonical_text(),
+            Item::Struct(ref kstruct) => kstruct.canonical_text(),
+        };
+    }
+}
+impl Enum {
+    pub fn fingerprint(&self) -> String {
+        return fnv1a64_hex(&self.canonical_text());
+    }
+    pub fn canonical_text(&self) -> String {
+        let mut s = String::new();
+        s.push_str("enum ");
+        s.push_str(&self.name);
+        s.push_str("{");
+        for kvariant in &self.variants {
+            s.push_str(&kvariant.name);
+            s.push_str("(");
+            s.push_str(&canonical_fields(&kvariant.fields));
+            s.push_str("),");
+        }
+        s.push_str("}");
+        return s;
+    }
+}
+impl Struct {
+    pub fn fingerprint(&self) -> String {
+        return fnv1a64_hex(&self.canonical_text());
+    }
+    pub fn canonical_text(&self) -> String {
+        let mut s = String::new();
+        s.push_str("struct ");
+        s.push_str(&self.name);
+        s.push_str("{");
+        s.push_str(&canonical_fields(&self.fields));
+        s.push_str("}");
+        return s;
+    }
+}
+
+fn canonical_fields(kfields: &[StructField]) -> String {
+    let mut s = String::new();
+    for kfield in kfields {
+        s.push_str(&kfield.name);
+        s.push_str(":");
+        s.push_str(&kfield.ty.to_string());
+        s.push_str(",");
+    }
+    return s;
+}
+
+fn item_name(kitem: &Item) -> String {
+    return match kitem {
+        Item::Module(ref kmod) => kmod.name.clone(),
+        Item::Enum(ref kenum) => kenum.name.clone(),
+        Item::Struct(ref kstruct) => kstruct.name.clone(),
+    };
+}
+
+fn fnv1a64_hex(s: &str) -> String {
+    let mut h = 0xcbf29ce484222325u64;
+    for b in s.bytes() {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+    return format!("{:016x}", h);
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use schema::Module;
+    use super::fnv1a64_hex;
+
+    #[test]
+    fn matches_fnv1a64_vectors() {
+        assert_eq!(fnv1a64_hex(""), "cbf29ce484222325");
+        assert_eq!(fnv1a64_hex("a"), "af63dc4c8601ec8c");
+        assert_eq!(fnv1a64_hex("foobar"), "85944171f73967e8");
+    }
+
+    #[test]
+    fn ignores_item_order_and_private_items() {
+        let a: Module = serde_json::from_str(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Struct": {"name": "B", "visibility": "Public", "fields": [{"name": "x", "ty": {"Vec": "String"}}]}},
+            {"Enum": {"name": "A", "visibility": "Public", "variants": [{"name": "V", "fields": []}]}}]}"#).unwrap();
+        let b: Module = serde_json::from_str(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Enum": {"name": "A", "visibility": "Public", "variants": [{"name": "V", "fields": []}]}},
+            {"Struct": {"name": "Hidden", "visibility": "Private", "fields": []}},
+            {"Struct": {"name": "B", "visibility": "Public", "fields": [{"name": "x", "ty": {"Vec": "String"}}]}}]}"#).unwrap();
+        assert_eq!(a.canonical_text(), "mod p{enum A{V(),}struct B{x:Vec<String>,}}");
+        assert_eq!(a.fingerprint(), fnv1a64_hex(&a.canonical_text()));
+        assert_eq!(a.fingerprint(), b.fingerprint());
+    }
+
+    #[test]
+    fn changes_item_fingerprints_only_with_their_shapes() {
+        let a: Module = serde_json::from_str(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Struct": {"name": "A", "visibility": "Public", "fields": [{"name": "x", "ty": {"Core": "I32"}}]}},
+            {"Struct": {"name": "B", "visibility": "Public", "fields": [{"name": "y", "ty": "String"}]}}]}"#).unwrap();
+        let b: Module = serde_json::from_str(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Struct": {"name": "A", "visibility": "Public", "fields": [{"name": "x", "ty": {"Core": "I64"}}]}},
+            {"Struct": {"name": "B", "visibility": "Public", "fields": [{"name": "y", "ty": "String"}]}}]}"#).unwrap();
+        assert!(a.items[0].fingerprint() != b.items[0].fingerprint());
+        assert_eq!(a.items[1].fingerprint(), b.items[1].fingerprint());
+        assert!(a.fingerprint() != b.fingerprint());
+    }
+}
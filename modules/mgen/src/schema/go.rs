@@ -0,0 +1,812 @@
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use super::Module;
+use super::Struct;
+use super::Enum;
+use super::EnumVariant;
+use super::StructField;
+use super::Item;
+use super::Type;
+use super::CoreType;
+use super::TypePath;
+use super::Visibility;
+use super::Deprecation;
+use super::ident::Lang;
+use super::ident::wire_name;
+use super::ident::string_literal;
+use super::codegen::GenOptions;
+use super::codegen::deprecation_message;
+
+/// Go backend.
+///
+/// Each module becomes a package in a directory of the same
+/// name. Structs become structs with JSON tags. Enums become an
+/// interface with a concrete struct type for each variant.
+/// `MarshalJSON` and `UnmarshalJSON` implement the wire format of
+/// Swift code, and `Unmarshal<Enum>` decodes enum values.
+///
+/// `GenOptions::package` is the import path of the root package.
+/// Generated code needs Go 1.18 or later for generics.
+impl Module {
+    /// Files relative to output directory.
+    pub fn gen_go_files(&self, opts: &GenOptions) -> Vec<(PathBuf, String)> {
+        let cx = Context { kroot: self, opts: opts };
+        let mut files = Vec::<(PathBuf, String)>::new();
+        gen_packages(&cx, self, &mut Vec::new(), &mut files);
+        return files;
+    }
+    /// Packages of submodules are imported by their full paths,
+    /// which only `GenOptions::package` tells.
+    pub fn check_go_package(&self, opts: &GenOptions) -> Result<(), String> {
+        if opts.package.is_some() {
+            return Ok(());
+        }
+        let has_packages = self.items.iter().any(|kitem| match kitem {
+            Item::Module(ref kmod) => kmod.visibility == Visibility::Public,
+            _ => false,
+        });
+        if has_packages {
+            return Err(format!("Go target needs `package` as `{}` has submodules", self.name));
+        }
+        return Ok(());
+    }
+}
+
+struct Context<'a> {
+    kroot: &'a Module,
+    opts: &'a GenOptions,
+}
+
+/// A package being generated.
+struct Package<'a, 'b: 'a> {
+    cx: &'a Context<'b>,
+    /// Module names from the root. Empty for the root.
+    path: Vec<String>,
+    imports: BTreeSet<String>,
+}
+
+fn gen_packages(cx: &Context, kmod: &Module, path: &mut Vec<String>, files: &mut Vec<(PathBuf, String)>) {
+    let mut file = PathBuf::from(package_name(&cx.kroot.name));
+    for name in path.iter() {
+        file.push(package_name(name));
+    }
+    file.push([&package_name(&kmod.name), ".go"].join(""));
+    let code = {
+        let mut pkg = Package { cx: cx, path: path.clone(), imports: BTreeSet::new() };
+        gen_package(&mut pkg, kmod)
+    };
+    files.push((file, code));
+    for kitem in &kmod.items {
+        if let Item::Module(ref kmod1) = kitem {
+            if kmod1.visibility != Visibility::Public { continue }
+            path.push(kmod1.name.clone());
+            gen_packages(cx, kmod1, path, files);
+            path.pop();
+        }
+    }
+}
+
+fn gen_package(pkg: &mut Package, kmod: &Module) -> String {
+    let mut ss = Vec::<String>::new();
+    for kitem in &kmod.items {
+        if kitem.visibility() != Visibility::Public { continue }
+        match kitem {
+            Item::Module(_) => {},
+            Item::Enum(ref kenum) => ss.push(gen_enum(pkg, kenum)),
+            Item::Struct(ref kstruct) => ss.push(gen_struct(pkg, kstruct)),
+        }
+    }
+    if pkg.path.len() == 0 {
+        ss.push(gen_fingerprint(pkg.cx.kroot));
+        if pkg.cx.opts.handshake {
+            ss.push(gen_handshake());
+        }
+    }
+    ss.push(gen_helpers());
+
+    let mut s = String::new();
+    s.push_str("// Code generated by mgen. DO NOT EDIT.\n");
+    s.push_str("\n");
+    s.push_str("package ");
+    s.push_str(&package_name(&kmod.name));
+    s.push_str("\n");
+    s.push_str("\n");
+    s.push_str("import (\n");
+    s.push_str("\t\"encoding/json\"\n");
+    s.push_str("\t\"fmt\"\n");
+    s.push_str("\t\"strings\"\n");
+    if pkg.imports.len() > 0 {
+        s.push_str("\n");
+    }
+    for import in &pkg.imports {
+        s.push_str("\t\"");
+        s.push_str(import);
+        s.push_str("\"\n");
+    }
+    s.push_str(")\n");
+    s.push_str("\n");
+    s.push_str(&ss.join("\n"));
+    return s;
+}
+
+fn gen_fingerprint(kroot: &Module) -> String {
+    return ["const IgenSchemaFingerprint = \"", &kroot.fingerprint(), "\"\n"].join("");
+}
+
+/// Same messages with Swift `IgenHandshake`.
+fn gen_handshake() -> String {
+    let mut s = String::new();
+    s.push_str("type IgenHello struct {\n");
+    s.push_str("\tFingerprints []string `json:\"fingerprints\"`\n");
+    s.push_str("}\n");
+    s.push_str("\n");
+    s.push_str("// IgenHelloAck has nil fingerprint if the hello is refused.\n");
+    s.push_str("type IgenHelloAck struct {\n");
+    s.push_str("\tFingerprint *string `json:\"fingerprint,omitempty\"`\n");
+    s.push_str("}\n");
+    s.push_str("\n");
+    s.push_str("func IgenMakeHello() IgenHello {\n");
+    s.push_str("\treturn IgenHello{Fingerprints: []string{IgenSchemaFingerprint}}\n");
+    s.push_str("}\n");
+    s.push_str("\n");
+    s.push_str("func IgenAccept(hello IgenHello) IgenHelloAck {\n");
+    s.push_str("\tfor _, f := range hello.Fingerprints {\n");
+    s.push_str("\t\tif f == IgenSchemaFingerprint {\n");
+    s.push_str("\t\t\tr := IgenSchemaFingerprint\n");
+    s.push_str("\t\t\treturn IgenHelloAck{Fingerprint: &r}\n");
+    s.push_str("\t\t}\n");
+    s.push_str("\t}\n");
+    s.push_str("\treturn IgenHelloAck{}\n");
+    s.push_str("}\n");
+    return s;
+}
+
+/// Unexported, so every package has its own copy.
+fn gen_helpers() -> String {
+    let mut s = String::new();
+    s.push_str("func igenMissing(what string, name string) error {\n");
+    s.push_str("\treturn fmt.Errorf(\"igen: missing %s of %s\", what, name)\n");
+    s.push_str("}\n");
+    s.push_str("\n");
+    s.push_str("func igenDecode[T any](b []byte) (T, error) {\n");
+    s.push_str("\tvar v T\n");
+    s.push_str("\terr := json.Unmarshal(b, &v)\n");
+    s.push_str("\treturn v, err\n");
+    s.push_str("}\n");
+    s.push_str("\n");
+    s.push_str("func igenDecodeSlice[T any](f func([]byte) (T, error)) func([]byte) ([]T, error) {\n");
+    s.push_str("\treturn func(b []byte) ([]T, error) {\n");
+    s.push_str("\t\tvar a []json.RawMessage\n");
+    s.push_str("\t\tif err := json.Unmarshal(b, &a); err != nil {\n");
+    s.push_str("\t\t\treturn nil, err\n");
+    s.push_str("\t\t}\n");
+    s.push_str("\t\tr := make([]T, 0, len(a))\n");
+    s.push_str("\t\tfor _, e := range a {\n");
+    s.push_str("\t\t\tv, err := f(e)\n");
+    s.push_str("\t\t\tif err != nil {\n");
+    s.push_str("\t\t\t\treturn nil, err\n");
+    s.push_str("\t\t\t}\n");
+    s.push_str("\t\t\tr = append(r, v)\n");
+    s.push_str("\t\t}\n");
+    s.push_str("\t\treturn r, nil\n");
+    s.push_str("\t}\n");
+    s.push_str("}\n");
+    s.push_str("\n");
+    s.push_str("func igenDecodePtr[T any](f func([]byte) (T, error)) func([]byte) (*T, error) {\n");
+    s.push_str("\treturn func(b []byte) (*T, error) {\n");
+    s.push_str("\t\tif strings.TrimSpace(string(b)) == \"null\" {\n");
+    s.push_str("\t\t\treturn nil, nil\n");
+    s.push_str("\t\t}\n");
+    s.push_str("\t\tv, err := f(b)\n");
+    s.push_str("\t\tif err != nil {\n");
+    s.push_str("\t\t\treturn nil, err\n");
+    s.push_str("\t\t}\n");
+    s.push_str("\t\treturn &v, nil\n");
+    s.push_str("\t}\n");
+    s.push_str("}\n");
+    s.push_str("\n");
+    s.push_str("// igenSlice encodes nil slices as `[]` instead of `null`.\n");
+    s.push_str("func igenSlice[T any](s []T) []T {\n");
+    s.push_str("\tif s == nil {\n");
+    s.push_str("\t\treturn []T{}\n");
+    s.push_str("\t}\n");
+    s.push_str("\treturn s\n");
+    s.push_str("}\n");
+    s.push_str("\n");
+    s.push_str("// igenBytes encodes byte slices as arrays instead of base64.\n");
+    s.push_str("func igenBytes(s []uint8) []uint16 {\n");
+    s.push_str("\tr := make([]uint16, 0, len(s))\n");
+    s.push_str("\tfor _, e := range s {\n");
+    s.push_str("\t\tr = append(r, uint16(e))\n");
+    s.push_str("\t}\n");
+    s.push_str("\treturn r\n");
+    s.push_str("}\n");
+    s.push_str("\n");
+    s.push_str("func igenMapSlice[T any](s []T, f func(T) interface{}) []interface{} {\n");
+    s.push_str("\tr := make([]interface{}, 0, len(s))\n");
+    s.push_str("\tfor _, e := range s {\n");
+    s.push_str("\t\tr = append(r, f(e))\n");
+    s.push_str("\t}\n");
+    s.push_str("\treturn r\n");
+    s.push_str("}\n");
+    s.push_str("\n");
+    s.push_str("func igenMapPtr[T any](p *T, f func(T) interface{}) interface{} {\n");
+    s.push_str("\tif p == nil {\n");
+    s.push_str("\t\treturn nil\n");
+    s.push_str("\t}\n");
+    s.push_str("\treturn f(*p)\n");
+    s.push_str("}\n");
+    return s;
+}
+
+fn gen_struct(pkg: &mut Package, kstruct: &Struct) -> String {
+    let name = go_ident(&kstruct.name);
+    let mut s = String::new();
+    s.push_str(&deprecation_comment(&kstruct.deprecation, ""));
+    s.push_str("type ");
+    s.push_str(&name);
+    s.push_str(" struct {\n");
+    s.push_str(&gen_fields(pkg, &kstruct.fields, true));
+    s.push_str("}\n");
+    s.push_str("\n");
+    s.push_str(&fingerprint_const(&name, &kstruct.fingerprint()));
+    s.push_str("\n");
+
+    // `null` of optional fields is omitted like Swift.
+    s.push_str("func (v ");
+    s.push_str(&name);
+    s.push_str(") MarshalJSON() ([]byte, error) {\n");
+    s.push_str("\to := map[string]interface{}{}\n");
+    for (i, kfield) in kstruct.fields.iter().enumerate() {
+        let value = ["v.", &field_name(pkg.cx.opts, kfield, i)].join("");
+        let key = string_literal(wire_name(&kfield.name));
+        if let Type::Option(_) = kfield.ty {
+            s.push_str("\tif ");
+            s.push_str(&value);
+            s.push_str(" != nil {\n");
+            s.push_str("\t\to[");
+            s.push_str(&key);
+            s.push_str("] = ");
+            s.push_str(&encode_value(pkg, &kfield.ty, &value));
+            s.push_str("\n");
+            s.push_str("\t}\n");
+        }
+        else {
+            s.push_str("\to[");
+            s.push_str(&key);
+            s.push_str("] = ");
+            s.push_str(&encode_value(pkg, &kfield.ty, &value));
+            s.push_str("\n");
+        }
+    }
+    s.push_str("\treturn json.Marshal(o)\n");
+    s.push_str("}\n");
+    s.push_str("\n");
+
+    // Unknown keys are ignored. Missing keys are allowed only for
+    // optional fields and fields with defaults. Zero values of Go
+    // are same with `Default::default()` of Rust.
+    s.push_str("func (v *");
+    s.push_str(&name);
+    s.push_str(") UnmarshalJSON(b []byte) error {\n");
+    s.push_str("\tvar o map[string]json.RawMessage\n");
+    s.push_str("\tif err := json.Unmarshal(b, &o); err != nil {\n");
+    s.push_str("\t\treturn err\n");
+    s.push_str("\t}\n");
+    s.push_str("\t*v = ");
+    s.push_str(&name);
+    s.push_str("{}\n");
+    if kstruct.fields.len() > 0 {
+        s.push_str("\tvar err error\n");
+    }
+    for (i, kfield) in kstruct.fields.iter().enumerate() {
+        s.push_str("\tif r, ok := o[");
+        s.push_str(&string_literal(wire_name(&kfield.name)));
+        s.push_str("]; ok {\n");
+        s.push_str("\t\tif v.");
+        s.push_str(&field_name(pkg.cx.opts, kfield, i));
+        s.push_str(", err = ");
+        s.push_str(&decoder(pkg, &kfield.ty));
+        s.push_str("(r); err != nil {\n");
+        s.push_str("\t\t\treturn err\n");
+        s.push_str("\t\t}\n");
+        s.push_str("\t}");
+        let optional = match kfield.ty {
+            Type::Option(_) => true,
+            _ => false,
+        };
+        if !kfield.default && !optional {
+            s.push_str(" else {\n");
+            s.push_str("\t\treturn igenMissing(");
+            s.push_str(&string_literal(&["key `", wire_name(&kfield.name), "`"].join("")));
+            s.push_str(", ");
+            s.push_str(&string_literal(wire_name(&kstruct.name)));
+            s.push_str(")\n");
+            s.push_str("\t}");
+        }
+        s.push_str("\n");
+    }
+    s.push_str("\treturn nil\n");
+    s.push_str("}\n");
+    return s;
+}
+
+fn gen_enum(pkg: &mut Package, kenum: &Enum) -> String {
+    let name = go_ident(&kenum.name);
+    let marker = ["is", wire_name(&kenum.name)].join("");
+    let mut s = String::new();
+    let variant_names = kenum.variants.iter().map(|kvariant| variant_type_name(pkg.cx.opts, kenum, kvariant)).collect::<Vec<String>>();
+    s.push_str("// ");
+    s.push_str(&name);
+    s.push_str(" is one of ");
+    s.push_str(&variant_names.join(", "));
+    s.push_str(".\n");
+    if kenum.deprecation.is_some() {
+        s.push_str("//\n");
+    }
+    s.push_str(&deprecation_comment(&kenum.deprecation, ""));
+    s.push_str("type ");
+    s.push_str(&name);
+    s.push_str(" interface {\n");
+    s.push_str("\tjson.Marshaler\n");
+    s.push_str("\t");
+    s.push_str(&marker);
+    s.push_str("()\n");
+    s.push_str("}\n");
+    s.push_str("\n");
+    s.push_str(&fingerprint_const(&name, &kenum.fingerprint()));
+
+    // Encoded as `[name, field0, field1, ...]`.
+    for kvariant in &kenum.variants {
+        let vname = variant_type_name(pkg.cx.opts, kenum, kvariant);
+        s.push_str("\n");
+        s.push_str(&deprecation_comment(&kvariant.deprecation, ""));
+        s.push_str("type ");
+        s.push_str(&vname);
+        if kvariant.fields.len() == 0 {
+            s.push_str(" struct{}\n");
+        }
+        else {
+            s.push_str(" struct {\n");
+            s.push_str(&gen_fields(pkg, &kvariant.fields, false));
+            s.push_str("}\n");
+        }
+        s.push_str("\n");
+        s.push_str("func (");
+        s.push_str(&vname);
+        s.push_str(") ");
+        s.push_str(&marker);
+        s.push_str("() {}\n");
+        s.push_str("\n");
+        s.push_str("func (v ");
+        s.push_str(&vname);
+        s.push_str(") MarshalJSON() ([]byte, error) {\n");
+        s.push_str("\treturn json.Marshal([]interface{}{");
+        let mut values = vec![string_literal(wire_name(&kvariant.name))];
+        for (i, kfield) in kvariant.fields.iter().enumerate() {
+            let value = ["v.", &field_name(pkg.cx.opts, kfield, i)].join("");
+            values.push(encode_value(pkg, &kfield.ty, &value));
+        }
+        s.push_str(&values.join(", "));
+        s.push_str("})\n");
+        s.push_str("}\n");
+    }
+
+    // Trailing elements are ignored. Missing trailing elements are
+    // allowed only for fields with defaults.
+    s.push_str("\n");
+    s.push_str("func Unmarshal");
+    s.push_str(wire_name(&kenum.name));
+    s.push_str("(b []byte) (");
+    s.push_str(&name);
+    s.push_str(", error) {\n");
+    s.push_str("\tvar a []json.RawMessage\n");
+    s.push_str("\tif err := json.Unmarshal(b, &a); err != nil {\n");
+    s.push_str("\t\treturn nil, err\n");
+    s.push_str("\t}\n");
+    s.push_str("\tif len(a) == 0 {\n");
+    s.push_str("\t\treturn nil, igenMissing(\"variant name\", ");
+    s.push_str(&string_literal(wire_name(&kenum.name)));
+    s.push_str(")\n");
+    s.push_str("\t}\n");
+    s.push_str("\tvar n string\n");
+    s.push_str("\tif err := json.Unmarshal(a[0], &n); err != nil {\n");
+    s.push_str("\t\treturn nil, err\n");
+    s.push_str("\t}\n");
+    s.push_str("\tswitch n {\n");
+    for kvariant in &kenum.variants {
+        let vname = variant_type_name(pkg.cx.opts, kenum, kvariant);
+        s.push_str("\tcase ");
+        s.push_str(&string_literal(wire_name(&kvariant.name)));
+        s.push_str(":\n");
+        s.push_str("\t\tv := ");
+        s.push_str(&vname);
+        s.push_str("{}\n");
+        if kvariant.fields.len() > 0 {
+            s.push_str("\t\tvar err error\n");
+        }
+        for (i, kfield) in kvariant.fields.iter().enumerate() {
+            let index = (i + 1).to_string();
+            s.push_str("\t\tif len(a) > ");
+            s.push_str(&index);
+            s.push_str(" {\n");
+            s.push_str("\t\t\tif v.");
+            s.push_str(&field_name(pkg.cx.opts, kfield, i));
+            s.push_str(", err = ");
+            s.push_str(&decoder(pkg, &kfield.ty));
+            s.push_str("(a[");
+            s.push_str(&index);
+            s.push_str("]); err != nil {\n");
+            s.push_str("\t\t\t\treturn nil, err\n");
+            s.push_str("\t\t\t}\n");
+            s.push_str("\t\t}");
+            if !kfield.default {
+                s.push_str(" else {\n");
+                s.push_str("\t\t\treturn nil, igenMissing(");
+                s.push_str(&string_literal(&["field ", &i.to_string()].join("")));
+                s.push_str(", ");
+                s.push_str(&string_literal(wire_name(&kvariant.name)));
+                s.push_str(")\n");
+                s.push_str("\t\t}");
+            }
+            s.push_str("\n");
+        }
+        s.push_str("\t\treturn v, nil\n");
+    }
+    s.push_str("\t}\n");
+    s.push_str("\treturn nil, fmt.Errorf(\"igen: unknown variant %q of %s\", n, ");
+    s.push_str(&string_literal(wire_name(&kenum.name)));
+    s.push_str(")\n");
+    s.push_str("}\n");
+    return s;
+}
+
+/// Columns are aligned like `gofmt`.
+fn gen_fields(pkg: &mut Package, kfields: &[StructField], tagged: bool) -> String {
+    let mut rows = Vec::<(String, String, String)>::new();
+    for (i, kfield) in kfields.iter().enumerate() {
+        let name = field_name(pkg.cx.opts, kfield, i);
+        let ty = go_type(pkg, &kfield.ty);
+        let mut tag = String::new();
+        if tagged {
+            tag.push_str("`json:\"");
+            tag.push_str(wire_name(&kfield.name));
+            if let Type::Option(_) = kfield.ty {
+                tag.push_str(",omitempty");
+            }
+            tag.push_str("\"`");
+        }
+        rows.push((name, ty, tag));
+    }
+    let name_width = rows.iter().map(|r| r.0.len()).max().unwrap_or(0);
+    let ty_width = rows.iter().map(|r| r.1.len()).max().unwrap_or(0);
+    let mut s = String::new();
+    for (kfield, row) in kfields.iter().zip(rows.iter()) {
+        s.push_str(&deprecation_comment(&kfield.deprecation, "\t"));
+        s.push_str("\t");
+        s.push_str(&pad(&row.0, name_width));
+        s.push_str(" ");
+        if row.2.len() > 0 {
+            s.push_str(&pad(&row.1, ty_width));
+            s.push_str(" ");
+            s.push_str(&row.2);
+        }
+        else {
+            s.push_str(&row.1);
+        }
+        s.push_str("\n");
+    }
+    return s;
+}
+
+fn go_type(pkg: &mut Package, ty: &Type) -> String {
+    return match ty {
+        Type::Core(ref core_type) => {
+            use self::CoreType::*;
+            match core_type {
+                Bool => "bool".to_string(),
+                U8 => "uint8".to_string(),
+                U16 => "uint16".to_string(),
+                U32 => "uint32".to_string(),
+                U64 => "uint64".to_string(),
+                I8 => "int8".to_string(),
+                I16 => "int16".to_string(),
+                I32 => "int32".to_string(),
+                I64 => "int64".to_string(),
+                F32 => "float32".to_string(),
+                F64 => "float64".to_string(),
+            }
+        },
+        Type::String => "string".to_string(),
+        Type::Item(ref path) => {
+            let (qualifier, _) = resolve(pkg, path);
+            [&qualifier[..], &go_ident(path_name(path))].join("")
+        },
+        Type::Option(ref ty1) => ["*", &go_type(pkg, ty1)].join(""),
+        Type::Vec(ref ty1) => ["[]", &go_type(pkg, ty1)].join(""),
+    };
+}
+
+/// Function expression which decodes a JSON value into `ty`.
+fn decoder(pkg: &mut Package, ty: &Type) -> String {
+    return match ty {
+        Type::Item(ref path) => {
+            let (qualifier, is_enum) = resolve(pkg, path);
+            if is_enum {
+                [&qualifier, "Unmarshal", wire_name(path_name(path))].join("")
+            }
+            else {
+                ["igenDecode[", &qualifier, &go_ident(path_name(path)), "]"].join("")
+            }
+        },
+        Type::Option(ref ty1) => ["igenDecodePtr(", &decoder(pkg, ty1), ")"].join(""),
+        Type::Vec(ref ty1) => ["igenDecodeSlice(", &decoder(pkg, ty1), ")"].join(""),
+        _ => ["igenDecode[", &go_type(pkg, ty), "]"].join(""),
+    };
+}
+
+/// Nested nil slices are still encoded as `null`. `encoding/json`
+/// encodes `[]uint8` as a base64 string, so byte slices are
+/// converted to arrays of numbers, also inside of other slices and
+/// pointers.
+fn encode_value(pkg: &mut Package, ty: &Type, value: &str) -> String {
+    return match ty {
+        Type::Vec(ref ty1) => match **ty1 {
+            Type::Core(CoreType::U8) => ["igenBytes(", value, ")"].join(""),
+            _ if has_bytes(ty1) => ["igenMapSlice(", value, ", ", &encoder(pkg, ty1), ")"].join(""),
+            _ => ["igenSlice(", value, ")"].join(""),
+        },
+        Type::Option(ref ty1) if has_bytes(ty1) => ["igenMapPtr(", value, ", ", &encoder(pkg, ty1), ")"].join(""),
+        _ => value.to_string(),
+    };
+}
+
+/// Function literal which converts an element for `encode_value`.
+fn encoder(pkg: &mut Package, ty: &Type) -> String {
+    let mut s = String::new();
+    s.push_str("func(e ");
+    s.push_str(&go_type(pkg, ty));
+    s.push_str(") interface{} { return ");
+    s.push_str(&encode_value(pkg, ty, "e"));
+    s.push_str(" }");
+    return s;
+}
+
+/// Whether `ty` contains `Vec<u8>` outside of items.
+fn has_bytes(ty: &Type) -> bool {
+    return match ty {
+        Type::Vec(ref ty1) => match **ty1 {
+            Type::Core(CoreType::U8) => true,
+            _ => has_bytes(ty1),
+        },
+        Type::Option(ref ty1) => has_bytes(ty1),
+        _ => false,
+    };
+}
+
+/// Package qualifier (`pkg.` or empty) and whether the item is an
+/// enum. Items out of the root are referred by short name like
+/// Swift.
+fn resolve(pkg: &mut Package, path: &TypePath) -> (String, bool) {
+    let segments = &path.0;
+    if segments.len() == 0 {
+        return (String::new(), false);
+    }
+    let name = &segments[segments.len() - 1];
+    for i in 0..segments.len() - 1 {
+        if segments[i] != pkg.cx.kroot.name { continue }
+        let mods = segments[i + 1..segments.len() - 1].to_vec();
+        let mod_refs = mods.iter().map(|s| &s[..]).collect::<Vec<&str>>();
+        let kmod = match pkg.cx.kroot.find_module(&mod_refs) {
+            Some(kmod) => kmod,
+            None => continue,
+        };
+        let kind = kmod.items.iter().filter_map(|kitem| match kitem {
+            Item::Enum(ref kenum) if &kenum.name == name => Some(true),
+            Item::Struct(ref kstruct) if &kstruct.name == name => Some(false),
+            _ => None,
+        }).next();
+        let is_enum = match kind {
+            Some(is_enum) => is_enum,
+            None => continue,
+        };
+        if mods == pkg.path {
+            return (String::new(), is_enum);
+        }
+        let mut import = match pkg.cx.opts.package {
+            Some(ref package) => package.clone(),
+            None => package_name(&pkg.cx.kroot.name),
+        };
+        for m in &mods {
+            import.push_str("/");
+            import.push_str(&package_name(m));
+        }
+        pkg.imports.insert(import);
+        let qualifier = match mods.last() {
+            Some(m) => package_name(m),
+            None => package_name(&pkg.cx.kroot.name),
+        };
+        return ([&qualifier, "."].join(""), is_enum);
+    }
+    return (String::new(), false);
+}
+
+fn path_name(path: &TypePath) -> &str {
+    return path.0.last().map(|s| &s[..]).unwrap_or("????");
+}
+
+/// Package names also must not shadow imported packages.
+fn package_name(name: &str) -> String {
+    let name = wire_name(name).to_lowercase();
+    return match &name[..] {
+        "json" | "fmt" | "strings" => [&name[..], "_"].join(""),
+        _ => go_ident(&name),
+    };
+}
+
+fn variant_type_name(opts: &GenOptions, kenum: &Enum, kvariant: &EnumVariant) -> String {
+    let name = Lang::Go.variant_name(opts.naming, &exported(wire_name(&kvariant.name)));
+    return [&go_ident(&kenum.name), &name[..]].join("");
+}
+
+/// Fields must be exported for other packages.
+/// Tuple fields are named by their positions. (`F0`, `F1`, ...)
+fn field_name(opts: &GenOptions, kfield: &StructField, index: usize) -> String {
+    if kfield.name == "" {
+        return ["F", &index.to_string()].join("");
+    }
+    return Lang::Go.field_name(opts.naming, &exported(wire_name(&kfield.name)));
+}
+
+/// Capitalized names are exported, and never collide with Go
+/// keywords.
+fn exported(name: &str) -> String {
+    let mut cs = name.chars();
+    return match cs.next() {
+        Some(c) => c.to_uppercase().chain(cs).collect(),
+        None => String::new(),
+    };
+}
+
+fn fingerprint_const(name: &str, fingerprint: &str) -> String {
+    return ["const ", name, "IgenFingerprint = \"", fingerprint, "\"\n"].join("");
+}
+
+/// Go recognizes `Deprecated:` paragraphs in doc comments.
+fn deprecation_comment(deprecation: &Option<Deprecation>, indent: &str) -> String {
+    let deprecation = match deprecation {
+        Some(ref deprecation) => deprecation,
+        None => return String::new(),
+    };
+    let message = deprecation_message(deprecation).unwrap_or("this is deprecated.".to_string());
+    return [indent, "// Deprecated: ", &message.replace("\n", " "), "\n"].join("");
+}
+
+fn pad(s: &str, width: usize) -> String {
+    let mut r = s.to_string();
+    while r.len() < width {
+        r.push(' ');
+    }
+    return r;
+}
+
+fn go_ident(name: &str) -> String {
+    return Lang::Go.escape_ident(name);
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use schema::Module;
+    use schema::codegen::GenOptions;
+
+    fn module() -> Module {
+        return serde_json::from_str(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Module": {"name": "json", "visibility": "Public", "items": [
+                {"Struct": {"name": "Size", "visibility": "Public", "fields": [
+                    {"name": "w", "ty": {"Core": "I32"}, "default": true}]}}]}},
+            {"Struct": {"name": "Shape", "visibility": "Public", "fields": [
+                {"name": "type", "ty": "String"},
+                {"name": "size", "ty": {"Item": ["p", "json", "Size"]}, "default": true},
+                {"name": "data", "ty": {"Vec": {"Core": "U8"}}},
+                {"name": "note", "ty": {"Option": "String"}}]}},
+            {"Enum": {"name": "Command", "visibility": "Public", "variants": [
+                {"name": "Stop", "fields": []},
+                {"name": "Resize", "fields": [
+                    {"name": "", "ty": {"Item": ["p", "json", "Size"]}},
+                    {"name": "", "ty": {"Vec": "String"}, "default": true}]}]}}]}"#).unwrap();
+    }
+
+    fn options() -> GenOptions {
+        let mut opts = GenOptions::new();
+        opts.package = Some("example.com/gen/p".to_string());
+        return opts;
+    }
+
+    fn root_code(kmod: &Module) -> String {
+        return kmod.gen_go_files(&options()).remove(0).1;
+    }
+
+    #[test]
+    fn imports_packages_of_submodules_under_package() {
+        let kmod = module();
+        let files = kmod.gen_go_files(&options());
+        assert_eq!(files.iter().map(|file| file.0.to_string_lossy().to_string()).collect::<Vec<String>>(),
+                   vec!["p/p.go".to_string(), "p/json_/json_.go".to_string()]);
+        assert!(files[0].1.contains("\t\"strings\"\n\n\t\"example.com/gen/p/json_\"\n)\n"));
+        assert!(files[0].1.contains("\tSize json_.Size `json:\"size\"`\n"));
+        assert!(files[1].1.starts_with("// Code generated by mgen. DO NOT EDIT.\n\npackage json_\n"));
+        assert!(kmod.check_go_package(&options()).is_ok());
+        assert_eq!(kmod.check_go_package(&GenOptions::new()).unwrap_err(),
+                   "Go target needs `package` as `p` has submodules");
+        let mut kmod = kmod;
+        kmod.items.remove(0);
+        assert!(kmod.check_go_package(&GenOptions::new()).is_ok());
+    }
+
+    #[test]
+    fn decodes_objects_with_defaults_and_required_keys() {
+        let code = root_code(&module());
+        assert!(code.contains(concat!(
+            "\t*v = Shape{}\n",
+            "\tvar err error\n",
+            "\tif r, ok := o[\"type\"]; ok {\n",
+            "\t\tif v.Type, err = igenDecode[string](r); err != nil {\n",
+            "\t\t\treturn err\n",
+            "\t\t}\n",
+            "\t} else {\n",
+            "\t\treturn igenMissing(\"key `type`\", \"Shape\")\n",
+            "\t}\n",
+            "\tif r, ok := o[\"size\"]; ok {\n",
+            "\t\tif v.Size, err = igenDecode[json_.Size](r); err != nil {\n",
+            "\t\t\treturn err\n",
+            "\t\t}\n",
+            "\t}\n")));
+        assert!(code.contains(concat!(
+            "\tif r, ok := o[\"note\"]; ok {\n",
+            "\t\tif v.Note, err = igenDecodePtr(igenDecode[string])(r); err != nil {\n")));
+        assert!(code.contains(concat!(
+            "\tif v.Note != nil {\n",
+            "\t\to[\"note\"] = v.Note\n",
+            "\t}\n")));
+    }
+
+    #[test]
+    fn decodes_variants_with_missing_trailing_defaults() {
+        let code = root_code(&module());
+        assert!(code.contains("\treturn json.Marshal([]interface{}{\"Resize\", v.F0, igenSlice(v.F1)})\n"));
+        assert!(code.contains(concat!(
+            "\tcase \"Resize\":\n",
+            "\t\tv := CommandResize{}\n",
+            "\t\tvar err error\n",
+            "\t\tif len(a) > 1 {\n",
+            "\t\t\tif v.F0, err = igenDecode[json_.Size](a[1]); err != nil {\n",
+            "\t\t\t\treturn nil, err\n",
+            "\t\t\t}\n",
+            "\t\t} else {\n",
+            "\t\t\treturn nil, igenMissing(\"field 0\", \"Resize\")\n",
+            "\t\t}\n",
+            "\t\tif len(a) > 2 {\n",
+            "\t\t\tif v.F1, err = igenDecodeSlice(igenDecode[string])(a[2]); err != nil {\n",
+            "\t\t\t\treturn nil, err\n",
+            "\t\t\t}\n",
+            "\t\t}\n",
+            "\t\treturn v, nil\n",
+            "\t}\n",
+            "\treturn nil, fmt.Errorf(\"igen: unknown variant %q of %s\", n, \"Command\")\n")));
+    }
+
+    #[test]
+    fn encodes_byte_slices_as_arrays() {
+        let kmod: Module = serde_json::from_str(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Struct": {"name": "Blob", "visibility": "Public", "fields": [
+                {"name": "data", "ty": {"Vec": {"Core": "U8"}}},
+                {"name": "chunks", "ty": {"Vec": {"Option": {"Vec": {"Core": "U8"}}}}}]}}]}"#).unwrap();
+        let code = root_code(&kmod);
+        assert!(code.contains("\to[\"data\"] = igenBytes(v.Data)\n"));
+        // Arrays of numbers, not base64 strings.
+        assert!(code.contains("\t\tif v.Data, err = igenDecodeSlice(igenDecode[uint8])(r); err != nil {\n"));
+        assert!(code.contains("\to[\"chunks\"] = igenMapSlice(v.Chunks, func(e *[]uint8) interface{} { return igenMapPtr(e, func(e []uint8) interface{} { return igenBytes(e) }) })\n"));
+    }
+}
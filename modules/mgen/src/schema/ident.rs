@@ -0,0 +1,277 @@
+
+/// Identifier handling for target languages.
+///
+/// Rust names can collide with reserved words of other
+/// languages. Such names are escaped in generated source code,
+/// but the name on the wire is always the Rust name.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    Swift,
+    TypeScript,
+    Kotlin,
+    Python,
+    CSharp,
+    Go,
+    Java,
+    Dart,
+    C,
+    Cpp,
+}
+
+impl Lang {
+    pub fn keywords(&self) -> &'static [&'static str] {
+        return match self {
+            Lang::Swift => SWIFT_KEYWORDS,
+            Lang::TypeScript => TYPESCRIPT_KEYWORDS,
+            Lang::Kotlin => KOTLIN_KEYWORDS,
+            Lang::Python => PYTHON_KEYWORDS,
+            Lang::CSharp => CSHARP_KEYWORDS,
+            Lang::Go => GO_KEYWORDS,
+            Lang::Java => JAVA_KEYWORDS,
+            Lang::Dart => DART_KEYWORDS,
+            Lang::C => C_KEYWORDS,
+            Lang::Cpp => CPP_KEYWORDS,
+        };
+    }
+    pub fn is_keyword(&self, name: &str) -> bool {
+        return self.keywords().contains(&name);
+    }
+    /// Produces an identifier which can be used in source code
+    /// of this language.
+    ///
+    /// - Swift: backticks. (`` `default` ``) `_` cannot be
+    ///   escaped, so it becomes `__`.
+    /// - TypeScript: quoted keys. (`"default"`)
+    /// - Kotlin: backticks. (`` `fun` ``)
+    /// - Python: trailing underscore. (`class_`)
+    /// - C#: at sign. (`@class`)
+    /// - Go: trailing underscore. (`type_`)
+    /// - Java: trailing underscore. (`class_`)
+    /// - Dart: trailing underscore. (`class_`)
+    /// - C: trailing underscore. (`int_`)
+    /// - C++: trailing underscore. (`class_`)
+    pub fn escape_ident(&self, name: &str) -> String {
+        let name = wire_name(name);
+        if *self == Lang::Swift && name == "_" {
+            return "__".to_string();
+        }
+        if !self.is_keyword(name) {
+            return name.to_string();
+        }
+        return match self {
+            Lang::Swift => ["`", name, "`"].join(""),
+            Lang::TypeScript => ["\"", name, "\""].join(""),
+            Lang::Kotlin => ["`", name, "`"].join(""),
+            Lang::Python => [name, "_"].join(""),
+            Lang::CSharp => ["@", name].join(""),
+            Lang::Go => [name, "_"].join(""),
+            Lang::Java => [name, "_"].join(""),
+            Lang::Dart => [name, "_"].join(""),
+            Lang::C => [name, "_"].join(""),
+            Lang::Cpp => [name, "_"].join(""),
+        };
+    }
+}
+
+/// Name used in encoded data.
+/// This is Rust name without raw identifier prefix. (`r#type` -> `type`)
+pub fn wire_name(name: &str) -> &str {
+    if name.starts_with("r#") {
+        return &name[2..];
+    }
+    return name;
+}
+
+/// Double quoted string literal.
+/// Escapes are common to all C-like target languages.
+pub fn string_literal(s: &str) -> String {
+    let mut r = String::new();
+    r.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => r.push_str("\\\""),
+            '\\' => r.push_str("\\\\"),
+            '\n' => r.push_str("\\n"),
+            '\r' => r.push_str("\\r"),
+            '\t' => r.push_str("\\t"),
+            _ => r.push(c),
+        }
+    }
+    r.push('"');
+    return r;
+}
+
+/// Indents each non-empty line by a level.
+pub fn indent(s: &str) -> String {
+    let mut r = String::new();
+    for line in s.lines() {
+        if line.len() > 0 {
+            r.push_str("    ");
+        }
+        r.push_str(line);
+        r.push_str("\n");
+    }
+    return r;
+}
+
+const SWIFT_KEYWORDS: &'static [&'static str] = &[
+    "associatedtype", "class", "deinit", "enum", "extension", "fileprivate",
+    "func", "import", "init", "inout", "internal", "let", "open", "operator",
+    "precedencegroup", "private", "protocol", "public", "rethrows", "static",
+    "struct", "subscript", "typealias", "var",
+    "break", "case", "continue", "default", "defer", "do", "else", "fallthrough",
+    "for", "guard", "if", "in", "repeat", "return", "switch", "where", "while",
+    "Any", "as", "catch", "false", "is", "nil", "self", "Self", "super",
+    "throw", "throws", "true", "try",
+];
+
+const TYPESCRIPT_KEYWORDS: &'static [&'static str] = &[
+    "await", "break", "case", "catch", "class", "const", "continue", "debugger",
+    "default", "delete", "do", "else", "enum", "export", "extends", "false",
+    "finally", "for", "function", "if", "import", "in", "instanceof", "new",
+    "null", "return", "super", "switch", "this", "throw", "true", "try",
+    "typeof", "var", "void", "while", "with",
+    "implements", "interface", "let", "package", "private", "protected",
+    "public", "static", "yield",
+];
+
+const KOTLIN_KEYWORDS: &'static [&'static str] = &[
+    "as", "break", "class", "continue", "do", "else", "false", "for", "fun",
+    "if", "in", "interface", "is", "null", "object", "package", "return",
+    "super", "this", "throw", "true", "try", "typealias", "typeof", "val",
+    "var", "when", "while",
+];
+
+const PYTHON_KEYWORDS: &'static [&'static str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break",
+    "class", "continue", "def", "del", "elif", "else", "except", "finally",
+    "for", "from", "global", "if", "import", "in", "is", "lambda", "nonlocal",
+    "not", "or", "pass", "raise", "return", "try", "while", "with", "yield",
+];
+
+const CSHARP_KEYWORDS: &'static [&'static str] = &[
+    "abstract", "as", "base", "bool", "break", "byte", "case", "catch", "char",
+    "checked", "class", "const", "continue", "decimal", "default", "delegate",
+    "do", "double", "else", "enum", "event", "explicit", "extern", "false",
+    "finally", "fixed", "float", "for", "foreach", "goto", "if", "implicit",
+    "in", "int", "interface", "internal", "is", "lock", "long", "namespace",
+    "new", "null", "object", "operator", "out", "override", "params", "private",
+    "protected", "public", "readonly", "ref", "return", "sbyte", "sealed",
+    "short", "sizeof", "stackalloc", "static", "string", "struct", "switch",
+    "this", "throw", "true", "try", "typeof", "uint", "ulong", "unchecked",
+    "unsafe", "ushort", "using", "virtual", "void", "volatile", "while",
+];
+
+const GO_KEYWORDS: &'static [&'static str] = &[
+    "break", "case", "chan", "const", "continue", "default", "defer", "else",
+    "fallthrough", "for", "func", "go", "goto", "if", "import", "interface",
+    "map", "package", "range", "return", "select", "struct", "switch", "type",
+    "var",
+];
+
+const JAVA_KEYWORDS: &'static [&'static str] = &[
+    "abstract", "assert", "boolean", "break", "byte", "case", "catch", "char",
+    "class", "const", "continue", "default", "do", "double", "else", "enum",
+    "extends", "false", "final", "finally", "float", "for", "goto", "if",
+    "implements", "import", "instanceof", "int", "interface", "long", "native",
+    "new", "null", "package", "private", "protected", "public", "return",
+    "short", "static", "strictfp", "super", "switch", "synchronized", "this",
+    "throw", "throws", "transient", "true", "try", "void", "volatile", "while",
+    "_",
+];
+
+/// Reserved words only. Built-in identifiers like `get` are
+/// valid member names.
+const DART_KEYWORDS: &'static [&'static str] = &[
+    "assert", "await", "break", "case", "catch", "class", "const", "continue",
+    "default", "do", "else", "enum", "extends", "false", "final", "finally",
+    "for", "if", "in", "is", "new", "null", "rethrow", "return", "super",
+    "switch", "this", "throw", "true", "try", "var", "void", "while", "with",
+    "yield",
+];
+
+/// Includes C++ keywords and standard macros, because headers
+/// can be included from C++.
+const C_KEYWORDS: &'static [&'static str] = &[
+    "auto", "break", "case", "char", "const", "continue", "default", "do",
+    "double", "else", "enum", "extern", "float", "for", "goto", "if", "inline",
+    "int", "long", "register", "restrict", "return", "short", "signed",
+    "sizeof", "static", "struct", "switch", "typedef", "union", "unsigned",
+    "void", "volatile", "while", "bool", "true", "false", "NULL",
+    "catch", "class", "delete", "explicit", "friend", "mutable", "namespace",
+    "new", "operator", "private", "protected", "public", "template", "this",
+    "throw", "try", "typename", "using", "virtual",
+];
+
+const CPP_KEYWORDS: &'static [&'static str] = &[
+    "alignas", "alignof", "and", "and_eq", "asm", "auto", "bitand", "bitor",
+    "bool", "break", "case", "catch", "char", "char16_t", "char32_t", "class",
+    "compl", "const", "const_cast", "constexpr", "continue", "decltype",
+    "default", "delete", "do", "double", "dynamic_cast", "else", "enum",
+    "explicit", "export", "extern", "false", "float", "for", "friend", "goto",
+    "if", "inline", "int", "long", "mutable", "namespace", "new", "noexcept",
+    "not", "not_eq", "nullptr", "operator", "or", "or_eq", "private",
+    "protected", "public", "register", "reinterpret_cast", "return", "short",
+    "signed", "sizeof", "static", "static_assert", "static_cast", "struct",
+    "switch", "template", "this", "thread_local", "throw", "true", "try",
+    "typedef", "typeid", "typename", "union", "unsigned", "using", "virtual",
+    "void", "volatile", "wchar_t", "while", "xor", "xor_eq",
+    // Namespace `std` in generated namespaces hides the standard
+    // library.
+    "std",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::Lang;
+    use super::wire_name;
+    use super::string_literal;
+    use super::indent;
+
+    #[test]
+    fn escapes_keywords_per_language() {
+        assert_eq!(Lang::Swift.escape_ident("default"), "`default`");
+        assert_eq!(Lang::TypeScript.escape_ident("default"), "\"default\"");
+        assert_eq!(Lang::Kotlin.escape_ident("fun"), "`fun`");
+        assert_eq!(Lang::Python.escape_ident("class"), "class_");
+        assert_eq!(Lang::CSharp.escape_ident("class"), "@class");
+        assert_eq!(Lang::Go.escape_ident("type"), "type_");
+        assert_eq!(Lang::Java.escape_ident("class"), "class_");
+        assert_eq!(Lang::Dart.escape_ident("class"), "class_");
+        assert_eq!(Lang::C.escape_ident("int"), "int_");
+        assert_eq!(Lang::Cpp.escape_ident("class"), "class_");
+    }
+
+    #[test]
+    fn keeps_other_names() {
+        assert_eq!(Lang::Swift.escape_ident("point"), "point");
+        assert_eq!(Lang::Python.escape_ident("type"), "type");
+        assert_eq!(Lang::Go.escape_ident("class"), "class");
+    }
+
+    #[test]
+    fn escapes_raw_identifiers_by_wire_name() {
+        assert_eq!(wire_name("r#type"), "type");
+        assert_eq!(wire_name("type"), "type");
+        assert_eq!(Lang::Go.escape_ident("r#type"), "type_");
+        assert_eq!(Lang::Swift.escape_ident("r#type"), "type");
+    }
+
+    #[test]
+    fn renames_swift_underscore() {
+        assert_eq!(Lang::Swift.escape_ident("_"), "__");
+        assert_eq!(Lang::Python.escape_ident("_"), "_");
+    }
+
+    #[test]
+    fn escapes_string_literals() {
+        assert_eq!(string_literal("a \"b\" \\ c"), "\"a \\\"b\\\" \\\\ c\"");
+        assert_eq!(string_literal("1\n2\r3\t4"), "\"1\\n2\\r3\\t4\"");
+    }
+
+    #[test]
+    fn indents_non_empty_lines() {
+        assert_eq!(indent("a\n\n  b\n"), "    a\n\n      b\n");
+    }
+}
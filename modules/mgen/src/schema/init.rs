@@ -1,27 +1,43 @@
 
 use super::Module;
+use super::Item;
 use super::Enum;
 use super::EnumVariant;
 use super::Struct;
+use super::Visibility;
 
 impl Module {
     pub fn new() -> Module {
-        return Module { name: String::new(), items: Vec::new() };
+        return Module { name: String::new(), visibility: Visibility::Public, items: Vec::new() };
+    }
+}
+impl Item {
+    pub fn visibility(&self) -> Visibility {
+        return match self {
+            Item::Module(ref kmod) => kmod.visibility,
+            Item::Enum(ref kenum) => kenum.visibility,
+            Item::Struct(ref kstruct) => kstruct.visibility,
+        };
     }
 }
 impl Enum {
     pub fn new() -> Enum {
-        return Enum { name: String::new(), variants: Vec::new() };
+        return Enum { name: String::new(), visibility: Visibility::Public, deprecation: None, reserved: Vec::new(), variants: Vec::new() };
     }
 }
 impl EnumVariant {
     pub fn new() -> EnumVariant {
-        return EnumVariant { name: String::new(), fields: Vec::new() };
+        return EnumVariant { name: String::new(), deprecation: None, tag: None, fields: Vec::new() };
     }
 }
 impl Struct {
     pub fn new() -> Struct {
-        return Struct { name: String::new(), fields: Vec::new() } 
+        return Struct { name: String::new(), visibility: Visibility::Public, deprecation: None, reserved: Vec::new(), fields: Vec::new() } 
+    }
+    /// `true` if all fields have defaults, so the struct itself
+    /// can be a default value.
+    pub fn has_default(&self) -> bool {
+        return self.fields.iter().all(|kfield| kfield.default);
     }
     //pub fn a(&self) {
     //    self.gen_swift_code();
@@ -0,0 +1,968 @@
+use std::path::PathBuf;
+use super::Module;
+use super::Struct;
+use super::Enum;
+use super::EnumVariant;
+use super::StructField;
+use super::Item;
+use super::Type;
+use super::CoreType;
+use super::TypePath;
+use super::Visibility;
+use super::Deprecation;
+use super::ident::Lang;
+use super::ident::wire_name;
+use super::ident::string_literal;
+use super::ident::indent;
+use super::naming::Naming;
+use super::naming::upper_camel_case;
+use super::codegen::GenOptions;
+
+/// Java backend.
+///
+/// The root module becomes a final class, and other modules
+/// become nested static classes. Structs become records, and
+/// enums become sealed interfaces with a nested record for each
+/// variant. Each type has `igenWrite`/`igenRead` which implement
+/// the wire format of Swift code, and Jackson serializers which
+/// call them, so no `ObjectMapper` configuration is needed.
+///
+/// `GenOptions::package` is the Java package of generated code.
+/// Generated code needs Java 17 or later and Jackson 2.10 or
+/// later.
+impl Module {
+    /// Source file path relative to the output directory.
+    /// (`com/example/Proto.java`)
+    pub fn gen_java_path(&self, opts: &GenOptions) -> PathBuf {
+        let mut path = PathBuf::new();
+        if let Some(ref package) = opts.package {
+            for segment in package.split('.') {
+                path.push(segment);
+            }
+        }
+        path.push([&module_class_name(opts, self, None, &[]), ".java"].join(""));
+        return path;
+    }
+    pub fn gen_java_code(&self, opts: &GenOptions) -> String {
+        let cx = Context { kroot: self, opts: opts };
+        let mut s = String::new();
+        if let Some(ref package) = opts.package {
+            s.push_str("package ");
+            s.push_str(package);
+            s.push_str(";\n");
+            s.push_str("\n");
+        }
+        s.push_str("import com.fasterxml.jackson.core.JsonGenerator;\n");
+        s.push_str("import com.fasterxml.jackson.core.JsonParser;\n");
+        s.push_str("import com.fasterxml.jackson.core.JsonProcessingException;\n");
+        s.push_str("import com.fasterxml.jackson.databind.DeserializationContext;\n");
+        s.push_str("import com.fasterxml.jackson.databind.JsonNode;\n");
+        s.push_str("import com.fasterxml.jackson.databind.SerializerProvider;\n");
+        s.push_str("import com.fasterxml.jackson.databind.annotation.JsonDeserialize;\n");
+        s.push_str("import com.fasterxml.jackson.databind.annotation.JsonSerialize;\n");
+        s.push_str("import com.fasterxml.jackson.databind.deser.std.StdDeserializer;\n");
+        s.push_str("import com.fasterxml.jackson.databind.ser.std.StdSerializer;\n");
+        s.push_str("import java.io.IOException;\n");
+        s.push_str("import java.math.BigInteger;\n");
+        s.push_str("import java.util.ArrayList;\n");
+        s.push_str("import java.util.Collections;\n");
+        s.push_str("import java.util.List;\n");
+        s.push_str("import java.util.Optional;\n");
+        s.push_str("import java.util.function.Supplier;\n");
+        s.push_str("\n");
+        s.push_str(&gen_module(&cx, self, None, &mut Vec::new()));
+        return s;
+    }
+}
+
+struct Context<'a> {
+    kroot: &'a Module,
+    opts: &'a GenOptions,
+}
+
+/// `enclosing` is names of classes around this module class.
+fn gen_module(cx: &Context, kmod: &Module, kparent: Option<&Module>, enclosing: &mut Vec<String>) -> String {
+    let is_root = kparent.is_none();
+    let name = module_class_name(cx.opts, kmod, kparent, enclosing);
+    enclosing.push(name.clone());
+    let mut ss = Vec::<String>::new();
+    ss.push(["private ", &name, "() {}\n"].join(""));
+    for kitem in &kmod.items {
+        if kitem.visibility() != Visibility::Public { continue }
+        ss.push(match kitem {
+            Item::Module(ref kmod1) => gen_module(cx, kmod1, Some(kmod), enclosing),
+            Item::Enum(ref kenum) => gen_enum(cx, kenum, enclosing),
+            Item::Struct(ref kstruct) => gen_struct(cx, kstruct, enclosing),
+        });
+    }
+    enclosing.pop();
+    if is_root {
+        ss.push(gen_fingerprint(cx.kroot));
+        if cx.opts.handshake {
+            ss.push(gen_handshake(cx));
+        }
+        ss.push(gen_json_helpers());
+    }
+    let mut s = String::new();
+    s.push_str(if is_root { "public final class " } else { "public static final class " });
+    s.push_str(&name);
+    s.push_str(" {\n");
+    s.push_str(&indent(&ss.join("\n")));
+    s.push_str("}\n");
+    return s;
+}
+
+fn gen_fingerprint(kroot: &Module) -> String {
+    let mut s = String::new();
+    s.push_str("public static final class IgenSchema {\n");
+    s.push_str("    private IgenSchema() {}\n");
+    s.push_str("\n");
+    s.push_str("    public static final String FINGERPRINT = \"");
+    s.push_str(&kroot.fingerprint());
+    s.push_str("\";\n");
+    s.push_str("}\n");
+    return s;
+}
+
+/// Same messages with Swift `IgenHandshake`.
+/// The messages are generated from equivalent schema structs.
+fn gen_handshake(cx: &Context) -> String {
+    let mut hello = Struct::new();
+    hello.name = "Hello".to_string();
+    hello.fields.push(StructField { name: "fingerprints".to_string(), ty: Type::Vec(Box::new(Type::String)), default: false, deprecation: None, tag: None });
+    let mut hello_ack = Struct::new();
+    hello_ack.name = "HelloAck".to_string();
+    hello_ack.fields.push(StructField { name: "fingerprint".to_string(), ty: Type::Option(Box::new(Type::String)), default: false, deprecation: None, tag: None });
+    let fingerprints = field_name(cx.opts, &hello.fields[0], 0);
+
+    let mut s = String::new();
+    s.push_str("public static final class IgenHandshake {\n");
+    s.push_str("    private IgenHandshake() {}\n");
+    s.push_str("\n");
+    s.push_str("    public static final String FINGERPRINT = IgenSchema.FINGERPRINT;\n");
+    s.push_str("\n");
+    let enclosing = vec![module_class_name(cx.opts, cx.kroot, None, &[]), "IgenHandshake".to_string()];
+    s.push_str(&indent(&gen_struct(cx, &hello, &enclosing)));
+    s.push_str("\n");
+    s.push_str(&indent(&gen_struct(cx, &hello_ack, &enclosing)));
+    s.push_str("\n");
+    s.push_str("    public static Hello hello() {\n");
+    s.push_str("        return new Hello(List.of(FINGERPRINT));\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    /** Empty fingerprint refuses the hello. */\n");
+    s.push_str("    public static HelloAck accept(Hello hello) {\n");
+    s.push_str("        boolean ok = hello.");
+    s.push_str(&fingerprints);
+    s.push_str("().contains(FINGERPRINT);\n");
+    s.push_str("        return new HelloAck(ok ? Optional.of(FINGERPRINT) : Optional.empty());\n");
+    s.push_str("    }\n");
+    s.push_str("}\n");
+    return s;
+}
+
+fn gen_struct(cx: &Context, kstruct: &Struct, enclosing: &[String]) -> String {
+    let name = type_name(java_ident(&kstruct.name), enclosing);
+    let mut s = String::new();
+    s.push_str(&deprecated(&kstruct.deprecation));
+    s.push_str(&gen_jackson_annotations(&name));
+    s.push_str("public record ");
+    s.push_str(&name);
+    s.push_str("(");
+    let params = gen_parameters(cx, &kstruct.fields);
+    if params.len() > 0 {
+        s.push_str("\n        ");
+        s.push_str(&params.join(",\n        "));
+    }
+    s.push_str(") {\n");
+    s.push_str(&fingerprint_member(&kstruct.fingerprint()));
+    if kstruct.has_default() {
+        s.push_str("\n");
+        s.push_str("    public static ");
+        s.push_str(&name);
+        s.push_str(" igenDefault() {\n");
+        s.push_str("        return new ");
+        s.push_str(&name);
+        s.push_str("(");
+        let values = kstruct.fields.iter().map(|kfield| default_value(cx, &kfield.ty)).collect::<Vec<String>>();
+        s.push_str(&values.join(", "));
+        s.push_str(");\n");
+        s.push_str("    }\n");
+    }
+
+    // `null` of optional fields is omitted like Swift.
+    s.push_str("\n");
+    s.push_str("    public static void igenWrite(JsonGenerator g, ");
+    s.push_str(&name);
+    s.push_str(" v) throws IOException {\n");
+    s.push_str("        g.writeStartObject();\n");
+    for (i, kfield) in kstruct.fields.iter().enumerate() {
+        let value = ["v.", &field_name(cx.opts, kfield, i), "()"].join("");
+        let mut lines = String::new();
+        lines.push_str("g.writeFieldName(");
+        lines.push_str(&string_literal(wire_name(&kfield.name)));
+        lines.push_str(");\n");
+        lines.push_str(&write_call(cx, &kfield.ty, &value));
+        lines.push_str(";\n");
+        if let Type::Option(_) = kfield.ty {
+            s.push_str("        if (");
+            s.push_str(&value);
+            s.push_str(".isPresent()) {\n");
+            s.push_str(&indent(&indent(&indent(&lines))));
+            s.push_str("        }\n");
+        }
+        else {
+            s.push_str(&indent(&indent(&lines)));
+        }
+    }
+    s.push_str("        g.writeEndObject();\n");
+    s.push_str("    }\n");
+
+    // Unknown keys are ignored. Missing keys are allowed only for
+    // optional fields and fields with defaults.
+    s.push_str("\n");
+    s.push_str("    public static ");
+    s.push_str(&name);
+    s.push_str(" igenRead(JsonNode n) throws IOException {\n");
+    s.push_str("        IgenJson.expectObject(n, ");
+    s.push_str(&string_literal(wire_name(&kstruct.name)));
+    s.push_str(");\n");
+    s.push_str("        return new ");
+    s.push_str(&name);
+    s.push_str("(");
+    let mut args = Vec::<String>::new();
+    for kfield in &kstruct.fields {
+        let key = string_literal(wire_name(&kfield.name));
+        let reader = reader(cx, &kfield.ty);
+        let arg = match fallback(cx, kfield) {
+            Some(value) => ["IgenJson.field(n, ", &key, ", ", &reader, ", () -> ", &value, ")"].join(""),
+            None => ["IgenJson.field(n, ", &key, ", ", &string_literal(wire_name(&kstruct.name)), ", ", &reader, ")"].join(""),
+        };
+        args.push(arg);
+    }
+    if args.len() > 0 {
+        s.push_str("\n            ");
+        s.push_str(&args.join(",\n            "));
+    }
+    s.push_str(");\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str(&indent(&gen_jackson_classes(&name, false)));
+    s.push_str("}\n");
+    return s;
+}
+
+/// Encoded as `[name, field0, field1, ...]`.
+/// Trailing elements are ignored. Missing trailing elements are
+/// allowed only for fields with defaults.
+fn gen_enum(cx: &Context, kenum: &Enum, enclosing: &[String]) -> String {
+    let name = type_name(java_ident(&kenum.name), enclosing);
+    let mut enclosing = enclosing.to_vec();
+    enclosing.push(name.clone());
+    let record_names = kenum.variants.iter().map(|kvariant| variant_name(cx.opts, kvariant, &enclosing)).collect::<Vec<String>>();
+    let mut s = String::new();
+    s.push_str(&deprecated(&kenum.deprecation));
+    s.push_str(&gen_jackson_annotations(&name));
+    // Sealed interfaces need at least one subclass.
+    if kenum.variants.len() == 0 {
+        s.push_str("public interface ");
+        s.push_str(&name);
+    }
+    else {
+        s.push_str("public sealed interface ");
+        s.push_str(&name);
+        s.push_str(" permits ");
+        let permits = record_names.iter().map(|record_name| [&name[..], ".", record_name].join("")).collect::<Vec<String>>();
+        s.push_str(&permits.join(", "));
+    }
+    s.push_str(" {\n");
+    s.push_str("    String IGEN_FINGERPRINT = \"");
+    s.push_str(&kenum.fingerprint());
+    s.push_str("\";\n");
+    for (kvariant, record_name) in kenum.variants.iter().zip(&record_names) {
+        s.push_str("\n");
+        s.push_str(&indent(&deprecated(&kvariant.deprecation)));
+        s.push_str("    record ");
+        s.push_str(record_name);
+        s.push_str("(");
+        s.push_str(&gen_parameters(cx, &kvariant.fields).join(", "));
+        s.push_str(") implements ");
+        s.push_str(&name);
+        s.push_str(" {}\n");
+    }
+
+    s.push_str("\n");
+    s.push_str("    static void igenWrite(JsonGenerator g, ");
+    s.push_str(&name);
+    s.push_str(" v) throws IOException {\n");
+    s.push_str("        g.writeStartArray();\n");
+    for (i, (kvariant, record_name)) in kenum.variants.iter().zip(&record_names).enumerate() {
+        s.push_str(if i == 0 { "        if (v instanceof " } else { " else if (v instanceof " });
+        s.push_str(record_name);
+        if kvariant.fields.len() > 0 {
+            s.push_str(" x");
+        }
+        s.push_str(") {\n");
+        s.push_str("            g.writeString(");
+        s.push_str(&string_literal(wire_name(&kvariant.name)));
+        s.push_str(");\n");
+        for (j, kfield) in kvariant.fields.iter().enumerate() {
+            let value = ["x.", &field_name(cx.opts, kfield, j), "()"].join("");
+            s.push_str("            ");
+            s.push_str(&write_call(cx, &kfield.ty, &value));
+            s.push_str(";\n");
+        }
+        s.push_str("        }");
+    }
+    if kenum.variants.len() > 0 {
+        s.push_str("\n");
+    }
+    s.push_str("        g.writeEndArray();\n");
+    s.push_str("    }\n");
+
+    s.push_str("\n");
+    s.push_str("    static ");
+    s.push_str(&name);
+    s.push_str(" igenRead(JsonNode n) throws IOException {\n");
+    s.push_str("        IgenJson.expectArray(n, ");
+    s.push_str(&string_literal(wire_name(&kenum.name)));
+    s.push_str(");\n");
+    s.push_str("        String name = IgenJson.element(n, 0, ");
+    s.push_str(&string_literal(wire_name(&kenum.name)));
+    s.push_str(", IgenJson::readString);\n");
+    s.push_str("        switch (name) {\n");
+    for (kvariant, record_name) in kenum.variants.iter().zip(&record_names) {
+        s.push_str("            case ");
+        s.push_str(&string_literal(wire_name(&kvariant.name)));
+        s.push_str(":\n");
+        s.push_str("                return new ");
+        s.push_str(record_name);
+        s.push_str("(");
+        let mut args = Vec::<String>::new();
+        for (i, kfield) in kvariant.fields.iter().enumerate() {
+            let index = (i + 1).to_string();
+            let reader = reader(cx, &kfield.ty);
+            if kfield.default {
+                args.push(["IgenJson.element(n, ", &index, ", ", &reader, ", () -> ", &default_value(cx, &kfield.ty), ")"].join(""));
+            }
+            else {
+                args.push(["IgenJson.element(n, ", &index, ", ", &string_literal(wire_name(&kvariant.name)), ", ", &reader, ")"].join(""));
+            }
+        }
+        if args.len() > 0 {
+            s.push_str("\n                    ");
+            s.push_str(&args.join(",\n                    "));
+        }
+        s.push_str(");\n");
+    }
+    s.push_str("            default:\n");
+    s.push_str("                throw new IgenJson.DecodingException(\"unknown variant `\" + name + \"` of ");
+    s.push_str(wire_name(&kenum.name));
+    s.push_str("\");\n");
+    s.push_str("        }\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str(&indent(&gen_jackson_classes(&name, true)));
+    s.push_str("}\n");
+    return s;
+}
+
+fn gen_jackson_annotations(name: &str) -> String {
+    let mut s = String::new();
+    s.push_str("@JsonSerialize(using = ");
+    s.push_str(name);
+    s.push_str(".IgenSerializer.class)\n");
+    s.push_str("@JsonDeserialize(using = ");
+    s.push_str(name);
+    s.push_str(".IgenDeserializer.class)\n");
+    return s;
+}
+
+/// Members of interfaces are implicitly public and static.
+fn gen_jackson_classes(name: &str, in_interface: bool) -> String {
+    let modifiers = if in_interface { "final class " } else { "public static final class " };
+    let mut s = String::new();
+    s.push_str(modifiers);
+    s.push_str("IgenSerializer extends StdSerializer<");
+    s.push_str(name);
+    s.push_str("> {\n");
+    s.push_str("    public IgenSerializer() {\n");
+    s.push_str("        super(");
+    s.push_str(name);
+    s.push_str(".class);\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    @Override\n");
+    s.push_str("    public void serialize(");
+    s.push_str(name);
+    s.push_str(" v, JsonGenerator g, SerializerProvider p) throws IOException {\n");
+    s.push_str("        igenWrite(g, v);\n");
+    s.push_str("    }\n");
+    s.push_str("}\n");
+    s.push_str("\n");
+    s.push_str(modifiers);
+    s.push_str("IgenDeserializer extends StdDeserializer<");
+    s.push_str(name);
+    s.push_str("> {\n");
+    s.push_str("    public IgenDeserializer() {\n");
+    s.push_str("        super(");
+    s.push_str(name);
+    s.push_str(".class);\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    @Override\n");
+    s.push_str("    public ");
+    s.push_str(name);
+    s.push_str(" deserialize(JsonParser p, DeserializationContext c) throws IOException {\n");
+    s.push_str("        return igenRead(c.readTree(p));\n");
+    s.push_str("    }\n");
+    s.push_str("}\n");
+    return s;
+}
+
+/// Readers and writers of core types, shared by all generated
+/// types.
+fn gen_json_helpers() -> String {
+    let mut s = String::new();
+    s.push_str("public static final class IgenJson {\n");
+    s.push_str("    private IgenJson() {}\n");
+    s.push_str("\n");
+    s.push_str("    @FunctionalInterface\n");
+    s.push_str("    public interface Reader<T> {\n");
+    s.push_str("        T read(JsonNode n) throws IOException;\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    @FunctionalInterface\n");
+    s.push_str("    public interface Writer<T> {\n");
+    s.push_str("        void write(JsonGenerator g, T v) throws IOException;\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    public static final class DecodingException extends JsonProcessingException {\n");
+    s.push_str("        private static final long serialVersionUID = 1L;\n");
+    s.push_str("\n");
+    s.push_str("        public DecodingException(String message) {\n");
+    s.push_str("            super(\"igen: \" + message);\n");
+    s.push_str("        }\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    static DecodingException mismatch(String expected, JsonNode n) {\n");
+    s.push_str("        return new DecodingException(\"expected \" + expected + \" but was \" + n.getNodeType());\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    public static void expectObject(JsonNode n, String type) throws IOException {\n");
+    s.push_str("        if (!n.isObject()) throw mismatch(\"object for \" + type, n);\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    public static void expectArray(JsonNode n, String type) throws IOException {\n");
+    s.push_str("        if (!n.isArray()) throw mismatch(\"array for \" + type, n);\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    public static <T> T field(JsonNode n, String key, String type, Reader<T> r) throws IOException {\n");
+    s.push_str("        JsonNode e = n.get(key);\n");
+    s.push_str("        if (e == null) throw new DecodingException(\"missing key `\" + key + \"` of \" + type);\n");
+    s.push_str("        return r.read(e);\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    public static <T> T field(JsonNode n, String key, Reader<T> r, Supplier<T> d) throws IOException {\n");
+    s.push_str("        JsonNode e = n.get(key);\n");
+    s.push_str("        return e == null ? d.get() : r.read(e);\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    public static <T> T element(JsonNode n, int index, String type, Reader<T> r) throws IOException {\n");
+    s.push_str("        if (n.size() <= index) throw new DecodingException(\"missing field \" + (index - 1) + \" of \" + type);\n");
+    s.push_str("        return r.read(n.get(index));\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    public static <T> T element(JsonNode n, int index, Reader<T> r, Supplier<T> d) throws IOException {\n");
+    s.push_str("        return n.size() <= index ? d.get() : r.read(n.get(index));\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    public static boolean readBool(JsonNode n) throws IOException {\n");
+    s.push_str("        if (!n.isBoolean()) throw mismatch(\"bool\", n);\n");
+    s.push_str("        return n.booleanValue();\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    static long readInteger(JsonNode n, String type, long min, long max) throws IOException {\n");
+    s.push_str("        if (!n.isIntegralNumber() || !n.canConvertToLong()) throw mismatch(type, n);\n");
+    s.push_str("        long v = n.longValue();\n");
+    s.push_str("        if (v < min || v > max) throw mismatch(type, n);\n");
+    s.push_str("        return v;\n");
+    s.push_str("    }\n");
+    for &(suffix, ty, cast, min, max) in INTEGERS {
+        s.push_str("\n");
+        s.push_str("    public static ");
+        s.push_str(ty);
+        s.push_str(" read");
+        s.push_str(suffix);
+        s.push_str("(JsonNode n) throws IOException {\n");
+        s.push_str("        return ");
+        s.push_str(cast);
+        s.push_str("readInteger(n, \"");
+        s.push_str(&suffix.to_lowercase());
+        s.push_str("\", ");
+        s.push_str(min);
+        s.push_str(", ");
+        s.push_str(max);
+        s.push_str(");\n");
+        s.push_str("    }\n");
+    }
+    s.push_str("\n");
+    s.push_str("    /** Bits of `u64` are kept in `long`. */\n");
+    s.push_str("    public static long readU64(JsonNode n) throws IOException {\n");
+    s.push_str("        if (!n.isIntegralNumber()) throw mismatch(\"u64\", n);\n");
+    s.push_str("        BigInteger v = n.bigIntegerValue();\n");
+    s.push_str("        if (v.signum() < 0 || v.bitLength() > 64) throw mismatch(\"u64\", n);\n");
+    s.push_str("        return v.longValue();\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    public static float readF32(JsonNode n) throws IOException {\n");
+    s.push_str("        if (!n.isNumber()) throw mismatch(\"f32\", n);\n");
+    s.push_str("        return n.floatValue();\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    public static double readF64(JsonNode n) throws IOException {\n");
+    s.push_str("        if (!n.isNumber()) throw mismatch(\"f64\", n);\n");
+    s.push_str("        return n.doubleValue();\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    public static String readString(JsonNode n) throws IOException {\n");
+    s.push_str("        if (!n.isTextual()) throw mismatch(\"string\", n);\n");
+    s.push_str("        return n.textValue();\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    public static <T> Reader<Optional<T>> readOptional(Reader<T> r) {\n");
+    s.push_str("        return n -> n.isNull() ? Optional.empty() : Optional.of(r.read(n));\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    public static <T> Reader<List<T>> readList(Reader<T> r) {\n");
+    s.push_str("        return n -> {\n");
+    s.push_str("            if (!n.isArray()) throw mismatch(\"array\", n);\n");
+    s.push_str("            List<T> a = new ArrayList<>(n.size());\n");
+    s.push_str("            for (JsonNode e : n) {\n");
+    s.push_str("                a.add(r.read(e));\n");
+    s.push_str("            }\n");
+    s.push_str("            return Collections.unmodifiableList(a);\n");
+    s.push_str("        };\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    public static void writeBool(JsonGenerator g, boolean v) throws IOException {\n");
+    s.push_str("        g.writeBoolean(v);\n");
+    s.push_str("    }\n");
+    for &(suffix, ty, _, _, _) in INTEGERS {
+        s.push_str("\n");
+        s.push_str("    public static void write");
+        s.push_str(suffix);
+        s.push_str("(JsonGenerator g, ");
+        s.push_str(ty);
+        s.push_str(" v) throws IOException {\n");
+        s.push_str("        g.writeNumber(v);\n");
+        s.push_str("    }\n");
+    }
+    s.push_str("\n");
+    s.push_str("    public static void writeU64(JsonGenerator g, long v) throws IOException {\n");
+    s.push_str("        g.writeNumber(new BigInteger(Long.toUnsignedString(v)));\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    public static void writeF32(JsonGenerator g, float v) throws IOException {\n");
+    s.push_str("        g.writeNumber(v);\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    public static void writeF64(JsonGenerator g, double v) throws IOException {\n");
+    s.push_str("        g.writeNumber(v);\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    public static void writeString(JsonGenerator g, String v) throws IOException {\n");
+    s.push_str("        g.writeString(v);\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    public static <T> Writer<Optional<T>> writeOptional(Writer<T> w) {\n");
+    s.push_str("        return (g, v) -> {\n");
+    s.push_str("            if (v.isPresent()) {\n");
+    s.push_str("                w.write(g, v.get());\n");
+    s.push_str("            } else {\n");
+    s.push_str("                g.writeNull();\n");
+    s.push_str("            }\n");
+    s.push_str("        };\n");
+    s.push_str("    }\n");
+    s.push_str("\n");
+    s.push_str("    public static <T> Writer<List<T>> writeList(Writer<T> w) {\n");
+    s.push_str("        return (g, v) -> {\n");
+    s.push_str("            g.writeStartArray();\n");
+    s.push_str("            for (T e : v) {\n");
+    s.push_str("                w.write(g, e);\n");
+    s.push_str("            }\n");
+    s.push_str("            g.writeEndArray();\n");
+    s.push_str("        };\n");
+    s.push_str("    }\n");
+    s.push_str("}\n");
+    return s;
+}
+
+/// Integer types checked by range. (suffix, Java type, cast, min, max)
+/// Unsigned types use the next wider signed type except `u64`.
+const INTEGERS: &'static [(&'static str, &'static str, &'static str, &'static str, &'static str)] = &[
+    ("I8", "byte", "(byte) ", "Byte.MIN_VALUE", "Byte.MAX_VALUE"),
+    ("I16", "short", "(short) ", "Short.MIN_VALUE", "Short.MAX_VALUE"),
+    ("I32", "int", "(int) ", "Integer.MIN_VALUE", "Integer.MAX_VALUE"),
+    ("I64", "long", "", "Long.MIN_VALUE", "Long.MAX_VALUE"),
+    ("U8", "short", "(short) ", "0", "255"),
+    ("U16", "int", "(int) ", "0", "65535"),
+    ("U32", "long", "", "0", "4294967295L"),
+];
+
+/// Record components.
+fn gen_parameters(cx: &Context, kfields: &[StructField]) -> Vec<String> {
+    let mut ss = Vec::<String>::new();
+    for (i, kfield) in kfields.iter().enumerate() {
+        let mut s = String::new();
+        if let Some(ref deprecation) = kfield.deprecation {
+            s.push_str(&deprecated_annotation(deprecation));
+            s.push_str(" ");
+        }
+        s.push_str(&java_type(cx, &kfield.ty, false));
+        s.push_str(" ");
+        s.push_str(&field_name(cx.opts, kfield, i));
+        ss.push(s);
+    }
+    return ss;
+}
+
+/// Value for a missing key, or `None` if the key is required.
+fn fallback(cx: &Context, kfield: &StructField) -> Option<String> {
+    if kfield.default {
+        return Some(default_value(cx, &kfield.ty));
+    }
+    return match kfield.ty {
+        Type::Option(_) => Some("Optional.empty()".to_string()),
+        _ => None,
+    };
+}
+
+/// Boxed types are used in type arguments.
+fn java_type(cx: &Context, ty: &Type, boxed: bool) -> String {
+    return match ty {
+        Type::Core(ref core_type) => {
+            use self::CoreType::*;
+            let (primitive, boxed_name) = match core_type {
+                Bool => ("boolean", "Boolean"),
+                U8 => ("short", "Short"),
+                U16 => ("int", "Integer"),
+                U32 => ("long", "Long"),
+                U64 => ("long", "Long"),
+                I8 => ("byte", "Byte"),
+                I16 => ("short", "Short"),
+                I32 => ("int", "Integer"),
+                I64 => ("long", "Long"),
+                F32 => ("float", "Float"),
+                F64 => ("double", "Double"),
+            };
+            (if boxed { boxed_name } else { primitive }).to_string()
+        },
+        Type::String => "String".to_string(),
+        Type::Item(ref path) => type_path(cx, path),
+        Type::Option(ref ty1) => ["Optional<", &java_type(cx, ty1, true), ">"].join(""),
+        Type::Vec(ref ty1) => ["List<", &java_type(cx, ty1, true), ">"].join(""),
+    };
+}
+
+fn default_value(cx: &Context, ty: &Type) -> String {
+    return match ty {
+        Type::Core(ref core_type) => {
+            use self::CoreType::*;
+            match core_type {
+                Bool => "false",
+                I8 => "(byte) 0",
+                I16 | U8 => "(short) 0",
+                I32 | U16 => "0",
+                I64 | U32 | U64 => "0L",
+                F32 => "0.0f",
+                F64 => "0.0",
+            }.to_string()
+        },
+        Type::String => "\"\"".to_string(),
+        Type::Item(ref path) => [&type_path(cx, path), ".igenDefault()"].join(""),
+        Type::Option(_) => "Optional.empty()".to_string(),
+        Type::Vec(_) => "List.of()".to_string(),
+    };
+}
+
+/// `IgenJson.Reader` expression for a type.
+fn reader(cx: &Context, ty: &Type) -> String {
+    return match ty {
+        Type::Core(ref core_type) => ["IgenJson::read", core_suffix(core_type)].join(""),
+        Type::String => "IgenJson::readString".to_string(),
+        Type::Item(ref path) => [&type_path(cx, path), "::igenRead"].join(""),
+        Type::Option(ref ty1) => ["IgenJson.readOptional(", &reader(cx, ty1), ")"].join(""),
+        Type::Vec(ref ty1) => ["IgenJson.readList(", &reader(cx, ty1), ")"].join(""),
+    };
+}
+
+/// `IgenJson.Writer` expression for a type.
+fn writer(cx: &Context, ty: &Type) -> String {
+    return match ty {
+        Type::Core(ref core_type) => ["IgenJson::write", core_suffix(core_type)].join(""),
+        Type::String => "IgenJson::writeString".to_string(),
+        Type::Item(ref path) => [&type_path(cx, path), "::igenWrite"].join(""),
+        Type::Option(ref ty1) => ["IgenJson.writeOptional(", &writer(cx, ty1), ")"].join(""),
+        Type::Vec(ref ty1) => ["IgenJson.writeList(", &writer(cx, ty1), ")"].join(""),
+    };
+}
+
+/// Statement which writes `value` to `g` without trailing `;`.
+fn write_call(cx: &Context, ty: &Type, value: &str) -> String {
+    return match ty {
+        Type::Core(ref core_type) => ["IgenJson.write", core_suffix(core_type), "(g, ", value, ")"].join(""),
+        Type::String => ["IgenJson.writeString(g, ", value, ")"].join(""),
+        Type::Item(ref path) => [&type_path(cx, path), ".igenWrite(g, ", value, ")"].join(""),
+        Type::Option(_) | Type::Vec(_) => [&writer(cx, ty), ".write(g, ", value, ")"].join(""),
+    };
+}
+
+fn core_suffix(core_type: &CoreType) -> &'static str {
+    use self::CoreType::*;
+    return match core_type {
+        Bool => "Bool",
+        U8 => "U8",
+        U16 => "U16",
+        U32 => "U32",
+        U64 => "U64",
+        I8 => "I8",
+        I16 => "I16",
+        I32 => "I32",
+        I64 => "I64",
+        F32 => "F32",
+        F64 => "F64",
+    };
+}
+
+/// Path from the root class if the item is in the root module.
+/// Otherwise only the last segment is used like Swift.
+/// Qualified paths avoid shadowing by variant records.
+fn type_path(cx: &Context, path: &TypePath) -> String {
+    let segments = &path.0;
+    if segments.len() == 0 {
+        return "????".to_string();
+    }
+    let name = &segments[segments.len() - 1];
+    for i in 0..segments.len() - 1 {
+        if segments[i] != cx.kroot.name { continue }
+        let mods = segments[i + 1..segments.len() - 1].iter().map(|s| &s[..]).collect::<Vec<&str>>();
+        let kmod = match cx.kroot.find_module(&mods) {
+            Some(kmod) => kmod,
+            None => continue,
+        };
+        let found = kmod.items.iter().any(|kitem| match kitem {
+            Item::Enum(ref kenum) => &kenum.name == name,
+            Item::Struct(ref kstruct) => &kstruct.name == name,
+            Item::Module(_) => false,
+        });
+        if !found { continue }
+        let mut ss = vec![module_class_name(cx.opts, cx.kroot, None, &[])];
+        let mut kparent = cx.kroot;
+        for j in 0..mods.len() {
+            let kmod1 = kparent.find_module(&mods[j..j + 1]).unwrap();
+            let class_name = module_class_name(cx.opts, kmod1, Some(kparent), &ss);
+            ss.push(class_name);
+            kparent = kmod1;
+        }
+        let item_name = type_name(java_ident(name), &ss);
+        ss.push(item_name);
+        return ss.join(".");
+    }
+    return type_name(java_ident(name), &[]);
+}
+
+fn class_name(opts: &GenOptions, name: &str) -> String {
+    let name = wire_name(name);
+    return match opts.naming {
+        Naming::Preserve => java_ident(name),
+        Naming::Idiomatic => java_ident(&upper_camel_case(name)),
+    };
+}
+
+/// Simple names of types which generated code uses without
+/// qualification. Generated types of the same names would shadow
+/// them.
+const JAVA_TYPE_NAMES: &'static [&'static str] = &[
+    "ArrayList", "BigInteger", "Boolean", "Byte", "Collections",
+    "DeserializationContext", "Deprecated", "Double", "Float",
+    "FunctionalInterface", "IOException", "IgenDeserializer",
+    "IgenHandshake", "IgenJson", "IgenSchema", "IgenSerializer",
+    "Integer", "JsonDeserialize", "JsonGenerator", "JsonNode",
+    "JsonParser", "JsonProcessingException", "JsonSerialize", "List",
+    "Long", "Optional", "Override", "SerializerProvider", "Short",
+    "StdDeserializer", "StdSerializer", "String", "Supplier",
+];
+
+/// A nested type cannot have the name of a class around it in
+/// Java, and must not shadow types which generated code uses.
+/// Such names get a trailing underscore like C#.
+fn type_name(name: String, enclosing: &[String]) -> String {
+    let mut name = name;
+    while JAVA_TYPE_NAMES.contains(&&name[..]) || enclosing.contains(&name) {
+        name.push_str("_");
+    }
+    return name;
+}
+
+/// Module classes contain items and other module classes, so a
+/// class which has the name of a child or a sibling item gets a
+/// trailing underscore too. Types keep their names.
+fn module_class_name(opts: &GenOptions, kmod: &Module, kparent: Option<&Module>, enclosing: &[String]) -> String {
+    let name = class_name(opts, &kmod.name);
+    let item_name = |kitem: &Item| match kitem {
+        Item::Module(ref kmod1) => class_name(opts, &kmod1.name),
+        Item::Enum(ref kenum) => java_ident(&kenum.name),
+        Item::Struct(ref kstruct) => java_ident(&kstruct.name),
+    };
+    let mut clashes = kmod.items.iter().any(|kitem| item_name(kitem) == name);
+    if let Some(kparent) = kparent {
+        clashes = clashes || kparent.items.iter().any(|kitem| match kitem {
+            Item::Module(_) => false,
+            _ => item_name(kitem) == name,
+        });
+    }
+    if clashes {
+        return type_name([&name[..], "_"].join(""), enclosing);
+    }
+    return type_name(name, enclosing);
+}
+
+fn variant_name(opts: &GenOptions, kvariant: &EnumVariant, enclosing: &[String]) -> String {
+    return type_name(Lang::Java.variant_name(opts.naming, &kvariant.name), enclosing);
+}
+
+/// Tuple fields are named by their positions. (`f0`, `f1`, ...)
+fn field_name(opts: &GenOptions, kfield: &StructField, index: usize) -> String {
+    if kfield.name == "" {
+        return ["f", &index.to_string()].join("");
+    }
+    return Lang::Java.field_name(opts.naming, &kfield.name);
+}
+
+fn fingerprint_member(fingerprint: &str) -> String {
+    return ["    public static final String IGEN_FINGERPRINT = \"", fingerprint, "\";\n"].join("");
+}
+
+/// Javadoc `@deprecated` carries the note, and the annotation
+/// carries the version.
+fn deprecated(deprecation: &Option<Deprecation>) -> String {
+    let deprecation = match deprecation {
+        Some(ref deprecation) => deprecation,
+        None => return String::new(),
+    };
+    let mut s = String::new();
+    if let Some(ref note) = deprecation.note {
+        s.push_str("/** @deprecated ");
+        s.push_str(&note.replace("*/", "* /").replace("\n", " "));
+        s.push_str(" */\n");
+    }
+    s.push_str(&deprecated_annotation(deprecation));
+    s.push_str("\n");
+    return s;
+}
+
+fn deprecated_annotation(deprecation: &Deprecation) -> String {
+    return match deprecation.since {
+        Some(ref since) => ["@Deprecated(since = ", &string_literal(since), ")"].join(""),
+        None => "@Deprecated".to_string(),
+    };
+}
+
+fn java_ident(name: &str) -> String {
+    return Lang::Java.escape_ident(name);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use serde_json;
+    use schema::Module;
+    use schema::codegen::GenOptions;
+
+    fn module() -> Module {
+        return serde_json::from_str(r#"{"name": "point", "visibility": "Public", "items": [
+            {"Struct": {"name": "Point", "visibility": "Public", "fields": [
+                {"name": "x", "ty": {"Core": "U64"}, "default": true},
+                {"name": "tags", "ty": {"Item": ["point", "List"]}},
+                {"name": "label", "ty": {"Option": "String"}}]}},
+            {"Struct": {"name": "List", "visibility": "Public", "fields": [
+                {"name": "items", "ty": {"Vec": "String"}, "default": true}]}},
+            {"Module": {"name": "optional", "visibility": "Public", "items": [
+                {"Struct": {"name": "Optional", "visibility": "Public", "fields": []}}]}},
+            {"Enum": {"name": "Shape", "visibility": "Public", "variants": [
+                {"name": "Shape", "fields": []},
+                {"name": "String", "fields": [
+                    {"name": "", "ty": "String"},
+                    {"name": "", "ty": {"Item": ["point", "optional", "Optional"]}, "default": true}]}]}}]}"#).unwrap();
+    }
+
+    #[test]
+    fn renames_root_class_named_like_its_items() {
+        let kmod = module();
+        let mut opts = GenOptions::new();
+        opts.package = Some("com.example".to_string());
+        assert_eq!(kmod.gen_java_path(&opts), PathBuf::from("com/example/Point_.java"));
+        let code = kmod.gen_java_code(&opts);
+        assert!(code.contains("package com.example;\n"));
+        assert!(code.contains(concat!(
+            "public final class Point_ {\n",
+            "    private Point_() {}\n")));
+        assert!(code.contains("    public record Point(\n"));
+    }
+
+    #[test]
+    fn renames_variants_named_like_their_enums() {
+        let code = module().gen_java_code(&GenOptions::new());
+        assert!(code.contains("    public sealed interface Shape permits Shape.Shape_, Shape.String_ {\n"));
+        assert!(code.contains("        record Shape_() implements Shape {}\n"));
+        assert!(code.contains(concat!(
+            "                case \"Shape\":\n",
+            "                    return new Shape_();\n")));
+    }
+
+    #[test]
+    fn renames_types_which_shadow_jdk_types() {
+        let code = module().gen_java_code(&GenOptions::new());
+        // Field types still refer to JDK types.
+        assert!(code.contains(concat!(
+            "    public record List_(\n",
+            "            List<String> items) {\n")));
+        assert!(code.contains("            Point_.List_ tags,\n"));
+        assert!(code.contains("        record String_(String f0, Point_.Optional_.Optional__ f1) implements Shape {}\n"));
+        // The module class clashes with its item first.
+        assert!(code.contains(concat!(
+            "    public static final class Optional_ {\n",
+            "        private Optional_() {}\n")));
+        assert!(code.contains("        public record Optional__() {\n"));
+        // Wire names are kept.
+        assert!(code.contains("            IgenJson.expectObject(n, \"List\");\n"));
+        assert!(code.contains("                g.writeString(\"String\");\n"));
+    }
+
+    #[test]
+    fn decodes_objects_with_defaults_and_required_keys() {
+        let code = module().gen_java_code(&GenOptions::new());
+        assert!(code.contains(concat!(
+            "            return new Point(\n",
+            "                IgenJson.field(n, \"x\", IgenJson::readU64, () -> 0L),\n",
+            "                IgenJson.field(n, \"tags\", \"Point\", Point_.List_::igenRead),\n",
+            "                IgenJson.field(n, \"label\", IgenJson.readOptional(IgenJson::readString), () -> Optional.empty()));\n")));
+        assert!(code.contains(concat!(
+            "            IgenJson.writeU64(g, v.x());\n",
+            "            g.writeFieldName(\"tags\");\n",
+            "            Point_.List_.igenWrite(g, v.tags());\n",
+            "            if (v.label().isPresent()) {\n")));
+    }
+
+    #[test]
+    fn decodes_variants_with_missing_trailing_defaults() {
+        let code = module().gen_java_code(&GenOptions::new());
+        assert!(code.contains(concat!(
+            "                    return new String_(\n",
+            "                        IgenJson.element(n, 1, \"String\", IgenJson::readString),\n",
+            "                        IgenJson.element(n, 2, Point_.Optional_.Optional__::igenRead, () -> Point_.Optional_.Optional__.igenDefault()));\n",
+            "                default:\n",
+            "                    throw new IgenJson.DecodingException(\"unknown variant `\" + name + \"` of Shape\");\n")));
+    }
+}
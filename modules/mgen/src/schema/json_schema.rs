@@ -0,0 +1,401 @@
+use serde_json;
+use serde_json::Map;
+use serde_json::Value;
+use super::Module;
+use super::Struct;
+use super::Enum;
+use super::EnumVariant;
+use super::StructField;
+use super::Item;
+use super::Type;
+use super::CoreType;
+use super::TypePath;
+use super::Visibility;
+use super::Deprecation;
+use super::ident::wire_name;
+use super::codegen::GenOptions;
+use super::codegen::deprecation_message;
+
+const DRAFT: &'static str = "https://json-schema.org/draft/2020-12/schema";
+
+/// JSON Schema (draft 2020-12) export.
+///
+/// Every public item becomes an entry of `$defs` keyed by its
+/// path joined with dots. (`proto.sub.Point`) References are
+/// `$ref`s to these entries, so a single item can be validated
+/// with `<file>#/$defs/<path>`. Paths start at the root module
+/// even if it is not the crate root.
+///
+/// Schemas describe the wire format of Swift code. Unknown keys
+/// and trailing elements are allowed, because decoders skip them.
+impl Module {
+    pub fn gen_json_schema(&self, opts: &GenOptions) -> String {
+        let cx = Context { kroot: self, flavor: Flavor::JsonSchema };
+        let mut defs = Map::new();
+        gen_module(&cx, self, &[], &mut defs);
+        if opts.handshake {
+            gen_handshake(&cx, &mut defs);
+        }
+        let mut root = Map::new();
+        root.insert("$schema".to_string(), Value::String(DRAFT.to_string()));
+        root.insert("title".to_string(), Value::String(wire_name(&self.name).to_string()));
+        root.insert("$comment".to_string(), Value::String(["fingerprint ", &self.fingerprint()].join("")));
+        root.insert("$defs".to_string(), Value::Object(defs));
+        return serde_json::to_string_pretty(&Value::Object(root)).unwrap();
+    }
+}
+
+/// Documents which embed schemas of items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flavor {
+    /// Draft 2020-12 with items in `$defs`.
+    JsonSchema,
+    /// Draft 07 with items in `components/schemas`.
+    /// Tuples use `items` instead of `prefixItems`.
+    AsyncApi,
+}
+
+impl Flavor {
+    fn ref_prefix(&self) -> &'static str {
+        return match self {
+            Flavor::JsonSchema => "#/$defs/",
+            Flavor::AsyncApi => "#/components/schemas/",
+        };
+    }
+}
+
+/// Schema of a struct in `kroot` for other documents.
+pub fn struct_schema(kroot: &Module, kstruct: &Struct, flavor: Flavor) -> Value {
+    return gen_struct(&Context { kroot: kroot, flavor: flavor }, kstruct);
+}
+/// Schema of an enum in `kroot` for other documents.
+pub fn enum_schema(kroot: &Module, kenum: &Enum, flavor: Flavor) -> Value {
+    return gen_enum(&Context { kroot: kroot, flavor: flavor }, kenum);
+}
+/// Schema of a single variant of an enum in `kroot`.
+pub fn variant_schema(kroot: &Module, kvariant: &EnumVariant, flavor: Flavor) -> Value {
+    return gen_variant(&Context { kroot: kroot, flavor: flavor }, kvariant);
+}
+
+struct Context<'a> {
+    kroot: &'a Module,
+    flavor: Flavor,
+}
+
+/// `mods` is the path of `kmod` excluding the root module.
+fn gen_module(cx: &Context, kmod: &Module, mods: &[String], defs: &mut Map<String, Value>) {
+    for kitem in &kmod.items {
+        if kitem.visibility() != Visibility::Public { continue }
+        match kitem {
+            Item::Module(ref kmod1) => {
+                let mut mods1 = mods.to_vec();
+                mods1.push(kmod1.name.clone());
+                gen_module(cx, kmod1, &mods1, defs);
+            },
+            Item::Enum(ref kenum) => {
+                defs.insert(def_key(cx, mods, &kenum.name), gen_enum(cx, kenum));
+            },
+            Item::Struct(ref kstruct) => {
+                defs.insert(def_key(cx, mods, &kstruct.name), gen_struct(cx, kstruct));
+            },
+        }
+    }
+}
+
+/// Same messages with Swift `IgenHandshake`.
+/// They are not in the root module, so keys have no path.
+fn gen_handshake(cx: &Context, defs: &mut Map<String, Value>) {
+    let mut hello = Struct::new();
+    hello.name = "IgenHello".to_string();
+    hello.fields.push(StructField { name: "fingerprints".to_string(), ty: Type::Vec(Box::new(Type::String)), default: false, deprecation: None, tag: None });
+    let mut hello_ack = Struct::new();
+    hello_ack.name = "IgenHelloAck".to_string();
+    hello_ack.fields.push(StructField { name: "fingerprint".to_string(), ty: Type::Option(Box::new(Type::String)), default: false, deprecation: None, tag: None });
+    defs.insert(hello.name.clone(), gen_struct(cx, &hello));
+    defs.insert(hello_ack.name.clone(), gen_struct(cx, &hello_ack));
+}
+
+/// Missing keys are allowed only for optional fields and fields
+/// with defaults.
+fn gen_struct(cx: &Context, kstruct: &Struct) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::<Value>::new();
+    for kfield in &kstruct.fields {
+        let name = wire_name(&kfield.name).to_string();
+        properties.insert(name.clone(), gen_field(cx, kfield));
+        if is_required(kfield) {
+            required.push(Value::String(name));
+        }
+    }
+    let mut m = Map::new();
+    m.insert("title".to_string(), Value::String(wire_name(&kstruct.name).to_string()));
+    m.insert("$comment".to_string(), Value::String(["fingerprint ", &kstruct.fingerprint()].join("")));
+    insert_deprecation(&mut m, &kstruct.deprecation);
+    m.insert("type".to_string(), Value::String("object".to_string()));
+    m.insert("properties".to_string(), Value::Object(properties));
+    if required.len() > 0 {
+        m.insert("required".to_string(), Value::Array(required));
+    }
+    return Value::Object(m);
+}
+
+/// Encoded as `[name, field0, field1, ...]`.
+/// Missing trailing elements are allowed only for fields with
+/// defaults.
+fn gen_enum(cx: &Context, kenum: &Enum) -> Value {
+    let mut m = Map::new();
+    m.insert("title".to_string(), Value::String(wire_name(&kenum.name).to_string()));
+    m.insert("$comment".to_string(), Value::String(["fingerprint ", &kenum.fingerprint()].join("")));
+    insert_deprecation(&mut m, &kenum.deprecation);
+    // `oneOf` cannot be empty.
+    if kenum.variants.len() == 0 {
+        m.insert("not".to_string(), Value::Object(Map::new()));
+        return Value::Object(m);
+    }
+    let variants = kenum.variants.iter().map(|kvariant| gen_variant(cx, kvariant)).collect::<Vec<Value>>();
+    m.insert("oneOf".to_string(), Value::Array(variants));
+    return Value::Object(m);
+}
+
+fn gen_variant(cx: &Context, kvariant: &EnumVariant) -> Value {
+    let name = wire_name(&kvariant.name).to_string();
+    let mut tag = Map::new();
+    tag.insert("const".to_string(), Value::String(name.clone()));
+    let mut items = vec![Value::Object(tag)];
+    let mut min_items = 1;
+    for (i, kfield) in kvariant.fields.iter().enumerate() {
+        items.push(gen_field(cx, kfield));
+        if !kfield.default {
+            min_items = i + 2;
+        }
+    }
+    let mut m = Map::new();
+    m.insert("title".to_string(), Value::String(name));
+    insert_deprecation(&mut m, &kvariant.deprecation);
+    m.insert("type".to_string(), Value::String("array".to_string()));
+    match cx.flavor {
+        Flavor::JsonSchema => m.insert("prefixItems".to_string(), Value::Array(items)),
+        Flavor::AsyncApi => m.insert("items".to_string(), Value::Array(items)),
+    };
+    m.insert("minItems".to_string(), Value::from(min_items));
+    return Value::Object(m);
+}
+
+/// Named fields also have `title`.
+fn gen_field(cx: &Context, kfield: &StructField) -> Value {
+    let mut m = match gen_type(cx, &kfield.ty) {
+        Value::Object(m) => m,
+        _ => Map::new(),
+    };
+    if kfield.name != "" {
+        m.insert("title".to_string(), Value::String(wire_name(&kfield.name).to_string()));
+    }
+    insert_deprecation(&mut m, &kfield.deprecation);
+    if kfield.default {
+        if let Some(value) = default_value(cx, &kfield.ty) {
+            m.insert("default".to_string(), value);
+        }
+    }
+    return Value::Object(m);
+}
+
+fn gen_type(cx: &Context, ty: &Type) -> Value {
+    let mut m = Map::new();
+    match ty {
+        Type::Core(CoreType::Bool) => {
+            m.insert("type".to_string(), Value::String("boolean".to_string()));
+        },
+        Type::Core(CoreType::F32) | Type::Core(CoreType::F64) => {
+            m.insert("type".to_string(), Value::String("number".to_string()));
+        },
+        Type::Core(ref core_type) => {
+            let (min, max) = integer_range(core_type);
+            m.insert("type".to_string(), Value::String("integer".to_string()));
+            m.insert("minimum".to_string(), min);
+            m.insert("maximum".to_string(), max);
+        },
+        Type::String => {
+            m.insert("type".to_string(), Value::String("string".to_string()));
+        },
+        // Items out of the root have no entry, so any value is
+        // allowed for them.
+        Type::Item(ref path) => match find_item(cx.kroot, path) {
+            Some((key, _)) => {
+                m.insert("$ref".to_string(), Value::String([cx.flavor.ref_prefix(), &key].join("")));
+            },
+            None => {
+                m.insert("$comment".to_string(), Value::String([&path_key(path), " is not exported"].join("")));
+            },
+        },
+        // Decoders take `null` as absence. Nested options are
+        // indistinguishable on the wire.
+        Type::Option(ref ty1) => {
+            let mut null = Map::new();
+            null.insert("type".to_string(), Value::String("null".to_string()));
+            let mut ty1 = &**ty1;
+            while let Type::Option(ref ty2) = ty1 {
+                ty1 = ty2;
+            }
+            m.insert("anyOf".to_string(), Value::Array(vec![gen_type(cx, ty1), Value::Object(null)]));
+        },
+        Type::Vec(ref ty1) => {
+            m.insert("type".to_string(), Value::String("array".to_string()));
+            m.insert("items".to_string(), gen_type(cx, ty1));
+        },
+    }
+    return Value::Object(m);
+}
+
+/// Encoded value of `Default::default()`.
+/// `None` for items which are not in the root module.
+fn default_value(cx: &Context, ty: &Type) -> Option<Value> {
+    return match ty {
+        Type::Core(CoreType::Bool) => Some(Value::Bool(false)),
+        Type::Core(CoreType::F32) | Type::Core(CoreType::F64) => Some(Value::from(0.0)),
+        Type::Core(_) => Some(Value::from(0)),
+        Type::String => Some(Value::String(String::new())),
+        Type::Item(ref path) => {
+            let kstruct = match find_item(cx.kroot, path) {
+                Some((_, &Item::Struct(ref kstruct))) => kstruct,
+                _ => return None,
+            };
+            let mut m = Map::new();
+            for kfield in &kstruct.fields {
+                // Absent options are omitted.
+                if let Type::Option(_) = kfield.ty { continue }
+                let value = match default_value(cx, &kfield.ty) {
+                    Some(value) => value,
+                    None => return None,
+                };
+                m.insert(wire_name(&kfield.name).to_string(), value);
+            }
+            Some(Value::Object(m))
+        },
+        Type::Option(_) => Some(Value::Null),
+        Type::Vec(_) => Some(Value::Array(Vec::new())),
+    };
+}
+
+/// Item at `path` in `kroot` and its key in `$defs`.
+/// Paths start at the crate, so `kroot` may be in the middle.
+pub fn find_item<'a>(kroot: &'a Module, path: &TypePath) -> Option<(String, &'a Item)> {
+    let segments = &path.0;
+    if segments.len() == 0 {
+        return None;
+    }
+    let name = &segments[segments.len() - 1];
+    for i in 0..segments.len() - 1 {
+        if segments[i] != kroot.name { continue }
+        let mods = segments[i + 1..segments.len() - 1].iter().map(|s| &s[..]).collect::<Vec<&str>>();
+        let kmod = match kroot.find_module(&mods) {
+            Some(kmod) => kmod,
+            None => continue,
+        };
+        let kitem = kmod.items.iter().find(|kitem| match kitem {
+            Item::Enum(ref kenum) => &kenum.name == name,
+            Item::Struct(ref kstruct) => &kstruct.name == name,
+            Item::Module(_) => false,
+        });
+        if let Some(kitem) = kitem {
+            return Some((path_key(&TypePath(segments[i..].to_vec())), kitem));
+        }
+    }
+    return None;
+}
+
+fn insert_deprecation(m: &mut Map<String, Value>, deprecation: &Option<Deprecation>) {
+    let deprecation = match deprecation {
+        Some(ref deprecation) => deprecation,
+        None => return,
+    };
+    m.insert("deprecated".to_string(), Value::Bool(true));
+    if let Some(message) = deprecation_message(deprecation) {
+        m.insert("description".to_string(), Value::String(message));
+    }
+}
+
+fn is_required(kfield: &StructField) -> bool {
+    if kfield.default { return false }
+    return match kfield.ty {
+        Type::Option(_) => false,
+        _ => true,
+    };
+}
+
+fn integer_range(core_type: &CoreType) -> (Value, Value) {
+    use self::CoreType::*;
+    return match core_type {
+        U8 => (Value::from(0), Value::from(u8::max_value())),
+        U16 => (Value::from(0), Value::from(u16::max_value())),
+        U32 => (Value::from(0), Value::from(u32::max_value())),
+        U64 => (Value::from(0), Value::from(u64::max_value())),
+        I8 => (Value::from(i8::min_value()), Value::from(i8::max_value())),
+        I16 => (Value::from(i16::min_value()), Value::from(i16::max_value())),
+        I32 => (Value::from(i32::min_value()), Value::from(i32::max_value())),
+        I64 => (Value::from(i64::min_value()), Value::from(i64::max_value())),
+        Bool | F32 | F64 => (Value::Null, Value::Null),
+    };
+}
+
+fn def_key(cx: &Context, mods: &[String], name: &str) -> String {
+    let mut path = vec![cx.kroot.name.clone()];
+    path.extend(mods.iter().cloned());
+    path.push(name.to_string());
+    return path_key(&TypePath(path));
+}
+
+/// Key of an item in `$defs`. (`proto.sub.Point`)
+pub fn path_key(path: &TypePath) -> String {
+    return path.0.iter().map(|s| wire_name(s)).collect::<Vec<&str>>().join(".");
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use serde_json::Value;
+    use schema::Module;
+    use schema::codegen::GenOptions;
+    use schema::tests::sample_module;
+
+    fn value(json: &str) -> Value {
+        return serde_json::from_str(json).unwrap();
+    }
+
+    fn defs(kmod: &Module) -> Value {
+        let root: Value = serde_json::from_str(&kmod.gen_json_schema(&GenOptions::new())).unwrap();
+        return root["$defs"].clone();
+    }
+
+    #[test]
+    fn allows_absent_options_of_structs() {
+        let item = &defs(&sample_module())["p.Item"];
+        assert_eq!(item["required"], value(r#"["id"]"#));
+        assert_eq!(item["properties"]["label"]["anyOf"], value(r#"[{"type": "string"}, {"type": "null"}]"#));
+    }
+
+    #[test]
+    fn encodes_variants_as_name_and_fields() {
+        let message = &defs(&sample_module())["p.Message"];
+        assert_eq!(message["oneOf"][0]["prefixItems"], value(r#"[{"const": "Ping"}]"#));
+        assert_eq!(message["oneOf"][1]["minItems"], value(r#"3"#));
+        assert_eq!(message["oneOf"][1]["prefixItems"][0], value(r#"{"const": "Move"}"#));
+        assert_eq!(message["oneOf"][1]["prefixItems"][2]["anyOf"][1], value(r#"{"type": "null"}"#));
+    }
+
+    #[test]
+    fn keys_items_from_the_root_module() {
+        let kmod: Module = serde_json::from_str(r#"{"name": "protocol", "visibility": "Public", "items": [
+            {"Module": {"name": "sub", "visibility": "Public", "items": [
+                {"Struct": {"name": "Point", "visibility": "Public", "fields": [
+                    {"name": "x", "ty": {"Core": "I32"}, "default": true}]}}]}},
+            {"Struct": {"name": "Line", "visibility": "Public", "fields": [
+                {"name": "a", "ty": {"Item": ["app", "protocol", "sub", "Point"]}, "default": true},
+                {"name": "b", "ty": {"Item": ["other", "Thing"]}}]}}]}"#).unwrap();
+        let defs = defs(&kmod);
+        let a = &defs["protocol.Line"]["properties"]["a"];
+        assert_eq!(a["$ref"], value(r##""#/$defs/protocol.sub.Point""##));
+        assert_eq!(a["default"], value(r#"{"x": 0}"#));
+        assert!(defs["protocol.sub.Point"].is_object());
+        assert_eq!(defs["protocol.Line"]["properties"]["b"]["$ref"], Value::Null);
+    }
+}
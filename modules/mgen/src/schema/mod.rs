@@ -1,7 +1,22 @@
 
 mod init;
 pub mod scan;
+pub mod resolve;
+pub mod ident;
+pub mod naming;
+pub mod compat;
+pub mod fingerprint;
 pub mod codegen;
+pub mod python;
+pub mod csharp;
+pub mod go;
+pub mod java;
+pub mod dart;
+pub mod c;
+pub mod cpp;
+pub mod json_schema;
+pub mod proto;
+pub mod asyncapi;
 
 use ::serde;
 use ::serde_derive;
@@ -10,13 +25,14 @@ use ::serde_derive;
 /// It's easier to process on this simplified AST.
 /// As Rust AST changes constantly, it's better to have this one.
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[derive(Serialize, Deserialize)]
 pub struct Module {
     pub name: String,
+    pub visibility: Visibility,
     pub items: Vec<Item>,
 }
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[derive(Serialize, Deserialize)]
 pub enum Item {
     Module(Module),
@@ -24,10 +40,25 @@ pub enum Item {
     Struct(Struct),
 }
 
-#[derive(Debug)]
+/// Private items are kept in the tree so references to them
+/// can be diagnosed, but they are never exported to other languages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+pub enum Visibility {
+    Public,
+    Private,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[derive(Serialize, Deserialize)]
 pub struct Enum {
     pub name: String,
+    pub visibility: Visibility,
+    #[serde(default)]
+    pub deprecation: Option<Deprecation>,
+    /// Names of retired variants which must not be reused.
+    #[serde(default)]
+    pub reserved: Vec<String>,
     pub variants: Vec<EnumVariant>,
 }
 /// An enum variant always have non-empty name.
@@ -37,27 +68,60 @@ pub struct Enum {
 ///
 /// In Swift-side, tuple field will become name-less parameters.
 /// Struct fields will become named paramers.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[derive(Serialize, Deserialize)]
 pub struct EnumVariant {
     pub name: String,
+    #[serde(default)]
+    pub deprecation: Option<Deprecation>,
+    /// From `#[igen(tag = N)]`. See `StructField::tag`.
+    #[serde(default)]
+    pub tag: Option<u32>,
     pub fields: Vec<StructField>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[derive(Serialize, Deserialize)]
 pub struct Struct {
     pub name: String,
+    pub visibility: Visibility,
+    #[serde(default)]
+    pub deprecation: Option<Deprecation>,
+    /// Names of retired fields which must not be reused.
+    #[serde(default)]
+    pub reserved: Vec<String>,
     pub fields: Vec<StructField>,
 }
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[derive(Serialize, Deserialize)]
 pub struct StructField {
     pub name: String,
     pub ty: Type,
+    /// Decoders fill a missing value with `Default::default()`
+    /// of the type. For `Type::Item`, the item must be a struct
+    /// whose all fields have defaults.
+    #[serde(default)]
+    pub default: bool,
+    #[serde(default)]
+    pub deprecation: Option<Deprecation>,
+    /// Field number for Protocol Buffers from `#[igen(tag = N)]`.
+    /// `None` uses the position from 1. This does not affect
+    /// the wire format or fingerprints.
+    #[serde(default)]
+    pub tag: Option<u32>,
+}
+
+/// From `#[deprecated]`.
+/// Deprecated items are still exported. Backends mark them
+/// deprecated in generated code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+pub struct Deprecation {
+    pub since: Option<String>,
+    pub note: Option<String>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[derive(Serialize, Deserialize)]
 pub enum Type {
     Core(CoreType),
@@ -70,7 +134,7 @@ pub enum Type {
     //HashMap(Box<Type>,Box<Type>),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[derive(Serialize, Deserialize)]
 pub enum CoreType {
     Bool,
@@ -85,7 +149,27 @@ pub enum CoreType {
 type Variant = EnumVariant;
 type Field = StructField;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[derive(Serialize, Deserialize)]
 pub struct TypePath(Vec<String>);
 
+#[cfg(test)]
+pub mod tests {
+    use serde_json;
+    use super::Module;
+
+    /// `struct Item { id: i32, label: Option<String> }` and
+    /// `enum Message { Ping, Move(i32, Option<String>) }` in `p`
+    /// for golden tests of backends.
+    pub fn sample_module() -> Module {
+        return serde_json::from_str(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Struct": {"name": "Item", "visibility": "Public", "fields": [
+                {"name": "id", "ty": {"Core": "I32"}},
+                {"name": "label", "ty": {"Option": "String"}}]}},
+            {"Enum": {"name": "Message", "visibility": "Public", "variants": [
+                {"name": "Ping", "fields": []},
+                {"name": "Move", "fields": [
+                    {"name": "", "ty": {"Core": "I32"}},
+                    {"name": "", "ty": {"Option": "String"}}]}]}}]}"#).unwrap();
+    }
+}
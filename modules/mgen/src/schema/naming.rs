@@ -0,0 +1,195 @@
+
+use super::ident::Lang;
+use super::ident::wire_name;
+
+/// How Rust names are converted into names in target
+/// language source code.
+///
+/// Names on the wire are never converted. Generated code maps
+/// converted names back to the Rust names when encoding and
+/// decoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Naming {
+    /// Use Rust names as is.
+    Preserve,
+    /// Follow naming convention of each target language.
+    Idiomatic,
+}
+
+impl Naming {
+    pub fn parse(s: &str) -> Option<Naming> {
+        return match s {
+            "preserve" => Some(Naming::Preserve),
+            "idiomatic" => Some(Naming::Idiomatic),
+            _ => None,
+        };
+    }
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Naming::Preserve => "preserve",
+            Naming::Idiomatic => "idiomatic",
+        };
+    }
+}
+
+impl Lang {
+    /// Name of a `struct` field or an enum variant field.
+    pub fn field_name(&self, naming: Naming, name: &str) -> String {
+        let name = wire_name(name);
+        let name = match naming {
+            Naming::Preserve => name.to_string(),
+            Naming::Idiomatic => match self {
+                Lang::Python => snake_case(name),
+                Lang::CSharp => upper_camel_case(name),
+                Lang::Go => upper_camel_case(name),
+                Lang::C => snake_case(name),
+                Lang::Cpp => snake_case(name),
+                _ => lower_camel_case(name),
+            },
+        };
+        return self.escape_ident(&name);
+    }
+    /// Name of an enum variant.
+    pub fn variant_name(&self, naming: Naming, name: &str) -> String {
+        let name = wire_name(name);
+        let name = match naming {
+            Naming::Preserve => name.to_string(),
+            Naming::Idiomatic => match self {
+                Lang::Swift => lower_camel_case(name),
+                Lang::TypeScript => name.to_string(),
+                Lang::Kotlin => upper_camel_case(name),
+                Lang::Python => upper_camel_case(name),
+                Lang::CSharp => upper_camel_case(name),
+                Lang::Go => upper_camel_case(name),
+                Lang::Java => upper_camel_case(name),
+                Lang::Dart => upper_camel_case(name),
+                Lang::C => snake_case(name),
+                Lang::Cpp => upper_camel_case(name),
+            },
+        };
+        return self.escape_ident(&name);
+    }
+}
+
+/// `foo_bar` -> `fooBar`, `FooBar` -> `fooBar`.
+pub fn lower_camel_case(name: &str) -> String {
+    let mut s = String::new();
+    for (i, word) in split_words(name).iter().enumerate() {
+        if i == 0 {
+            s.push_str(&word.to_lowercase());
+        }
+        else {
+            s.push_str(&capitalize(word));
+        }
+    }
+    return s;
+}
+
+/// `foo_bar` -> `FooBar`, `fooBar` -> `FooBar`.
+pub fn upper_camel_case(name: &str) -> String {
+    let mut s = String::new();
+    for word in split_words(name) {
+        s.push_str(&capitalize(&word));
+    }
+    return s;
+}
+
+/// `fooBar` -> `foo_bar`, `HTTPServer` -> `http_server`.
+pub fn snake_case(name: &str) -> String {
+    let words = split_words(name).iter().map(|word| word.to_lowercase()).collect::<Vec<String>>();
+    return words.join("_");
+}
+
+fn capitalize(word: &str) -> String {
+    let mut cs = word.chars();
+    return match cs.next() {
+        None => String::new(),
+        Some(c) => c.to_uppercase().chain(cs).collect(),
+    };
+}
+
+/// Splits a `snake_case` or `CamelCase` name into words.
+/// Consecutive capitals are treated as an acronym. (`HTTPServer` -> `HTTP`, `Server`)
+fn split_words(name: &str) -> Vec<String> {
+    let cs = name.chars().collect::<Vec<char>>();
+    let mut words = Vec::<String>::new();
+    let mut word = String::new();
+    for i in 0..cs.len() {
+        let c = cs[i];
+        if c == '_' {
+            if word.len() > 0 { words.push(word.clone()) }
+            word.clear();
+            continue;
+        }
+        if c.is_uppercase() && word.len() > 0 {
+            let prev = cs[i - 1];
+            let next_is_lower = i + 1 < cs.len() && cs[i + 1].is_lowercase();
+            if !prev.is_uppercase() || next_is_lower {
+                words.push(word.clone());
+                word.clear();
+            }
+        }
+        word.push(c);
+    }
+    if word.len() > 0 { words.push(word) }
+    return words;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Naming;
+    use super::Lang;
+    use super::lower_camel_case;
+    use super::upper_camel_case;
+    use super::snake_case;
+
+    #[test]
+    fn converts_cases() {
+        assert_eq!(lower_camel_case("foo_bar"), "fooBar");
+        assert_eq!(lower_camel_case("FooBar"), "fooBar");
+        assert_eq!(upper_camel_case("foo_bar"), "FooBar");
+        assert_eq!(upper_camel_case("fooBar"), "FooBar");
+        assert_eq!(snake_case("fooBar"), "foo_bar");
+        assert_eq!(snake_case("HTTPServer"), "http_server");
+        assert_eq!(snake_case("__a__b"), "a_b");
+    }
+
+    #[test]
+    fn round_trips_through_cases() {
+        for &name in &["foo_bar", "x", "http_server", "user_id_list"] {
+            assert_eq!(snake_case(&upper_camel_case(name)), name);
+            assert_eq!(snake_case(&lower_camel_case(name)), name);
+        }
+        for &name in &["FooBar", "HttpServer", "X"] {
+            assert_eq!(upper_camel_case(&snake_case(name)), name);
+        }
+    }
+
+    #[test]
+    fn converts_names_per_language() {
+        assert_eq!(Lang::Swift.field_name(Naming::Idiomatic, "user_id"), "userId");
+        assert_eq!(Lang::Python.field_name(Naming::Idiomatic, "userId"), "user_id");
+        assert_eq!(Lang::CSharp.field_name(Naming::Idiomatic, "user_id"), "UserId");
+        assert_eq!(Lang::Go.field_name(Naming::Idiomatic, "user_id"), "UserId");
+        assert_eq!(Lang::C.variant_name(Naming::Idiomatic, "MoveTo"), "move_to");
+        assert_eq!(Lang::Swift.variant_name(Naming::Idiomatic, "MoveTo"), "moveTo");
+        assert_eq!(Lang::TypeScript.variant_name(Naming::Idiomatic, "move_to"), "move_to");
+        assert_eq!(Lang::Kotlin.variant_name(Naming::Idiomatic, "move_to"), "MoveTo");
+        assert_eq!(Lang::Kotlin.field_name(Naming::Idiomatic, "user_id"), "userId");
+        assert_eq!(Lang::Swift.field_name(Naming::Preserve, "user_id"), "user_id");
+    }
+
+    #[test]
+    fn escapes_converted_names() {
+        assert_eq!(Lang::Swift.variant_name(Naming::Idiomatic, "Default"), "`default`");
+        assert_eq!(Lang::Python.field_name(Naming::Idiomatic, "r#Class"), "class_");
+        assert_eq!(Lang::Go.field_name(Naming::Idiomatic, "r#type"), "Type");
+    }
+
+    #[test]
+    fn parses_names() {
+        assert_eq!(Naming::parse("preserve"), Some(Naming::Preserve));
+        assert_eq!(Naming::parse(Naming::Idiomatic.name()), Some(Naming::Idiomatic));
+        assert_eq!(Naming::parse("camel"), None);
+    }
+}
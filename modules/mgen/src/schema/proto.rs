@@ -0,0 +1,484 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use super::Module;
+use super::Struct;
+use super::Enum;
+use super::StructField;
+use super::Item;
+use super::Type;
+use super::CoreType;
+use super::TypePath;
+use super::Visibility;
+use super::Deprecation;
+use super::ident::wire_name;
+use super::naming::Naming;
+use super::naming::snake_case;
+use super::naming::upper_camel_case;
+use super::codegen::GenOptions;
+use super::codegen::deprecation_message;
+use super::resolve::field_tags;
+use super::resolve::variant_tags;
+
+/// Protocol Buffers (proto3) export.
+///
+/// Each module becomes a file with a package, and a struct
+/// becomes a message. An enum with fields becomes a message with
+/// a nested message for each variant in `oneof value`. An enum
+/// without any field becomes a proto enum.
+///
+/// Field numbers come from `#[igen(tag = N)]`, and members
+/// without tags take free numbers in declaration order, as
+/// `resolve::field_tags` assigns them. `Option` and `Vec` which
+/// proto cannot nest are wrapped in messages.
+///
+/// `GenOptions::package` replaces the package of the root module.
+/// Modules which refer to each other produce an import cycle,
+/// which `protoc` does not allow.
+impl Module {
+    /// Files relative to output directory.
+    pub fn gen_proto_files(&self, opts: &GenOptions) -> Vec<(PathBuf, String)> {
+        let cx = Context { kroot: self, opts: opts };
+        let mut files = Vec::<(PathBuf, String)>::new();
+        gen_files(&cx, self, &mut Vec::new(), &mut files);
+        return files;
+    }
+}
+
+struct Context<'a> {
+    kroot: &'a Module,
+    opts: &'a GenOptions,
+}
+
+/// A file being generated.
+struct File<'a, 'b: 'a> {
+    cx: &'a Context<'b>,
+    /// Module names from the root. Empty for the root.
+    path: Vec<String>,
+    imports: BTreeSet<String>,
+    /// Wrapper messages keyed by their names.
+    wrappers: BTreeMap<String, String>,
+}
+
+fn gen_files(cx: &Context, kmod: &Module, path: &mut Vec<String>, files: &mut Vec<(PathBuf, String)>) {
+    let code = {
+        let mut file = File { cx: cx, path: path.clone(), imports: BTreeSet::new(), wrappers: BTreeMap::new() };
+        gen_file(&mut file, kmod)
+    };
+    files.push((PathBuf::from(file_path(cx, path)), code));
+    for kitem in &kmod.items {
+        if let Item::Module(ref kmod1) = kitem {
+            if kmod1.visibility != Visibility::Public { continue }
+            path.push(kmod1.name.clone());
+            gen_files(cx, kmod1, path, files);
+            path.pop();
+        }
+    }
+}
+
+fn gen_file(file: &mut File, kmod: &Module) -> String {
+    let mut ss = Vec::<String>::new();
+    for kitem in &kmod.items {
+        if kitem.visibility() != Visibility::Public { continue }
+        match kitem {
+            Item::Module(_) => {},
+            Item::Enum(ref kenum) => ss.push(gen_enum(file, kenum)),
+            Item::Struct(ref kstruct) => ss.push(gen_struct(file, kstruct)),
+        }
+    }
+    if file.path.len() == 0 && file.cx.opts.handshake {
+        ss.push(gen_handshake(file));
+    }
+    let wrappers = file.wrappers.values().cloned().collect::<Vec<String>>();
+    ss.extend(wrappers.into_iter());
+
+    let mut s = String::new();
+    s.push_str("// Code generated by mgen. DO NOT EDIT.\n");
+    if file.path.len() == 0 {
+        s.push_str("// Schema fingerprint: ");
+        s.push_str(&file.cx.kroot.fingerprint());
+        s.push_str("\n");
+    }
+    s.push_str("\n");
+    s.push_str("syntax = \"proto3\";\n");
+    s.push_str("\n");
+    s.push_str("package ");
+    s.push_str(&package(file.cx, &file.path).join("."));
+    s.push_str(";\n");
+    if file.imports.len() > 0 {
+        s.push_str("\n");
+        for import in &file.imports {
+            s.push_str("import \"");
+            s.push_str(import);
+            s.push_str("\";\n");
+        }
+    }
+    for code in &ss {
+        s.push_str("\n");
+        s.push_str(code);
+    }
+    return s;
+}
+
+/// Same messages with Swift `IgenHandshake`.
+fn gen_handshake(file: &mut File) -> String {
+    let mut hello = Struct::new();
+    hello.name = "IgenHello".to_string();
+    hello.fields.push(StructField { name: "fingerprints".to_string(), ty: Type::Vec(Box::new(Type::String)), default: false, deprecation: None, tag: Some(1) });
+    let mut hello_ack = Struct::new();
+    hello_ack.name = "IgenHelloAck".to_string();
+    hello_ack.fields.push(StructField { name: "fingerprint".to_string(), ty: Type::Option(Box::new(Type::String)), default: false, deprecation: None, tag: Some(1) });
+    return [gen_struct(file, &hello), gen_struct(file, &hello_ack)].join("\n");
+}
+
+fn gen_struct(file: &mut File, kstruct: &Struct) -> String {
+    let mut s = String::new();
+    s.push_str(&fingerprint_comment(&kstruct.fingerprint()));
+    s.push_str(&deprecation_comment(&kstruct.deprecation, ""));
+    s.push_str("message ");
+    s.push_str(wire_name(&kstruct.name));
+    s.push_str(" {\n");
+    let mut header = String::new();
+    if kstruct.deprecation.is_some() {
+        header.push_str("  option deprecated = true;\n");
+    }
+    let names = kstruct.reserved.iter().map(|name| field_name(file.cx.opts, name)).collect::<Vec<String>>();
+    header.push_str(&reserved(&names));
+    s.push_str(&header);
+    if header.len() > 0 && kstruct.fields.len() > 0 {
+        s.push_str("\n");
+    }
+    s.push_str(&gen_fields(file, &kstruct.fields, "  "));
+    s.push_str("}\n");
+    return s;
+}
+
+fn gen_enum(file: &mut File, kenum: &Enum) -> String {
+    let is_plain = kenum.variants.len() > 0 && kenum.variants.iter().all(|kvariant| kvariant.fields.len() == 0);
+    if is_plain {
+        return gen_plain_enum(kenum);
+    }
+    let tags = variant_tags(&kenum.variants);
+    let mut s = String::new();
+    s.push_str(&fingerprint_comment(&kenum.fingerprint()));
+    s.push_str(&deprecation_comment(&kenum.deprecation, ""));
+    s.push_str("message ");
+    s.push_str(wire_name(&kenum.name));
+    s.push_str(" {\n");
+    if kenum.deprecation.is_some() {
+        s.push_str("  option deprecated = true;\n");
+    }
+    let names = kenum.reserved.iter().map(|name| oneof_field_name(name)).collect::<Vec<String>>();
+    s.push_str(&reserved(&names));
+    if kenum.deprecation.is_some() || names.len() > 0 {
+        s.push_str("\n");
+    }
+    for kvariant in &kenum.variants {
+        s.push_str(&deprecation_comment(&kvariant.deprecation, "  "));
+        s.push_str("  message ");
+        s.push_str(wire_name(&kvariant.name));
+        if kvariant.fields.len() == 0 {
+            s.push_str(" {}\n");
+            continue;
+        }
+        s.push_str(" {\n");
+        s.push_str(&gen_fields(file, &kvariant.fields, "    "));
+        s.push_str("  }\n");
+    }
+    if kenum.variants.len() > 0 {
+        s.push_str("\n");
+        s.push_str("  oneof value {\n");
+        for (i, kvariant) in kenum.variants.iter().enumerate() {
+            s.push_str("    ");
+            s.push_str(wire_name(&kvariant.name));
+            s.push_str(" ");
+            s.push_str(&oneof_field_name(&kvariant.name));
+            s.push_str(" = ");
+            s.push_str(&tags[i].to_string());
+            s.push_str(&deprecated_option(&kvariant.deprecation));
+            s.push_str(";\n");
+        }
+        s.push_str("  }\n");
+    }
+    s.push_str("}\n");
+    return s;
+}
+
+/// Values are prefixed with the enum name, because values of
+/// proto enums share the scope of the enum. Zero is required as
+/// the first value of proto3 enums.
+fn gen_plain_enum(kenum: &Enum) -> String {
+    let tags = variant_tags(&kenum.variants);
+    let prefix = [&snake_case(wire_name(&kenum.name)).to_uppercase(), "_"].join("");
+    let mut s = String::new();
+    s.push_str(&fingerprint_comment(&kenum.fingerprint()));
+    s.push_str(&deprecation_comment(&kenum.deprecation, ""));
+    s.push_str("enum ");
+    s.push_str(wire_name(&kenum.name));
+    s.push_str(" {\n");
+    if kenum.deprecation.is_some() {
+        s.push_str("  option deprecated = true;\n");
+    }
+    let names = kenum.reserved.iter().map(|name| [&prefix[..], &snake_case(wire_name(name)).to_uppercase()].join("")).collect::<Vec<String>>();
+    s.push_str(&reserved(&names));
+    s.push_str("  ");
+    s.push_str(&prefix);
+    s.push_str("UNSPECIFIED = 0;\n");
+    for (i, kvariant) in kenum.variants.iter().enumerate() {
+        s.push_str(&deprecation_comment(&kvariant.deprecation, "  "));
+        s.push_str("  ");
+        s.push_str(&prefix);
+        s.push_str(&snake_case(wire_name(&kvariant.name)).to_uppercase());
+        s.push_str(" = ");
+        s.push_str(&tags[i].to_string());
+        s.push_str(&deprecated_option(&kvariant.deprecation));
+        s.push_str(";\n");
+    }
+    s.push_str("}\n");
+    return s;
+}
+
+fn gen_fields(file: &mut File, kfields: &[StructField], indent: &str) -> String {
+    let tags = field_tags(kfields);
+    let mut s = String::new();
+    for (i, kfield) in kfields.iter().enumerate() {
+        s.push_str(&deprecation_comment(&kfield.deprecation, indent));
+        s.push_str(indent);
+        s.push_str(&field_type(file, &kfield.ty));
+        s.push_str(" ");
+        if kfield.name == "" {
+            s.push_str("f");
+            s.push_str(&i.to_string());
+        }
+        else {
+            s.push_str(&field_name(file.cx.opts, &kfield.name));
+        }
+        s.push_str(" = ");
+        s.push_str(&tags[i].to_string());
+        s.push_str(&deprecated_option(&kfield.deprecation));
+        s.push_str(";\n");
+    }
+    return s;
+}
+
+/// Type with a label. (`optional string`, `repeated Point`)
+/// Nested options are indistinguishable on the wire, so they
+/// become a single option.
+fn field_type(file: &mut File, ty: &Type) -> String {
+    return match ty {
+        Type::Option(ref ty1) => {
+            let mut ty1 = &**ty1;
+            while let Type::Option(ref ty2) = ty1 {
+                ty1 = ty2;
+            }
+            ["optional ", &element_type(file, ty1)].join("")
+        },
+        Type::Vec(ref ty1) => ["repeated ", &element_type(file, ty1)].join(""),
+        _ => element_type(file, ty),
+    };
+}
+
+/// Type which can have a label. Options and vectors are wrapped.
+fn element_type(file: &mut File, ty: &Type) -> String {
+    return match ty {
+        Type::Core(ref core_type) => core_name(core_type).to_string(),
+        Type::String => "string".to_string(),
+        Type::Item(ref path) => type_path(file, path),
+        Type::Option(_) | Type::Vec(_) => {
+            let name = ["Igen", &mangle(ty)].join("");
+            if !file.wrappers.contains_key(&name) {
+                // Reserves the name first for recursive calls.
+                file.wrappers.insert(name.clone(), String::new());
+                let mut s = String::new();
+                s.push_str("message ");
+                s.push_str(&name);
+                s.push_str(" {\n");
+                s.push_str("  ");
+                s.push_str(&field_type(file, ty));
+                s.push_str(" value = 1;\n");
+                s.push_str("}\n");
+                file.wrappers.insert(name.clone(), s);
+            }
+            name
+        },
+    };
+}
+
+/// Name of a type in names of wrapper messages.
+/// (`String`, `Point`, `OptString`, `VecPoint`)
+fn mangle(ty: &Type) -> String {
+    return match ty {
+        Type::Core(ref core_type) => upper_camel_case(core_name(core_type)),
+        Type::String => "String".to_string(),
+        Type::Item(ref path) => path.0.last().map(|s| wire_name(s).to_string()).unwrap_or("????".to_string()),
+        Type::Option(ref ty1) => ["Opt", &mangle(ty1)].join(""),
+        Type::Vec(ref ty1) => ["Vec", &mangle(ty1)].join(""),
+    };
+}
+
+/// Proto has no 8-bit and 16-bit integers.
+fn core_name(core_type: &CoreType) -> &'static str {
+    use self::CoreType::*;
+    return match core_type {
+        Bool => "bool",
+        U8 | U16 | U32 => "uint32",
+        U64 => "uint64",
+        I8 | I16 | I32 => "int32",
+        I64 => "int64",
+        F32 => "float",
+        F64 => "double",
+    };
+}
+
+/// Fully qualified name if the item is in the root module.
+/// The file of the item is imported.
+/// Otherwise only the last segment is used like Swift.
+fn type_path(file: &mut File, path: &TypePath) -> String {
+    let segments = &path.0;
+    if segments.len() == 0 {
+        return "????".to_string();
+    }
+    let name = wire_name(&segments[segments.len() - 1]);
+    for i in 0..segments.len() - 1 {
+        if segments[i] != file.cx.kroot.name { continue }
+        let mods = segments[i + 1..segments.len() - 1].to_vec();
+        let found = {
+            let mods1 = mods.iter().map(|s| &s[..]).collect::<Vec<&str>>();
+            file.cx.kroot.find_module(&mods1).map(|kmod| {
+                return kmod.items.iter().any(|kitem| match kitem {
+                    Item::Enum(ref kenum) => wire_name(&kenum.name) == name,
+                    Item::Struct(ref kstruct) => wire_name(&kstruct.name) == name,
+                    Item::Module(_) => false,
+                });
+            }).unwrap_or(false)
+        };
+        if found {
+            if mods != file.path {
+                file.imports.insert(file_path(file.cx, &mods));
+            }
+            return [".", &package(file.cx, &mods).join("."), ".", name].join("");
+        }
+    }
+    return name.to_string();
+}
+
+/// Package segments of a module. (`["proto", "sub"]`)
+fn package(cx: &Context, mods: &[String]) -> Vec<String> {
+    let mut r = match cx.opts.package {
+        Some(ref package) => vec![package.clone()],
+        None => vec![wire_name(&cx.kroot.name).to_string()],
+    };
+    for m in mods {
+        r.push(wire_name(m).to_string());
+    }
+    return r;
+}
+
+/// `proto.proto` for the root, `proto/sub.proto` for `sub`.
+fn file_path(cx: &Context, mods: &[String]) -> String {
+    let mut segments = vec![wire_name(&cx.kroot.name)];
+    for m in mods {
+        segments.push(wire_name(m));
+    }
+    return [&segments.join("/")[..], ".proto"].join("");
+}
+
+fn field_name(opts: &GenOptions, name: &str) -> String {
+    let name = wire_name(name);
+    return match opts.naming {
+        Naming::Preserve => name.to_string(),
+        Naming::Idiomatic => snake_case(name),
+    };
+}
+
+/// Always in snake case, because a field in `oneof` cannot have
+/// the name of the nested message of its variant.
+fn oneof_field_name(name: &str) -> String {
+    return snake_case(wire_name(name));
+}
+
+fn reserved(names: &[String]) -> String {
+    if names.len() == 0 {
+        return String::new();
+    }
+    let names = names.iter().map(|name| ["\"", name, "\""].join("")).collect::<Vec<String>>();
+    return ["  reserved ", &names.join(", "), ";\n"].join("");
+}
+
+fn fingerprint_comment(fingerprint: &str) -> String {
+    return ["// Fingerprint: ", fingerprint, "\n"].join("");
+}
+
+fn deprecation_comment(deprecation: &Option<Deprecation>, indent: &str) -> String {
+    let message = match deprecation {
+        Some(ref deprecation) => deprecation_message(deprecation),
+        None => None,
+    };
+    return match message {
+        Some(message) => [indent, "// Deprecated: ", &message.replace("\n", " "), "\n"].join(""),
+        None => String::new(),
+    };
+}
+
+fn deprecated_option(deprecation: &Option<Deprecation>) -> String {
+    return match deprecation {
+        Some(_) => " [deprecated = true]".to_string(),
+        None => String::new(),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use schema::Module;
+    use schema::codegen::GenOptions;
+    use schema::tests::sample_module;
+
+    fn gen_code() -> String {
+        let files = sample_module().gen_proto_files(&GenOptions::new());
+        assert_eq!(files.len(), 1);
+        return files[0].1.clone();
+    }
+
+    #[test]
+    fn marks_options_of_structs_optional() {
+        assert!(gen_code().contains(concat!(
+            "message Item {\n",
+            "  int32 id = 1;\n",
+            "  optional string label = 2;\n",
+            "}\n")));
+    }
+
+    #[test]
+    fn encodes_variants_as_oneof_messages() {
+        assert!(gen_code().contains(concat!(
+            "message Message {\n",
+            "  message Ping {}\n",
+            "  message Move {\n",
+            "    int32 f0 = 1;\n",
+            "    optional string f1 = 2;\n",
+            "  }\n",
+            "\n",
+            "  oneof value {\n",
+            "    Ping ping = 1;\n",
+            "    Move move = 2;\n",
+            "  }\n",
+            "}\n")));
+    }
+
+    #[test]
+    fn numbers_untagged_fields_around_tagged_ones() {
+        let kmod: Module = serde_json::from_str(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Struct": {"name": "Point", "visibility": "Public", "fields": [
+                {"name": "x", "ty": {"Core": "I32"}, "tag": 2},
+                {"name": "y", "ty": {"Core": "I32"}},
+                {"name": "z", "ty": {"Core": "I32"}}]}}]}"#).unwrap();
+        let files = kmod.gen_proto_files(&GenOptions::new());
+        assert!(files[0].1.contains(concat!(
+            "message Point {\n",
+            "  int32 x = 2;\n",
+            "  int32 y = 1;\n",
+            "  int32 z = 3;\n",
+            "}\n")));
+    }
+}
@@ -0,0 +1,532 @@
+
+use super::Module;
+use super::Struct;
+use super::Enum;
+use super::EnumVariant;
+use super::StructField;
+use super::Item;
+use super::Type;
+use super::CoreType;
+use super::TypePath;
+use super::Visibility;
+use super::Deprecation;
+use super::ident::Lang;
+use super::ident::wire_name;
+use super::ident::string_literal;
+use super::codegen::GenOptions;
+use super::codegen::deprecation_message;
+
+/// Python backend.
+///
+/// Structs become `@dataclass` classes. Enums become a base
+/// class with a `@dataclass` subclass for each variant.
+/// Each class has `to_json` and `from_json` which convert from/to
+/// JSON values of `json` module in the same wire format of Swift
+/// code.
+///
+/// Modules are flattened like Swift code.
+impl Module {
+    pub fn gen_python_code(&self, opts: &GenOptions) -> String {
+        let mut ss = Vec::<String>::new();
+        for kitem in &self.items {
+            if kitem.visibility() != Visibility::Public { continue }
+            ss.push(kitem.gen_python_code(opts));
+        }
+        return ss.join("\n\n");
+    }
+    /// Imports needed by the code of this module as a root.
+    pub fn gen_python_prelude(&self) -> String {
+        let mut s = String::new();
+        s.push_str("from __future__ import annotations\n");
+        s.push_str("from dataclasses import dataclass\n");
+        s.push_str("from typing import Any, ClassVar, Optional\n");
+        if python_has_deprecation(self) {
+            s.push_str("try:\n");
+            s.push_str("    from warnings import deprecated\n");
+            s.push_str("except ImportError:\n");
+            s.push_str("    from typing_extensions import deprecated\n");
+        }
+        return s;
+    }
+    pub fn gen_python_fingerprint(&self) -> String {
+        let mut s = String::new();
+        s.push_str("class IgenSchema:\n");
+        s.push_str("    fingerprint: ClassVar[str] = \"");
+        s.push_str(&self.fingerprint());
+        s.push_str("\"\n");
+        return s;
+    }
+    /// Same messages with Swift `IgenHandshake`.
+    pub fn gen_python_handshake(&self) -> String {
+        let mut s = String::new();
+        s.push_str("class IgenHandshake:\n");
+        s.push_str("    fingerprint: ClassVar[str] = IgenSchema.fingerprint\n");
+        s.push_str("\n");
+        s.push_str("    @dataclass\n");
+        s.push_str("    class Hello:\n");
+        s.push_str("        fingerprints: list[str]\n");
+        s.push_str("\n");
+        s.push_str("        def to_json(self) -> Any:\n");
+        s.push_str("            return {\"fingerprints\": list(self.fingerprints)}\n");
+        s.push_str("\n");
+        s.push_str("        @staticmethod\n");
+        s.push_str("        def from_json(j: Any) -> IgenHandshake.Hello:\n");
+        s.push_str("            return IgenHandshake.Hello(fingerprints=[str(x) for x in j[\"fingerprints\"]])\n");
+        s.push_str("\n");
+        s.push_str("    @dataclass\n");
+        s.push_str("    class HelloAck:\n");
+        s.push_str("        fingerprint: Optional[str]\n");
+        s.push_str("\n");
+        s.push_str("        def to_json(self) -> Any:\n");
+        s.push_str("            if self.fingerprint is None:\n");
+        s.push_str("                return {}\n");
+        s.push_str("            return {\"fingerprint\": self.fingerprint}\n");
+        s.push_str("\n");
+        s.push_str("        @staticmethod\n");
+        s.push_str("        def from_json(j: Any) -> IgenHandshake.HelloAck:\n");
+        s.push_str("            return IgenHandshake.HelloAck(fingerprint=j.get(\"fingerprint\"))\n");
+        s.push_str("\n");
+        s.push_str("    @staticmethod\n");
+        s.push_str("    def hello() -> IgenHandshake.Hello:\n");
+        s.push_str("        return IgenHandshake.Hello(fingerprints=[IgenHandshake.fingerprint])\n");
+        s.push_str("\n");
+        s.push_str("    @staticmethod\n");
+        s.push_str("    def accept(hello: IgenHandshake.Hello) -> IgenHandshake.HelloAck:\n");
+        s.push_str("        ok = IgenHandshake.fingerprint in hello.fingerprints\n");
+        s.push_str("        return IgenHandshake.HelloAck(fingerprint=IgenHandshake.fingerprint if ok else None)\n");
+        return s;
+    }
+}
+impl Item {
+    pub fn gen_python_code(&self, opts: &GenOptions) -> String {
+        return match self {
+            Item::Module(ref kmod) => kmod.gen_python_code(opts),
+            Item::Enum(ref kenum) => kenum.gen_python_code(opts),
+            Item::Struct(ref kstruct) => kstruct.gen_python_code(opts),
+        };
+    }
+}
+impl Enum {
+    pub fn gen_python_code(&self, opts: &GenOptions) -> String {
+        let name = python_ident(&self.name);
+        let mut s = String::new();
+        s.push_str(&python_deprecation(&self.deprecation));
+        s.push_str("class ");
+        s.push_str(&name);
+        s.push_str(":\n");
+        s.push_str(&python_fingerprint_member(&self.fingerprint()));
+        s.push_str("\n");
+        s.push_str("    def to_json(self) -> Any:\n");
+        s.push_str("        raise NotImplementedError\n");
+        s.push_str("\n");
+        s.push_str("    @staticmethod\n");
+        s.push_str("    def from_json(j: Any) -> ");
+        s.push_str(&name);
+        s.push_str(":\n");
+        s.push_str("        n = j[0]\n");
+        for kvariant in &self.variants {
+            s.push_str("        if n == ");
+            s.push_str(&string_literal(wire_name(&kvariant.name)));
+            s.push_str(":\n");
+            s.push_str("            return ");
+            s.push_str(&self.python_variant_class_name(opts, kvariant));
+            s.push_str(".from_json(j)\n");
+        }
+        s.push_str("        raise ValueError(");
+        s.push_str(&string_literal(&["unknown variant of ", &self.name, ": "].join("")));
+        s.push_str(" + repr(n))\n");
+        for kvariant in &self.variants {
+            s.push_str("\n\n");
+            s.push_str(&self.gen_python_variant(opts, kvariant));
+        }
+        return s;
+    }
+}
+impl Struct {
+    pub fn gen_python_code(&self, opts: &GenOptions) -> String {
+        let name = python_ident(&self.name);
+        let mut s = String::new();
+        s.push_str(&python_deprecation(&self.deprecation));
+        s.push_str("@dataclass\n");
+        s.push_str("class ");
+        s.push_str(&name);
+        s.push_str(":\n");
+        s.push_str(&python_fingerprint_member(&self.fingerprint()));
+        s.push_str(&gen_python_fields(opts, &self.fields));
+        if self.has_default() {
+            s.push_str("\n");
+            s.push_str("    @staticmethod\n");
+            s.push_str("    def igen_default() -> ");
+            s.push_str(&name);
+            s.push_str(":\n");
+            s.push_str("        return ");
+            s.push_str(&name);
+            s.push_str("(");
+            let mut ss = Vec::<String>::new();
+            for kfield in &self.fields {
+                ss.push([&Lang::Python.field_name(opts.naming, &kfield.name), "=", &kfield.ty.gen_python_default_value()].join(""));
+            }
+            s.push_str(&ss.join(", "));
+            s.push_str(")\n");
+        }
+        s.push_str("\n");
+        s.push_str("    def to_json(self) -> Any:\n");
+        s.push_str("        j = {}\n");
+        for kfield in &self.fields {
+            let value = ["self.", &Lang::Python.field_name(opts.naming, &kfield.name)].join("");
+            let key = string_literal(wire_name(&kfield.name));
+            // Swift omits `nil` of optional properties.
+            match kfield.ty {
+                Type::Option(ref ty1) => {
+                    s.push_str("        if ");
+                    s.push_str(&value);
+                    s.push_str(" is not None:\n");
+                    s.push_str("            j[");
+                    s.push_str(&key);
+                    s.push_str("] = ");
+                    s.push_str(&ty1.gen_python_encoding(&value, 0));
+                    s.push_str("\n");
+                },
+                _ => {
+                    s.push_str("        j[");
+                    s.push_str(&key);
+                    s.push_str("] = ");
+                    s.push_str(&kfield.ty.gen_python_encoding(&value, 0));
+                    s.push_str("\n");
+                },
+            }
+        }
+        s.push_str("        return j\n");
+        s.push_str("\n");
+        s.push_str("    @staticmethod\n");
+        s.push_str("    def from_json(j: Any) -> ");
+        s.push_str(&name);
+        s.push_str(":\n");
+        s.push_str("        return ");
+        s.push_str(&name);
+        s.push_str("(\n");
+        for kfield in &self.fields {
+            let key = string_literal(wire_name(&kfield.name));
+            let value = ["j[", &key, "]"].join("");
+            s.push_str("            ");
+            s.push_str(&Lang::Python.field_name(opts.naming, &kfield.name));
+            s.push_str("=");
+            // Unknown keys are ignored. Missing keys are allowed
+            // only for optional fields and fields with defaults.
+            if kfield.default {
+                s.push_str("(");
+                s.push_str(&kfield.ty.gen_python_decoding(&value, 0));
+                s.push_str(" if ");
+                s.push_str(&key);
+                s.push_str(" in j else ");
+                s.push_str(&kfield.ty.gen_python_default_value());
+                s.push_str(")");
+            }
+            else if let Type::Option(_) = kfield.ty {
+                let value = ["j.get(", &key, ")"].join("");
+                s.push_str(&kfield.ty.gen_python_decoding(&value, 0));
+            }
+            else {
+                s.push_str(&kfield.ty.gen_python_decoding(&value, 0));
+            }
+            s.push_str(",\n");
+        }
+        s.push_str("        )\n");
+        return s;
+    }
+}
+
+impl Enum {
+    fn python_variant_class_name(&self, opts: &GenOptions, kvariant: &EnumVariant) -> String {
+        let name = [wire_name(&self.name), &Lang::Python.variant_name(opts.naming, &kvariant.name)].join("");
+        return python_ident(&name);
+    }
+    /// Encoded as `[name, field0, field1, ...]`.
+    fn gen_python_variant(&self, opts: &GenOptions, kvariant: &EnumVariant) -> String {
+        let name = self.python_variant_class_name(opts, kvariant);
+        let mut s = String::new();
+        s.push_str(&python_deprecation(&kvariant.deprecation));
+        s.push_str("@dataclass\n");
+        s.push_str("class ");
+        s.push_str(&name);
+        s.push_str("(");
+        s.push_str(&python_ident(&self.name));
+        s.push_str("):\n");
+        s.push_str(&gen_python_fields(opts, &kvariant.fields));
+        if kvariant.fields.len() > 0 {
+            s.push_str("\n");
+        }
+        s.push_str("    def to_json(self) -> Any:\n");
+        s.push_str("        return [");
+        let mut ss = vec![string_literal(wire_name(&kvariant.name))];
+        for (i, kfield) in kvariant.fields.iter().enumerate() {
+            let value = ["self.", &python_field_name(opts, kfield, i)].join("");
+            ss.push(kfield.ty.gen_python_encoding(&value, 0));
+        }
+        s.push_str(&ss.join(", "));
+        s.push_str("]\n");
+        s.push_str("\n");
+        s.push_str("    @staticmethod\n");
+        s.push_str("    def from_json(j: Any) -> ");
+        s.push_str(&name);
+        s.push_str(":\n");
+        s.push_str("        return ");
+        s.push_str(&name);
+        s.push_str("(\n");
+        for (i, kfield) in kvariant.fields.iter().enumerate() {
+            let index = (i + 1).to_string();
+            let value = ["j[", &index, "]"].join("");
+            s.push_str("            ");
+            if kfield.default {
+                s.push_str("(");
+                s.push_str(&kfield.ty.gen_python_decoding(&value, 0));
+                s.push_str(" if len(j) > ");
+                s.push_str(&index);
+                s.push_str(" else ");
+                s.push_str(&kfield.ty.gen_python_default_value());
+                s.push_str(")");
+            }
+            else {
+                s.push_str(&kfield.ty.gen_python_decoding(&value, 0));
+            }
+            s.push_str(",\n");
+        }
+        s.push_str("        )\n");
+        return s;
+    }
+}
+
+/// Dataclass fields have no default values, because they must
+/// follow fields without ones. Defaults are applied only by
+/// `from_json`.
+fn gen_python_fields(opts: &GenOptions, kfields: &[StructField]) -> String {
+    let mut s = String::new();
+    for (i, kfield) in kfields.iter().enumerate() {
+        if let Some(ref deprecation) = kfield.deprecation {
+            s.push_str("    # Deprecated.");
+            if let Some(ref message) = deprecation_message(deprecation) {
+                s.push_str(" ");
+                s.push_str(&message.replace("\n", " "));
+            }
+            s.push_str("\n");
+        }
+        s.push_str("    ");
+        s.push_str(&python_field_name(opts, kfield, i));
+        s.push_str(": ");
+        s.push_str(&kfield.ty.gen_python_code());
+        s.push_str("\n");
+    }
+    return s;
+}
+
+/// Tuple fields are named by their positions. (`f0`, `f1`, ...)
+fn python_field_name(opts: &GenOptions, kfield: &StructField, index: usize) -> String {
+    if kfield.name == "" {
+        return ["f", &index.to_string()].join("");
+    }
+    return Lang::Python.field_name(opts.naming, &kfield.name);
+}
+
+impl Type {
+    pub fn gen_python_code(&self) -> String {
+        return match self {
+            Type::Core(CoreType::Bool) => "bool".to_string(),
+            Type::Core(CoreType::F32) => "float".to_string(),
+            Type::Core(CoreType::F64) => "float".to_string(),
+            Type::Core(_) => "int".to_string(),
+            Type::String => "str".to_string(),
+            Type::Item(ref path) => path.gen_python_code(),
+            Type::Option(ref ty) => ["Optional[", &ty.gen_python_code(), "]"].join(""),
+            Type::Vec(ref ty) => ["list[", &ty.gen_python_code(), "]"].join(""),
+        };
+    }
+    pub fn gen_python_default_value(&self) -> String {
+        return match self {
+            Type::Core(CoreType::Bool) => "False".to_string(),
+            Type::Core(CoreType::F32) => "0.0".to_string(),
+            Type::Core(CoreType::F64) => "0.0".to_string(),
+            Type::Core(_) => "0".to_string(),
+            Type::String => "\"\"".to_string(),
+            Type::Item(ref path) => [&path.gen_python_code(), ".igen_default()"].join(""),
+            Type::Option(_) => "None".to_string(),
+            Type::Vec(_) => "[]".to_string(),
+        };
+    }
+    /// Expression converting `value` into JSON value.
+    /// `depth` makes names of comprehension variables unique.
+    fn gen_python_encoding(&self, value: &str, depth: usize) -> String {
+        return match self {
+            Type::Core(_) => value.to_string(),
+            Type::String => value.to_string(),
+            Type::Item(_) => [value, ".to_json()"].join(""),
+            Type::Option(ref ty) => {
+                let value1 = ty.gen_python_encoding(value, depth);
+                if value1 == value { return value1 }
+                ["(None if ", value, " is None else ", &value1, ")"].join("")
+            },
+            Type::Vec(ref ty) => {
+                let x = ["x", &depth.to_string()].join("");
+                ["[", &ty.gen_python_encoding(&x, depth + 1), " for ", &x, " in ", value, "]"].join("")
+            },
+        };
+    }
+    /// Expression converting JSON `value` into this type.
+    fn gen_python_decoding(&self, value: &str, depth: usize) -> String {
+        return match self {
+            Type::Core(CoreType::Bool) => ["bool(", value, ")"].join(""),
+            Type::Core(CoreType::F32) => ["float(", value, ")"].join(""),
+            Type::Core(CoreType::F64) => ["float(", value, ")"].join(""),
+            Type::Core(_) => ["int(", value, ")"].join(""),
+            Type::String => ["str(", value, ")"].join(""),
+            Type::Item(ref path) => [&path.gen_python_code(), ".from_json(", value, ")"].join(""),
+            Type::Option(ref ty) => {
+                ["(None if ", value, " is None else ", &ty.gen_python_decoding(value, depth), ")"].join("")
+            },
+            Type::Vec(ref ty) => {
+                let x = ["x", &depth.to_string()].join("");
+                ["[", &ty.gen_python_decoding(&x, depth + 1), " for ", &x, " in ", value, "]"].join("")
+            },
+        };
+    }
+}
+
+impl TypePath {
+    pub fn gen_python_code(&self) -> String {
+        return match self.0.last() {
+            Some(ref name) => python_ident(name),
+            None => "????".to_string(),
+        };
+    }
+}
+
+/// Class attributes are not dataclass fields with `ClassVar`.
+fn python_fingerprint_member(fingerprint: &str) -> String {
+    return ["    IGEN_FINGERPRINT: ClassVar[str] = \"", fingerprint, "\"\n"].join("");
+}
+
+/// `category=None` keeps decoding of old data silent.
+/// Type checkers still report uses.
+fn python_deprecation(deprecation: &Option<Deprecation>) -> String {
+    let mut s = String::new();
+    let deprecation = match deprecation {
+        Some(ref deprecation) => deprecation,
+        None => return s,
+    };
+    let message = deprecation_message(deprecation).unwrap_or("deprecated".to_string());
+    s.push_str("@deprecated(");
+    s.push_str(&string_literal(&message));
+    s.push_str(", category=None)\n");
+    return s;
+}
+
+fn python_has_deprecation(kmod: &Module) -> bool {
+    return kmod.items.iter().any(|kitem| {
+        if kitem.visibility() != Visibility::Public { return false }
+        return match kitem {
+            Item::Module(ref kmod1) => python_has_deprecation(kmod1),
+            Item::Enum(ref kenum) => kenum.deprecation.is_some() || kenum.variants.iter().any(|v| v.deprecation.is_some()),
+            Item::Struct(ref kstruct) => kstruct.deprecation.is_some(),
+        };
+    });
+}
+
+fn python_ident(name: &str) -> String {
+    return Lang::Python.escape_ident(name);
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use schema::Module;
+    use schema::codegen::GenOptions;
+
+    fn module() -> Module {
+        return serde_json::from_str(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Struct": {"name": "Size", "visibility": "Public", "fields": [
+                {"name": "w", "ty": {"Core": "I32"}, "default": true}]}},
+            {"Struct": {"name": "Shape", "visibility": "Public", "fields": [
+                {"name": "class", "ty": "String"},
+                {"name": "size", "ty": {"Item": ["p", "Size"]}, "default": true},
+                {"name": "grid", "ty": {"Vec": {"Vec": {"Option": {"Item": ["p", "Size"]}}}}},
+                {"name": "note", "ty": {"Option": "String"}}]}},
+            {"Enum": {"name": "Command", "visibility": "Public", "variants": [
+                {"name": "Stop", "fields": [], "deprecation": {"note": "use Halt", "since": null}},
+                {"name": "Resize", "fields": [
+                    {"name": "", "ty": {"Item": ["p", "Size"]}},
+                    {"name": "", "ty": {"Vec": "String"}, "default": true}]},
+                {"name": "None", "fields": []}]}}]}"#).unwrap();
+    }
+
+    #[test]
+    fn decodes_missing_fields_with_defaults_and_absent_options() {
+        let code = module().gen_python_code(&GenOptions::new());
+        assert!(code.contains(concat!(
+            "    @staticmethod\n",
+            "    def igen_default() -> Size:\n",
+            "        return Size(w=0)\n")));
+        assert!(code.contains(concat!(
+            "        return Shape(\n",
+            "            class_=str(j[\"class\"]),\n",
+            "            size=(Size.from_json(j[\"size\"]) if \"size\" in j else Size.igen_default()),\n",
+            "            grid=[[(None if x1 is None else Size.from_json(x1)) for x1 in x0] for x0 in j[\"grid\"]],\n",
+            "            note=(None if j.get(\"note\") is None else str(j.get(\"note\"))),\n",
+            "        )\n")));
+    }
+
+    #[test]
+    fn omits_absent_options_and_converts_nested_values() {
+        let code = module().gen_python_code(&GenOptions::new());
+        assert!(code.contains(concat!(
+            "    def to_json(self) -> Any:\n",
+            "        j = {}\n",
+            "        j[\"class\"] = self.class_\n",
+            "        j[\"size\"] = self.size.to_json()\n",
+            "        j[\"grid\"] = [[(None if x1 is None else x1.to_json()) for x1 in x0] for x0 in self.grid]\n",
+            "        if self.note is not None:\n",
+            "            j[\"note\"] = self.note\n",
+            "        return j\n")));
+    }
+
+    #[test]
+    fn dispatches_variants_by_wire_names() {
+        let code = module().gen_python_code(&GenOptions::new());
+        assert!(code.contains(concat!(
+            "        n = j[0]\n",
+            "        if n == \"Stop\":\n",
+            "            return CommandStop.from_json(j)\n",
+            "        if n == \"Resize\":\n",
+            "            return CommandResize.from_json(j)\n",
+            "        if n == \"None\":\n",
+            "            return CommandNone_.from_json(j)\n",
+            "        raise ValueError(\"unknown variant of Command: \" + repr(n))\n")));
+        assert!(code.contains("class CommandNone_(Command):\n    def to_json(self) -> Any:\n        return [\"None\"]\n"));
+    }
+
+    #[test]
+    fn decodes_missing_trailing_fields_of_variants_with_defaults() {
+        let code = module().gen_python_code(&GenOptions::new());
+        assert!(code.contains("        return [\"Resize\", self.f0.to_json(), [x0 for x0 in self.f1]]\n"));
+        assert!(code.contains(concat!(
+            "        return CommandResize(\n",
+            "            Size.from_json(j[1]),\n",
+            "            ([str(x0) for x0 in j[2]] if len(j) > 2 else []),\n",
+            "        )\n")));
+    }
+
+    #[test]
+    fn imports_deprecated_with_fallback_for_older_pythons() {
+        let kmod = module();
+        assert!(kmod.gen_python_prelude().ends_with(concat!(
+            "try:\n",
+            "    from warnings import deprecated\n",
+            "except ImportError:\n",
+            "    from typing_extensions import deprecated\n")));
+        assert!(kmod.gen_python_code(&GenOptions::new()).contains(concat!(
+            "@deprecated(\"use Halt\", category=None)\n",
+            "@dataclass\n",
+            "class CommandStop(Command):\n")));
+        let mut kmod = kmod;
+        kmod.items.truncate(2);
+        assert!(!kmod.gen_python_prelude().contains("deprecated"));
+    }
+}
@@ -0,0 +1,681 @@
+
+use std::collections::BTreeMap;
+use std::fmt;
+use super::Module;
+use super::Item;
+use super::EnumVariant;
+use super::StructField;
+use super::Type;
+use super::TypePath;
+use super::ident::wire_name;
+use super::naming::Naming;
+use super::naming::snake_case;
+use super::naming::upper_camel_case;
+use super::Visibility;
+
+/// All exportable items in a module tree keyed by their
+/// fully qualified path.
+///
+/// Paths start with the root module name, which is the crate
+/// name when the tree comes from the scanner. This is the same
+/// form `TypePath` uses.
+#[derive(Debug)]
+pub struct SymbolTable {
+    symbols: BTreeMap<Vec<String>, Symbol>,
+}
+#[derive(Debug)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    /// `true` only if the item and all of its enclosing
+    /// modules are public.
+    pub public: bool,
+    /// `true` if this is a struct whose all fields have defaults.
+    pub default: bool,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Enum,
+    Struct,
+}
+
+#[derive(Debug)]
+pub enum ResolveError {
+    /// Two items have the same fully qualified path.
+    Duplicate { path: Vec<String> },
+    /// A `TypePath` without any segment.
+    EmptyPath { referrer: Vec<String> },
+    /// A `TypePath` which does not point to any item in the tree.
+    Dangling { referrer: Vec<String>, path: Vec<String> },
+    /// An exported item refers to an item which is not exported.
+    Private { referrer: Vec<String>, path: Vec<String> },
+    /// A field with default refers to an item without default.
+    NoDefault { referrer: Vec<String>, field: String, path: Vec<String> },
+    /// A variant or field uses a reserved name of its item.
+    Reserved { path: Vec<String>, name: String },
+    /// A class for an enum variant is named like another item or
+    /// variant class in backends which name it by joining the
+    /// enum and variant names. (`Shape::Circle` and `ShapeCircle`)
+    VariantClass { name: String, paths: Vec<Vec<String>> },
+    /// Variants or fields whose names become the same identifier
+    /// under `Naming::Idiomatic`. (`foo_bar` and `fooBar`)
+    NameCollision { path: Vec<String>, names: Vec<String> },
+    /// A tag which Protocol Buffers does not allow.
+    InvalidTag { path: Vec<String>, name: String, tag: u32 },
+    /// Fields or variants of an item share a tag.
+    DuplicateTag { path: Vec<String>, tag: u32 },
+}
+
+/// Public items sharing a short name.
+/// These collide in backends which flatten namespaces.
+/// For example, Swift code refers items only by their last
+/// path segment.
+#[derive(Debug)]
+pub struct Ambiguity {
+    pub name: String,
+    pub paths: Vec<Vec<String>>,
+}
+
+impl Module {
+    /// Builds a symbol table for this tree and checks every
+    /// `Type::Item` reference against it.
+    pub fn resolve(&self) -> Result<SymbolTable, Vec<ResolveError>> {
+        let mut table = SymbolTable { symbols: BTreeMap::new() };
+        let mut errs = Vec::<ResolveError>::new();
+        collect_symbols(self, &mut Vec::new(), true, &mut table, &mut errs);
+        check_refs(self, &mut Vec::new(), true, &table, &mut errs);
+        if errs.len() > 0 {
+            return Err(errs);
+        }
+        return Ok(table);
+    }
+    /// Checks that classes for variants do not take names of other
+    /// items or variant classes. Only Python, Go and Dart name
+    /// them by joining enum and variant names.
+    pub fn check_variant_classes(&self, naming: Naming) -> Result<(), Vec<ResolveError>> {
+        let mut class_names = BTreeMap::<String, Vec<(Vec<String>, bool)>>::new();
+        collect_class_names(self, &mut Vec::new(), true, naming, &mut class_names);
+        let mut errs = Vec::<ResolveError>::new();
+        for (name, sources) in class_names {
+            if sources.len() < 2 || sources.iter().all(|&(_, is_variant)| !is_variant) { continue }
+            let paths = sources.into_iter().map(|(path, _)| path).collect::<Vec<Vec<String>>>();
+            errs.push(ResolveError::VariantClass { name: name, paths: paths });
+        }
+        if errs.len() > 0 {
+            return Err(errs);
+        }
+        return Ok(());
+    }
+    /// Checks that variants and fields of each item keep distinct
+    /// names under `Naming::Idiomatic`. Targets which preserve
+    /// Rust names do not need this.
+    pub fn check_converted_names(&self) -> Result<(), Vec<ResolveError>> {
+        let mut errs = Vec::<ResolveError>::new();
+        collect_name_collisions(self, &mut Vec::new(), &mut errs);
+        if errs.len() > 0 {
+            return Err(errs);
+        }
+        return Ok(());
+    }
+    /// Checks `#[igen(tag = N)]` of exported items against each
+    /// other. Only Protocol Buffers uses tags, and members without
+    /// tags take free numbers, so they never conflict.
+    pub fn check_tags(&self) -> Result<(), Vec<ResolveError>> {
+        let mut errs = Vec::<ResolveError>::new();
+        collect_tag_errors(self, &mut Vec::new(), &mut errs);
+        if errs.len() > 0 {
+            return Err(errs);
+        }
+        return Ok(());
+    }
+}
+
+impl Module {
+    /// Finds a descendant module by path relative to this module.
+    /// An empty path designates this module.
+    pub fn find_module(&self, path: &[&str]) -> Option<&Module> {
+        if path.len() == 0 {
+            return Some(self);
+        }
+        for kitem in &self.items {
+            if let Item::Module(ref kmod) = kitem {
+                if kmod.name == path[0] {
+                    return kmod.find_module(&path[1..]);
+                }
+            }
+        }
+        return None;
+    }
+}
+
+impl SymbolTable {
+    pub fn get(&self, path: &TypePath) -> Option<&Symbol> {
+        return self.symbols.get(&path.0);
+    }
+    pub fn ambiguities(&self) -> Vec<Ambiguity> {
+        let mut name_paths_map = BTreeMap::<String, Vec<Vec<String>>>::new();
+        for (path, symbol) in &self.symbols {
+            if !symbol.public { continue }
+            let name = path.last().unwrap().clone();
+            name_paths_map.entry(name).or_insert_with(Vec::new).push(path.clone());
+        }
+        let mut r = Vec::<Ambiguity>::new();
+        for (name, paths) in name_paths_map {
+            if paths.len() > 1 {
+                r.push(Ambiguity { name: name, paths: paths });
+            }
+        }
+        return r;
+    }
+}
+
+fn collect_symbols(kmod: &Module, path: &mut Vec<String>, public: bool, table: &mut SymbolTable, errs: &mut Vec<ResolveError>) {
+    let public = public && kmod.visibility == Visibility::Public;
+    path.push(kmod.name.clone());
+    for kitem in &kmod.items {
+        let (name, visibility, kind, default) = match kitem {
+            Item::Module(ref kmod1) => {
+                collect_symbols(kmod1, path, public, table, errs);
+                continue;
+            },
+            Item::Enum(ref kenum) => (&kenum.name, kenum.visibility, SymbolKind::Enum, false),
+            Item::Struct(ref kstruct) => (&kstruct.name, kstruct.visibility, SymbolKind::Struct, kstruct.has_default()),
+        };
+        let mut item_path = path.clone();
+        item_path.push(name.clone());
+        if table.symbols.contains_key(&item_path) {
+            errs.push(ResolveError::Duplicate { path: item_path });
+            continue;
+        }
+        let symbol = Symbol { kind: kind, public: public && visibility == Visibility::Public, default: default };
+        table.symbols.insert(item_path, symbol);
+    }
+    path.pop();
+}
+
+/// Names of public items, and names of classes for their
+/// variants in Python, Go and Dart. Items sharing a short name
+/// are only ambiguous, so sources are marked whether they are
+/// variants.
+fn collect_class_names(kmod: &Module, path: &mut Vec<String>, public: bool, naming: Naming, names: &mut BTreeMap<String, Vec<(Vec<String>, bool)>>) {
+    let public = public && kmod.visibility == Visibility::Public;
+    path.push(kmod.name.clone());
+    for kitem in &kmod.items {
+        if let Item::Module(ref kmod1) = kitem {
+            collect_class_names(kmod1, path, public, naming, names);
+            continue;
+        }
+        if !public || kitem.visibility() != Visibility::Public { continue }
+        let name = match kitem {
+            Item::Enum(ref kenum) => &kenum.name,
+            Item::Struct(ref kstruct) => &kstruct.name,
+            Item::Module(_) => continue,
+        };
+        let mut item_path = path.clone();
+        item_path.push(name.clone());
+        names.entry(wire_name(name).to_string()).or_insert_with(Vec::new).push((item_path.clone(), false));
+        if let Item::Enum(ref kenum) = kitem {
+            for kvariant in &kenum.variants {
+                let mut variant_path = item_path.clone();
+                variant_path.push(kvariant.name.clone());
+                let class_name = match naming {
+                    Naming::Preserve => [wire_name(name), wire_name(&kvariant.name)].join(""),
+                    Naming::Idiomatic => [wire_name(name), &upper_camel_case(wire_name(&kvariant.name))].join(""),
+                };
+                names.entry(class_name).or_insert_with(Vec::new).push((variant_path, true));
+            }
+        }
+    }
+    path.pop();
+}
+
+fn check_refs(kmod: &Module, path: &mut Vec<String>, public: bool, table: &SymbolTable, errs: &mut Vec<ResolveError>) {
+    let public = public && kmod.visibility == Visibility::Public;
+    path.push(kmod.name.clone());
+    for kitem in &kmod.items {
+        let mut item_path = path.clone();
+        match kitem {
+            Item::Module(ref kmod1) => {
+                check_refs(kmod1, path, public, table, errs);
+            },
+            Item::Enum(ref kenum) => {
+                item_path.push(kenum.name.clone());
+                let public = public && kenum.visibility == Visibility::Public;
+                for kvariant in &kenum.variants {
+                    check_field_refs(&kvariant.fields, &item_path, public, table, errs);
+                    check_reserved(&kvariant.name, &kenum.reserved, &item_path, errs);
+                }
+            },
+            Item::Struct(ref kstruct) => {
+                item_path.push(kstruct.name.clone());
+                let public = public && kstruct.visibility == Visibility::Public;
+                check_field_refs(&kstruct.fields, &item_path, public, table, errs);
+                for kfield in &kstruct.fields {
+                    check_reserved(&kfield.name, &kstruct.reserved, &item_path, errs);
+                }
+            },
+        }
+    }
+    path.pop();
+}
+
+fn collect_name_collisions(kmod: &Module, path: &mut Vec<String>, errs: &mut Vec<ResolveError>) {
+    path.push(kmod.name.clone());
+    for kitem in &kmod.items {
+        let mut item_path = path.clone();
+        match kitem {
+            Item::Module(ref kmod1) => collect_name_collisions(kmod1, path, errs),
+            Item::Enum(ref kenum) => {
+                item_path.push(kenum.name.clone());
+                for kvariant in &kenum.variants {
+                    let mut variant_path = item_path.clone();
+                    variant_path.push(kvariant.name.clone());
+                    let names = kvariant.fields.iter().map(|kfield| &kfield.name[..]).collect::<Vec<&str>>();
+                    check_converted_names(&names, &variant_path, errs);
+                }
+                let names = kenum.variants.iter().map(|kvariant| &kvariant.name[..]).collect::<Vec<&str>>();
+                check_converted_names(&names, &item_path, errs);
+            },
+            Item::Struct(ref kstruct) => {
+                item_path.push(kstruct.name.clone());
+                let names = kstruct.fields.iter().map(|kfield| &kfield.name[..]).collect::<Vec<&str>>();
+                check_converted_names(&names, &item_path, errs);
+            },
+        }
+    }
+    path.pop();
+}
+
+/// Compares wire names, so raw identifiers match plain ones.
+fn check_reserved(name: &str, reserved: &[String], path: &Vec<String>, errs: &mut Vec<ResolveError>) {
+    if reserved.iter().any(|name1| wire_name(name1) == wire_name(name)) {
+        errs.push(ResolveError::Reserved { path: path.clone(), name: name.to_string() });
+    }
+}
+
+/// Every case conversion keeps word boundaries, so names with
+/// the same words collide in some backend.
+/// Tuple fields have no name and are skipped.
+fn check_converted_names(names: &[&str], path: &Vec<String>, errs: &mut Vec<ResolveError>) {
+    let mut words_names_map = BTreeMap::<String, Vec<String>>::new();
+    for &name in names {
+        if name == "" { continue }
+        words_names_map.entry(snake_case(wire_name(name))).or_insert_with(Vec::new).push(name.to_string());
+    }
+    for (_, names) in words_names_map {
+        if names.len() > 1 {
+            errs.push(ResolveError::NameCollision { path: path.clone(), names: names });
+        }
+    }
+}
+
+/// Private items are not exported, so their tags are not
+/// checked.
+fn collect_tag_errors(kmod: &Module, path: &mut Vec<String>, errs: &mut Vec<ResolveError>) {
+    path.push(kmod.name.clone());
+    for kitem in &kmod.items {
+        if kitem.visibility() != Visibility::Public { continue }
+        let mut item_path = path.clone();
+        match kitem {
+            Item::Module(ref kmod1) => collect_tag_errors(kmod1, path, errs),
+            Item::Enum(ref kenum) => {
+                item_path.push(kenum.name.clone());
+                for kvariant in &kenum.variants {
+                    let mut variant_path = item_path.clone();
+                    variant_path.push(kvariant.name.clone());
+                    let names = kvariant.fields.iter().map(|kfield| &kfield.name[..]).collect::<Vec<&str>>();
+                    let tags = kvariant.fields.iter().map(|kfield| kfield.tag).collect::<Vec<Option<u32>>>();
+                    check_tags(&names, &tags, &variant_path, errs);
+                }
+                let names = kenum.variants.iter().map(|kvariant| &kvariant.name[..]).collect::<Vec<&str>>();
+                let tags = kenum.variants.iter().map(|kvariant| kvariant.tag).collect::<Vec<Option<u32>>>();
+                check_tags(&names, &tags, &item_path, errs);
+            },
+            Item::Struct(ref kstruct) => {
+                item_path.push(kstruct.name.clone());
+                let names = kstruct.fields.iter().map(|kfield| &kfield.name[..]).collect::<Vec<&str>>();
+                let tags = kstruct.fields.iter().map(|kfield| kfield.tag).collect::<Vec<Option<u32>>>();
+                check_tags(&names, &tags, &item_path, errs);
+            },
+        }
+    }
+    path.pop();
+}
+
+/// Explicit tags must be valid and unique among members of an
+/// item.
+fn check_tags(names: &[&str], tags: &[Option<u32>], path: &Vec<String>, errs: &mut Vec<ResolveError>) {
+    for (i, &tag) in tags.iter().enumerate() {
+        let tag = match tag {
+            Some(tag) => tag,
+            None => continue,
+        };
+        if !is_valid_tag(tag) {
+            errs.push(ResolveError::InvalidTag { path: path.clone(), name: names[i].to_string(), tag: tag });
+        }
+        if tags[..i].contains(&Some(tag)) && !tags[i + 1..].contains(&Some(tag)) {
+            errs.push(ResolveError::DuplicateTag { path: path.clone(), tag: tag });
+        }
+    }
+}
+
+/// Field numbers of fields.
+pub fn field_tags(kfields: &[StructField]) -> Vec<u32> {
+    return assign_tags(&kfields.iter().map(|kfield| kfield.tag).collect::<Vec<Option<u32>>>());
+}
+
+/// Field numbers of variants in `oneof`, or values of a proto
+/// enum.
+pub fn variant_tags(kvariants: &[EnumVariant]) -> Vec<u32> {
+    return assign_tags(&kvariants.iter().map(|kvariant| kvariant.tag).collect::<Vec<Option<u32>>>());
+}
+
+/// Explicit tags are kept, and members without tags take the
+/// smallest valid numbers which no other member uses, in
+/// declaration order. Without any explicit tag, these are
+/// positions from 1.
+fn assign_tags(tags: &[Option<u32>]) -> Vec<u32> {
+    let mut next = 0;
+    return tags.iter().map(|&tag| {
+        if let Some(tag) = tag {
+            return tag;
+        }
+        next += 1;
+        while !is_valid_tag(next) || tags.contains(&Some(next)) {
+            next += 1;
+        }
+        return next;
+    }).collect();
+}
+
+/// Numbers from 19000 to 19999 are reserved by Protocol Buffers.
+pub fn is_valid_tag(tag: u32) -> bool {
+    return tag >= 1 && tag <= 536870911 && !(tag >= 19000 && tag <= 19999);
+}
+
+fn check_field_refs(kfields: &[StructField], referrer: &Vec<String>, public: bool, table: &SymbolTable, errs: &mut Vec<ResolveError>) {
+    for kfield in kfields {
+        check_type_refs(&kfield.ty, referrer, public, table, errs);
+        if !kfield.default { continue }
+        // Defaults of `Option` and `Vec` are always empty.
+        if let Type::Item(ref path) = kfield.ty {
+            let default = table.get(path).map(|symbol| symbol.default).unwrap_or(true);
+            if !default {
+                errs.push(ResolveError::NoDefault { referrer: referrer.clone(), field: kfield.name.clone(), path: path.0.clone() });
+            }
+        }
+    }
+}
+
+fn check_type_refs(ty: &Type, referrer: &Vec<String>, public: bool, table: &SymbolTable, errs: &mut Vec<ResolveError>) {
+    match ty {
+        Type::Core(_) => {},
+        Type::String => {},
+        Type::Option(ref ty1) => check_type_refs(ty1, referrer, public, table, errs),
+        Type::Vec(ref ty1) => check_type_refs(ty1, referrer, public, table, errs),
+        Type::Item(ref path) => {
+            if path.0.len() == 0 {
+                errs.push(ResolveError::EmptyPath { referrer: referrer.clone() });
+                return;
+            }
+            match table.get(path) {
+                None => {
+                    errs.push(ResolveError::Dangling { referrer: referrer.clone(), path: path.0.clone() });
+                },
+                Some(ref symbol) => {
+                    if public && !symbol.public {
+                        errs.push(ResolveError::Private { referrer: referrer.clone(), path: path.0.clone() });
+                    }
+                },
+            }
+        },
+    }
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResolveError::Duplicate { ref path } => {
+                write!(f, "`{}` is defined more than once", path.join("::"))
+            },
+            ResolveError::EmptyPath { ref referrer } => {
+                write!(f, "`{}` refers to a type with an empty path", referrer.join("::"))
+            },
+            ResolveError::Dangling { ref referrer, ref path } => {
+                write!(f, "`{}` refers to `{}` which does not exist", referrer.join("::"), path.join("::"))
+            },
+            ResolveError::Private { ref referrer, ref path } => {
+                write!(f, "`{}` refers to `{}` which is not exported", referrer.join("::"), path.join("::"))
+            },
+            ResolveError::NoDefault { ref referrer, ref field, ref path } => {
+                write!(f, "`{}::{}` has a default but `{}` has no default", referrer.join("::"), field, path.join("::"))
+            },
+            ResolveError::Reserved { ref path, ref name } => {
+                write!(f, "`{}` uses reserved name `{}`", path.join("::"), name)
+            },
+            ResolveError::VariantClass { ref name, ref paths } => {
+                let paths = paths.iter().map(|path| ["`", &path.join("::"), "`"].join("")).collect::<Vec<String>>();
+                write!(f, "{} produce the same class name `{}`", paths.join(", "), name)
+            },
+            ResolveError::NameCollision { ref path, ref names } => {
+                let names = names.iter().map(|name| ["`", name, "`"].join("")).collect::<Vec<String>>();
+                write!(f, "{} in `{}` have the same name after case conversion", names.join(", "), path.join("::"))
+            },
+            ResolveError::InvalidTag { ref path, ref name, tag } => {
+                write!(f, "`{}::{}` has invalid tag {}", path.join("::"), name, tag)
+            },
+            ResolveError::DuplicateTag { ref path, tag } => {
+                write!(f, "tag {} is used more than once in `{}`", tag, path.join("::"))
+            },
+        }
+    }
+}
+
+impl fmt::Display for Ambiguity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let paths = self.paths.iter().map(|p| p.join("::")).collect::<Vec<String>>();
+        write!(f, "short name `{}` is shared by {}", self.name, paths.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use schema::naming::Naming;
+    use schema::Module;
+    use schema::Item;
+    use schema::StructField;
+    use super::ResolveError;
+    use super::is_valid_tag;
+    use super::assign_tags;
+    use super::field_tags;
+    use super::variant_tags;
+
+    fn errors(json: &str) -> Vec<String> {
+        let kmod: Module = serde_json::from_str(json).unwrap();
+        return match kmod.resolve() {
+            Ok(_) => Vec::new(),
+            Err(errs) => errs.iter().map(|err| err.to_string()).collect(),
+        };
+    }
+
+    #[test]
+    fn resolves_paths_into_submodules() {
+        let errs = errors(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Module": {"name": "sub", "visibility": "Public", "items": [
+                {"Struct": {"name": "Point", "visibility": "Public", "fields": []}}]}},
+            {"Struct": {"name": "Line", "visibility": "Public", "fields": [
+                {"name": "a", "ty": {"Item": ["p", "sub", "Point"]}}]}}]}"#);
+        assert_eq!(errs, Vec::<String>::new());
+    }
+
+    #[test]
+    fn reports_dangling_and_empty_paths() {
+        let errs = errors(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Struct": {"name": "Line", "visibility": "Public", "fields": [
+                {"name": "a", "ty": {"Vec": {"Item": ["p", "Point"]}}},
+                {"name": "b", "ty": {"Item": []}}]}}]}"#);
+        assert_eq!(errs, vec![
+            "`p::Line` refers to `p::Point` which does not exist".to_string(),
+            "`p::Line` refers to a type with an empty path".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn reports_exported_items_referring_private_items() {
+        let errs = errors(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Module": {"name": "sub", "visibility": "Private", "items": [
+                {"Struct": {"name": "Point", "visibility": "Public", "fields": []}}]}},
+            {"Struct": {"name": "Line", "visibility": "Public", "fields": [
+                {"name": "a", "ty": {"Item": ["p", "sub", "Point"]}}]}},
+            {"Struct": {"name": "Hidden", "visibility": "Private", "fields": [
+                {"name": "a", "ty": {"Item": ["p", "sub", "Point"]}}]}}]}"#);
+        assert_eq!(errs, vec!["`p::Line` refers to `p::sub::Point` which is not exported".to_string()]);
+    }
+
+    #[test]
+    fn reports_duplicate_paths() {
+        let errs = errors(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Struct": {"name": "Point", "visibility": "Public", "fields": []}},
+            {"Enum": {"name": "Point", "visibility": "Public", "variants": []}}]}"#);
+        assert_eq!(errs, vec!["`p::Point` is defined more than once".to_string()]);
+    }
+
+    #[test]
+    fn reports_reuse_of_reserved_names() {
+        let errs = errors(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Struct": {"name": "Point", "visibility": "Public", "reserved": ["z", "r#type"], "fields": [
+                {"name": "x", "ty": {"Core": "I32"}},
+                {"name": "z", "ty": {"Core": "I32"}},
+                {"name": "type", "ty": "String"}]}},
+            {"Enum": {"name": "Shape", "visibility": "Public", "reserved": ["Square"], "variants": [
+                {"name": "Circle", "fields": []},
+                {"name": "Square", "fields": []}]}}]}"#);
+        assert_eq!(errs, vec![
+            "`p::Point` uses reserved name `z`".to_string(),
+            "`p::Point` uses reserved name `type`".to_string(),
+            "`p::Shape` uses reserved name `Square`".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn reports_defaults_of_items_without_defaults() {
+        let errs = errors(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Struct": {"name": "Point", "visibility": "Public", "fields": [
+                {"name": "x", "ty": {"Core": "I32"}, "default": true},
+                {"name": "y", "ty": {"Core": "I32"}}]}},
+            {"Struct": {"name": "Size", "visibility": "Public", "fields": [
+                {"name": "w", "ty": {"Core": "I32"}, "default": true}]}},
+            {"Enum": {"name": "Shape", "visibility": "Public", "variants": []}},
+            {"Struct": {"name": "Rect", "visibility": "Public", "fields": [
+                {"name": "origin", "ty": {"Item": ["p", "Point"]}, "default": true},
+                {"name": "size", "ty": {"Item": ["p", "Size"]}, "default": true},
+                {"name": "shape", "ty": {"Item": ["p", "Shape"]}, "default": true},
+                {"name": "tags", "ty": {"Vec": {"Item": ["p", "Point"]}}, "default": true}]}}]}"#);
+        assert_eq!(errs, vec![
+            "`p::Rect::origin` has a default but `p::Point` has no default".to_string(),
+            "`p::Rect::shape` has a default but `p::Shape` has no default".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn reports_names_which_collide_after_case_conversion() {
+        let kmod: Module = serde_json::from_str(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Struct": {"name": "User", "visibility": "Public", "fields": [
+                {"name": "user_id", "ty": "String"},
+                {"name": "userId", "ty": "String"},
+                {"name": "name", "ty": "String"}]}},
+            {"Enum": {"name": "Event", "visibility": "Private", "variants": [
+                {"name": "Move", "fields": []},
+                {"name": "move_", "fields": []}]}}]}"#).unwrap();
+        // Valid for targets which preserve Rust names.
+        assert!(kmod.resolve().is_ok());
+        let errs = kmod.check_converted_names().unwrap_err().iter().map(|err| err.to_string()).collect::<Vec<String>>();
+        assert_eq!(errs, vec![
+            "`user_id`, `userId` in `p::User` have the same name after case conversion".to_string(),
+            "`Move`, `move_` in `p::Event` have the same name after case conversion".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn reports_variant_classes_named_like_other_classes() {
+        let kmod: Module = serde_json::from_str(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Struct": {"name": "ShapeCircle", "visibility": "Public", "fields": []}},
+            {"Enum": {"name": "Shape", "visibility": "Public", "variants": [
+                {"name": "Circle", "fields": []},
+                {"name": "square", "fields": []}]}},
+            {"Struct": {"name": "Shapesquare", "visibility": "Public", "fields": []}}]}"#).unwrap();
+        // Valid for targets which nest or prefix variants.
+        assert!(kmod.resolve().is_ok());
+        let errs = |naming| kmod.check_variant_classes(naming).unwrap_err().iter().map(|err: &ResolveError| err.to_string()).collect::<Vec<String>>();
+        assert_eq!(errs(Naming::Idiomatic), vec![
+            "`p::ShapeCircle`, `p::Shape::Circle` produce the same class name `ShapeCircle`".to_string(),
+        ]);
+        assert_eq!(errs(Naming::Preserve), vec![
+            "`p::ShapeCircle`, `p::Shape::Circle` produce the same class name `ShapeCircle`".to_string(),
+            "`p::Shape::square`, `p::Shapesquare` produce the same class name `Shapesquare`".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn accepts_tags_outside_reserved_ranges() {
+        assert!(!is_valid_tag(0));
+        assert!(is_valid_tag(1));
+        assert!(!is_valid_tag(19000));
+        assert!(!is_valid_tag(19999));
+        assert!(is_valid_tag(20000));
+        assert!(is_valid_tag(536870911));
+        assert!(!is_valid_tag(536870912));
+    }
+
+    #[test]
+    fn reports_invalid_and_duplicate_tags() {
+        let kmod: Module = serde_json::from_str(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Struct": {"name": "Point", "visibility": "Public", "fields": [
+                {"name": "x", "ty": {"Core": "I32"}, "tag": 1},
+                {"name": "y", "ty": {"Core": "I32"}, "tag": 1},
+                {"name": "w", "ty": {"Core": "I32"}},
+                {"name": "z", "ty": {"Core": "I32"}, "tag": 19001}]}}]}"#).unwrap();
+        // Valid for targets other than Protocol Buffers.
+        assert!(kmod.resolve().is_ok());
+        let errs = kmod.check_tags().unwrap_err().iter().map(|err| err.to_string()).collect::<Vec<String>>();
+        assert_eq!(errs, vec![
+            "tag 1 is used more than once in `p::Point`".to_string(),
+            "`p::Point::z` has invalid tag 19001".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn numbers_untagged_members_with_free_tags() {
+        let kmod: Module = serde_json::from_str(r#"{"name": "p", "visibility": "Public", "items": [
+            {"Struct": {"name": "Point", "visibility": "Public", "fields": [
+                {"name": "a", "ty": {"Core": "I32"}, "tag": 2},
+                {"name": "b", "ty": {"Core": "I32"}},
+                {"name": "c", "ty": {"Core": "I32"}},
+                {"name": "d", "ty": {"Core": "I32"}, "tag": 1}]}},
+            {"Enum": {"name": "Shape", "visibility": "Public", "variants": [
+                {"name": "Dot", "fields": [{"name": "", "ty": {"Core": "I32"}}]},
+                {"name": "Empty", "fields": [], "tag": 1}]}},
+            {"Struct": {"name": "Hidden", "visibility": "Private", "fields": [
+                {"name": "x", "ty": {"Core": "I32"}, "tag": 1},
+                {"name": "y", "ty": {"Core": "I32"}, "tag": 1}]}}]}"#).unwrap();
+        assert!(kmod.resolve().is_ok());
+        assert!(kmod.check_tags().is_ok());
+        let (point, shape) = match (&kmod.items[0], &kmod.items[1]) {
+            (&Item::Struct(ref kstruct), &Item::Enum(ref kenum)) => (kstruct, kenum),
+            _ => panic!(),
+        };
+        assert_eq!(field_tags(&point.fields), vec![2, 3, 4, 1]);
+        assert_eq!(variant_tags(&shape.variants), vec![2, 1]);
+        assert_eq!(field_tags(&shape.variants[0].fields), vec![1]);
+    }
+
+    #[test]
+    fn skips_reserved_numbers_for_untagged_members() {
+        let kfields: Vec<StructField> = serde_json::from_str(r#"[
+            {"name": "a", "ty": {"Core": "I32"}, "tag": 18999},
+            {"name": "b", "ty": {"Core": "I32"}, "tag": 18998},
+            {"name": "c", "ty": {"Core": "I32"}}]"#).unwrap();
+        assert_eq!(field_tags(&kfields), vec![18999, 18998, 1]);
+        assert_eq!(assign_tags(&[Some(1), None, Some(3), None]), vec![1, 2, 3, 4]);
+    }
+}
@@ -1,50 +1,405 @@
 
-use rustc::hir;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
 use rustc::hir::def_id::DefId;
 use rustdoc::clean;
+use syntax::ast;
 use super::Module;
+use super::Item;
+use super::Enum;
+use super::EnumVariant;
+use super::Struct;
+use super::StructField;
+use super::Type;
+use super::CoreType;
+use super::TypePath;
+use super::Visibility;
+use super::Deprecation;
+
+/// An unsupported Rust construct found while scanning.
+#[derive(Debug)]
+pub struct ScanError {
+    /// Path to the item which contains the construct.
+    pub path: Vec<String>,
+    pub message: String,
+}
 
 impl Module {
-    pub fn scan(ccrate: &clean::Crate) -> Module {
-        let id_item_map = collect_defs(ccrate);
-        /*
-        match scan(ccrate) {
-            Ok(ref kmod) => kmod,
+    /// Builds a module tree from a cleaned crate.
+    /// Items other than modules, structs and enums are ignored.
+    pub fn scan(ccrate: &clean::Crate) -> Result<Module, Vec<ScanError>> {
+        let citem = match ccrate.module {
+            Some(ref citem) => citem,
             None => {
-                panic!("Crate module is missing.");
+                let err = ScanError { path: vec![ccrate.name.clone()], message: "crate module is missing".to_string() };
+                return Err(vec![err]);
             },
+        };
+        let mut scanner = Scanner { id_path_map: HashMap::new(), derived_defaults: HashSet::new(), path: Vec::new(), errs: Vec::new() };
+        collect_defs(citem, &mut vec![ccrate.name.clone()], &mut scanner.id_path_map);
+        collect_derived_defaults(citem, &mut scanner.derived_defaults);
+        let mut kmod = Module::new();
+        kmod.name = ccrate.name.clone();
+        if let clean::ModuleItem(ref cmod) = citem.inner {
+            scanner.path.push(ccrate.name.clone());
+            kmod.items = scanner.scan_items(&cmod.items);
         }
-        //println!("{:#?}", krate);
-        */
+        if scanner.errs.len() > 0 {
+            return Err(scanner.errs);
+        }
+        return Ok(kmod);
     }
 }
 
-fn collect_defs(ccrate: &clean::Crate) -> HashMap<DefId,&Item> {
-    if let Some(ref cmod) = ccrate.module {
-        return collect_defs(cmod);
+/// Collects fully qualified paths of all structs and enums
+/// defined in the crate. Type references in clean AST keep only
+/// path segments as written, so they are resolved by `DefId`
+/// with this map.
+fn collect_defs(citem: &clean::Item, path: &mut Vec<String>, id_path_map: &mut HashMap<DefId, Vec<String>>) {
+    let cmod = match citem.inner {
+        clean::ModuleItem(ref cmod) => cmod,
+        _ => return,
+    };
+    for citem1 in &cmod.items {
+        let name = match citem1.name {
+            Some(ref name) => name.clone(),
+            None => continue,
+        };
+        match citem1.inner {
+            clean::ModuleItem(_) => {
+                path.push(name);
+                collect_defs(citem1, path, id_path_map);
+                path.pop();
+            },
+            clean::StructItem(_) | clean::EnumItem(_) => {
+                let mut item_path = path.clone();
+                item_path.push(name);
+                id_path_map.insert(citem1.def_id, item_path);
+            },
+            _ => {},
+        }
     }
-    return HashMap::new();
 }
-fn collect_defs(citem: &clean::Item) -> HashMap<DefId,&Item> {
-       
+
+/// Collects types with `#[derive(Default)]`.
+/// Manual `Default` impls are not included because their values
+/// are unknown.
+fn collect_derived_defaults(citem: &clean::Item, ids: &mut HashSet<DefId>) {
+    let cmod = match citem.inner {
+        clean::ModuleItem(ref cmod) => cmod,
+        _ => return,
+    };
+    for citem1 in &cmod.items {
+        match citem1.inner {
+            clean::ModuleItem(_) => collect_derived_defaults(citem1, ids),
+            clean::ImplItem(ref cimpl) => {
+                let derived = citem1.attrs.other_attrs.iter().any(|attr| attr.check_name("automatically_derived"));
+                if !derived { continue }
+                let is_default = match cimpl.trait_ {
+                    Some(clean::ResolvedPath { ref path, .. }) => {
+                        path.segments.last().map(|s| s.name == "Default").unwrap_or(false)
+                    },
+                    _ => false,
+                };
+                if !is_default { continue }
+                if let clean::ResolvedPath { ref did, .. } = cimpl.for_ {
+                    ids.insert(*did);
+                }
+            },
+            _ => {},
+        }
+    }
 }
 
-/*
-fn scan(ccrate: &clean::Crate) -> Option<Module> {
-    let mut kmod = Module::new();
-    println!();
-    let cmod = &ccrate.module?;
-    match cmod.inner {
-        StructItem(ref cstruct) => {
-        },
-        EnumItem(ref cenum) => {
-        },
-        ModuleItem(ref cmod) => {
-        },
-        _,
+struct Scanner {
+    id_path_map: HashMap<DefId, Vec<String>>,
+    derived_defaults: HashSet<DefId>,
+    /// Path to current item.
+    path: Vec<String>,
+    errs: Vec<ScanError>,
+}
+
+impl Scanner {
+    fn error(&mut self, message: String) {
+        self.errs.push(ScanError { path: self.path.clone(), message: message });
+    }
+
+    fn scan_items(&mut self, citems: &[clean::Item]) -> Vec<Item> {
+        let mut kitems = Vec::<Item>::new();
+        for citem in citems {
+            let name = match citem.name {
+                Some(ref name) => name.clone(),
+                None => continue,
+            };
+            self.path.push(name.clone());
+            let visibility = scan_visibility(citem);
+            match citem.inner {
+                clean::ModuleItem(ref cmod) => {
+                    let mut kmod = Module::new();
+                    kmod.name = name;
+                    kmod.visibility = visibility;
+                    kmod.items = self.scan_items(&cmod.items);
+                    kitems.push(Item::Module(kmod));
+                },
+                clean::StructItem(ref cstruct) => {
+                    let mut kstruct = Struct::new();
+                    kstruct.name = name;
+                    kstruct.visibility = visibility;
+                    kstruct.deprecation = scan_deprecation(citem);
+                    kstruct.reserved = self.scan_reserved(citem);
+                    kstruct.fields = self.scan_struct(citem, cstruct);
+                    kitems.push(Item::Struct(kstruct));
+                },
+                clean::EnumItem(ref cenum) => {
+                    let mut kenum = Enum::new();
+                    kenum.name = name;
+                    kenum.visibility = visibility;
+                    kenum.deprecation = scan_deprecation(citem);
+                    kenum.reserved = self.scan_reserved(citem);
+                    kenum.variants = self.scan_enum(cenum);
+                    kitems.push(Item::Enum(kenum));
+                },
+                _ => {},
+            }
+            self.path.pop();
+        }
+        return kitems;
+    }
+
+    fn scan_struct(&mut self, citem: &clean::Item, cstruct: &clean::Struct) -> Vec<StructField> {
+        if cstruct.generics.params.len() > 0 {
+            self.error("generic structs are not supported".to_string());
+        }
+        // Tuple struct fields are named by their indices.
+        let is_tuple = cstruct.fields.iter().any(|citem| {
+            return citem.name.as_ref().map(|name| name.parse::<usize>().is_ok()).unwrap_or(false);
+        });
+        if is_tuple {
+            self.error("tuple structs are not supported".to_string());
+            return Vec::new();
+        }
+        // `#[derive(Default)]` gives every field the default of
+        // its type. `#[serde(default)]` on a struct needs the same.
+        let derived = self.derived_defaults.contains(&citem.def_id);
+        if self.scan_serde_default(citem) && !derived {
+            self.error("`#[serde(default)]` on a struct requires `#[derive(Default)]`".to_string());
+        }
+        return self.scan_fields(&cstruct.fields, derived);
+    }
+
+    fn scan_enum(&mut self, cenum: &clean::Enum) -> Vec<EnumVariant> {
+        if cenum.generics.params.len() > 0 {
+            self.error("generic enums are not supported".to_string());
+        }
+        let mut kvariants = Vec::<EnumVariant>::new();
+        for citem in &cenum.variants {
+            let cvariant = match citem.inner {
+                clean::VariantItem(ref cvariant) => cvariant,
+                _ => continue,
+            };
+            let mut kvariant = EnumVariant::new();
+            kvariant.name = citem.name.clone().unwrap_or(String::new());
+            kvariant.deprecation = scan_deprecation(citem);
+            self.path.push(kvariant.name.clone());
+            kvariant.tag = self.scan_tag(citem);
+            match cvariant.kind {
+                clean::VariantKind::CLike => {},
+                clean::VariantKind::Tuple(ref ctys) => {
+                    for cty in ctys {
+                        if let Some(ty) = self.scan_type(cty) {
+                            kvariant.fields.push(StructField { name: String::new(), ty: ty, default: false, deprecation: None, tag: None });
+                        }
+                    }
+                },
+                clean::VariantKind::Struct(ref cvstruct) => {
+                    kvariant.fields = self.scan_fields(&cvstruct.fields, false);
+                },
+            }
+            self.path.pop();
+            kvariants.push(kvariant);
+        }
+        return kvariants;
+    }
+
+    /// `default` is applied to all fields. A field can also have
+    /// its own `#[serde(default)]`.
+    fn scan_fields(&mut self, citems: &[clean::Item], default: bool) -> Vec<StructField> {
+        let mut kfields = Vec::<StructField>::new();
+        for citem in citems {
+            if let clean::StructFieldItem(ref cty) = citem.inner {
+                let name = citem.name.clone().unwrap_or(String::new());
+                self.path.push(name.clone());
+                let default = self.scan_serde_default(citem) || default;
+                if let Some(ty) = self.scan_type(cty) {
+                    let tag = self.scan_tag(citem);
+                    kfields.push(StructField { name: name, ty: ty, default: default, deprecation: scan_deprecation(citem), tag: tag });
+                }
+                self.path.pop();
+            }
+        }
+        return kfields;
     }
+
+    fn scan_type(&mut self, cty: &clean::Type) -> Option<Type> {
+        match cty {
+            clean::Primitive(ref cprim) => {
+                use rustdoc::clean::PrimitiveType as P;
+                let core_type = match cprim {
+                    P::Bool => CoreType::Bool,
+                    P::U8 => CoreType::U8,
+                    P::U16 => CoreType::U16,
+                    P::U32 => CoreType::U32,
+                    P::U64 => CoreType::U64,
+                    P::I8 => CoreType::I8,
+                    P::I16 => CoreType::I16,
+                    P::I32 => CoreType::I32,
+                    P::I64 => CoreType::I64,
+                    P::F32 => CoreType::F32,
+                    P::F64 => CoreType::F64,
+                    _ => {
+                        self.error(format!("primitive type `{}` is not supported", cprim.as_str()));
+                        return None;
+                    },
+                };
+                return Some(Type::Core(core_type));
+            },
+            clean::ResolvedPath { ref path, ref did, .. } => {
+                if let Some(kpath) = self.id_path_map.get(did) {
+                    return Some(Type::Item(TypePath(kpath.clone())));
+                }
+                let csegment = match path.segments.last() {
+                    Some(csegment) => csegment,
+                    None => {
+                        self.error("type with empty path".to_string());
+                        return None;
+                    },
+                };
+                return match csegment.name.as_str() {
+                    "String" => Some(Type::String),
+                    "Option" => self.scan_type_arg(csegment).map(|ty| Type::Option(Box::new(ty))),
+                    "Vec" => self.scan_type_arg(csegment).map(|ty| Type::Vec(Box::new(ty))),
+                    // Items of other crates. Resolver will report these.
+                    _ => {
+                        let names = path.segments.iter().map(|s| s.name.clone()).collect();
+                        Some(Type::Item(TypePath(names)))
+                    },
+                };
+            },
+            _ => {
+                self.error(format!("type `{:?}` is not supported", cty));
+                return None;
+            },
+        }
+    }
+
+    /// Checks `#[serde(default)]`.
+    /// `#[serde(default = "path")]` calls a function which cannot
+    /// be exported, so it is an error.
+    fn scan_serde_default(&mut self, citem: &clean::Item) -> bool {
+        let mut r = false;
+        for attr in &citem.attrs.other_attrs {
+            if !attr.check_name("serde") { continue }
+            let cmetas = match attr.meta_item_list() {
+                Some(cmetas) => cmetas,
+                None => continue,
+            };
+            for cmeta in &cmetas {
+                if !cmeta.check_name("default") { continue }
+                if cmeta.is_word() {
+                    r = true;
+                }
+                else {
+                    self.error("`#[serde(default = \"...\")]` is not supported".to_string());
+                }
+            }
+        }
+        return r;
+    }
+
+    /// Reads `#[igen(reserved(name1, name2, ...))]`.
+    fn scan_reserved(&mut self, citem: &clean::Item) -> Vec<String> {
+        let mut names = Vec::<String>::new();
+        for attr in &citem.attrs.other_attrs {
+            if !attr.check_name("igen") { continue }
+            let cmetas = match attr.meta_item_list() {
+                Some(cmetas) => cmetas,
+                None => continue,
+            };
+            for cmeta in &cmetas {
+                if !cmeta.check_name("reserved") { continue }
+                let cmetas1 = cmeta.meta_item_list().unwrap_or(&[]);
+                if cmetas1.len() == 0 {
+                    self.error("`reserved` requires a list of names".to_string());
+                }
+                for cmeta1 in cmetas1 {
+                    match cmeta1.name() {
+                        Some(ref name) if cmeta1.is_word() => names.push(name.to_string()),
+                        _ => self.error("`reserved` accepts only names".to_string()),
+                    }
+                }
+            }
+        }
+        return names;
+    }
+
+    /// Reads `#[igen(tag = N)]`.
+    fn scan_tag(&mut self, citem: &clean::Item) -> Option<u32> {
+        let mut tag = None;
+        for attr in &citem.attrs.other_attrs {
+            if !attr.check_name("igen") { continue }
+            let cmetas = match attr.meta_item_list() {
+                Some(cmetas) => cmetas,
+                None => continue,
+            };
+            for cmeta in &cmetas {
+                if !cmeta.check_name("tag") { continue }
+                let value = cmeta.meta_item().and_then(|cmeta1| match cmeta1.node {
+                    ast::MetaItemKind::NameValue(ref clit) => match clit.node {
+                        ast::LitKind::Int(n, _) if n > 0 && n <= u32::max_value() as u128 => Some(n as u32),
+                        _ => None,
+                    },
+                    _ => None,
+                });
+                match value {
+                    Some(n) => tag = Some(n),
+                    None => self.error("`tag` requires a positive integer".to_string()),
+                }
+            }
+        }
+        return tag;
+    }
+
+    /// Scans the only type argument of a generic type like `Vec<T>`.
+    fn scan_type_arg(&mut self, csegment: &clean::PathSegment) -> Option<Type> {
+        if let clean::GenericArgs::AngleBracketed { ref types, .. } = csegment.args {
+            if types.len() == 1 {
+                return self.scan_type(&types[0]);
+            }
+        }
+        self.error(format!("`{}` requires exactly one type argument", csegment.name));
+        return None;
+    }
+}
+
+fn scan_visibility(citem: &clean::Item) -> Visibility {
+    return match citem.visibility {
+        Some(clean::Visibility::Public) => Visibility::Public,
+        _ => Visibility::Private,
+    };
 }
 
-fn scan(citem: &clean::Item) -> Item {
+/// Empty strings in clean AST mean missing values.
+fn scan_deprecation(citem: &clean::Item) -> Option<Deprecation> {
+    return citem.deprecation.as_ref().map(|cdepr| {
+        let since = if cdepr.since.is_empty() { None } else { Some(cdepr.since.clone()) };
+        let note = if cdepr.note.is_empty() { None } else { Some(cdepr.note.clone()) };
+        return Deprecation { since: since, note: note };
+    });
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "`{}`: {}", self.path.join("::"), self.message)
+    }
 }
-*/
@@ -0,0 +1,118 @@
+
+//! `--watch` mode.
+//!
+//! Polls modification times of `.rs` files under directory of
+//! each input crate root, and regenerates code for inputs whose
+//! sources changed.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+use std::time::SystemTime;
+use errors::Handler;
+use getopts;
+use config;
+use driver;
+
+const POLL_INTERVAL_MS: u64 = 500;
+
+/// Source file paths and their modification times.
+type Stamp = BTreeMap<PathBuf, SystemTime>;
+
+/// Runs forever until the process is killed.
+pub fn watch(matches: &getopts::Matches, config: &config::Config, diag: &Handler) -> isize {
+    let mut stamps = config.inputs.iter().map(|_| None).collect::<Vec<Option<Stamp>>>();
+    loop {
+        for (i, input) in config.inputs.iter().enumerate() {
+            let dir = input.path.parent().unwrap_or(Path::new(".")).to_path_buf();
+            let stamp = scan_sources(&dir);
+            if stamps[i].as_ref() == Some(&stamp) { continue }
+            stamps[i] = Some(stamp);
+            eprintln!("generating from `{}`", input.path.display());
+            let mut config1 = config.clone();
+            config1.inputs = vec![input.clone()];
+            regenerate(matches, &config1, diag);
+        }
+        thread::sleep(Duration::from_millis(POLL_INTERVAL_MS));
+    }
+}
+
+fn regenerate(matches: &getopts::Matches, config: &config::Config, diag: &Handler) {
+    let generated = match driver::run(matches, config) {
+        Ok(generated) => generated,
+        Err(err) => {
+            diag.struct_err(&err.to_string()).emit();
+            return;
+        },
+    };
+    for warning in &generated.warnings {
+        diag.struct_warn(warning).emit();
+    }
+    match generated.write() {
+        Ok(paths) => {
+            for path in paths {
+                eprintln!("wrote `{}`", path.display());
+            }
+        },
+        Err(err) => {
+            diag.struct_err(&err.to_string()).emit();
+        },
+    }
+    for file in &generated.files {
+        if file.path.is_none() {
+            println!("{}", file.code);
+        }
+    }
+}
+
+fn scan_sources(dir: &Path) -> Stamp {
+    let mut stamp = Stamp::new();
+    collect_sources(dir, &mut stamp);
+    return stamp;
+}
+fn collect_sources(dir: &Path, stamp: &mut Stamp) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(_) => continue,
+        };
+        if path.is_dir() {
+            collect_sources(&path, stamp);
+            continue;
+        }
+        if path.extension().map(|ext| ext == "rs").unwrap_or(false) {
+            if let Ok(time) = fs::metadata(&path).and_then(|m| m.modified()) {
+                stamp.insert(path, time);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use super::scan_sources;
+
+    #[test]
+    fn stamps_rust_sources_in_all_subdirectories() {
+        let dir = env::temp_dir().join("mgen-watch-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("messages")).unwrap();
+        fs::write(dir.join("lib.rs"), "pub mod messages;").unwrap();
+        fs::write(dir.join("messages/mod.rs"), "").unwrap();
+        fs::write(dir.join("notes.txt"), "").unwrap();
+        let stamp = scan_sources(&dir);
+        assert_eq!(stamp.keys().cloned().collect::<Vec<PathBuf>>(), vec![dir.join("lib.rs"), dir.join("messages/mod.rs")]);
+        fs::write(dir.join("messages/ping.rs"), "").unwrap();
+        assert!(scan_sources(&dir) != stamp);
+    }
+}